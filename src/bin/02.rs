@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use aoc2019::goal_seek::{self, Location, Observation, Unknown};
+
 #[derive(PartialEq, Eq)]
 enum Opcode {
     Add,
@@ -94,15 +96,16 @@ fn compute(computer: &Computer, noun: u32, verb: u32) -> u32 {
 }
 
 fn part_two(input: &str) -> u32 {
-    let computer = parse_computer_string(input);
-    for noun in 0..=99 {
-        for verb in 0..=99 {
-            if compute(&computer, noun, verb) == 19690720 {
-                return 100 * noun + verb;
-            }
-        }
-    }
-    panic!("bug");
+    let program = aoc2019::intcode::Computer::parse(input);
+    let unknowns = [
+        Unknown::new(Location::Memory(1), 0..=99),
+        Unknown::new(Location::Memory(2), 0..=99),
+    ];
+    let solution = goal_seek::solve(&program, &unknowns, Observation::Memory(0), 19690720)
+        .unwrap()
+        .expect("no noun and verb produce the target");
+    let (noun, verb) = (solution.values[0], solution.values[1]);
+    (100 * noun + verb).try_into().unwrap()
 }
 
 fn main() {
@@ -153,6 +156,14 @@ mod tests {
         assert!(computer.done());
     }
 
+    #[test]
+    fn test_part_two_matches_local_computer() {
+        let input = include_str!("../inputs/02.txt").trim();
+        let two = part_two(input);
+        let computer = parse_computer_string(input);
+        assert_eq!(compute(&computer, two / 100, two % 100), 19690720);
+    }
+
     #[test]
     fn test_main() {
         main();
@@ -1,105 +1,47 @@
-use std::{collections::HashMap, io};
-
-// Parse the input string into a vector of tuples (String, String).  Each input
-// line is parsed as two integral numbers separated by a ')' character.  The
-// first number is the parent and the second the child.
-fn parse(input: &str) -> Result<Vec<(String, String)>, io::Error> {
-    let mut result = Vec::new();
-    for line in input.trim().lines() {
-        if let Some((parent, child)) = line.split_once(')') {
-            result.push((parent.to_string(), child.to_string()));
-        } else {
-            // Handle the error case where line.split_once(')') returns None
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Invalid input format",
-            ));
-        }
-    }
-    Ok(result)
+use anyhow::{Result, anyhow, bail};
+use aoc2019::tree::Tree;
+
+// Parse the input string into a tree of orbits.  Each input line is parsed as
+// two names separated by a ')' character.  The first name is the primary
+// (parent) and the second the secondary (child) that orbits it.
+fn parse(input: &str) -> Result<Tree<&str>> {
+    let pairs = input
+        .trim()
+        .lines()
+        .map(|line| {
+            line.split_once(')')
+                .ok_or_else(|| anyhow!("invalid input format: {:?}", line))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Tree::from_pairs(pairs)
 }
 
+// Every object orbits each of its ancestors, directly or indirectly.
 fn part_one(input: &str) -> u32 {
-    let orbits = parse(input).expect("invalid input");
-
-    // direct_orbits is a hash map from primary planets to the secondary planets
-    // that directly orbit them.
-    let mut direct_orbits = HashMap::new();
-    for (primary, secondary) in &orbits {
-        direct_orbits
-            .entry(primary.clone())
-            .or_insert(Vec::new())
-            .push(secondary.clone())
-    }
-
-    struct Frontier {
-        orbit_count: u32,
-        name: String,
-    }
-
-    // Declare a queue of Frontier objects and begin the breadth first traversal
-    // with the "COM" planet.
-    let mut queue = Vec::new();
-    queue.push(Frontier {
-        orbit_count: 0,
-        name: "COM".to_string(),
-    });
-
-    // Count all orbits in the system.
-    let mut total_orbit_count = 0;
-
-    while let Some(primary) = queue.pop() {
-        total_orbit_count += primary.orbit_count;
-        if let Some(secondaries) = direct_orbits.get(&primary.name) {
-            for secondary in secondaries {
-                queue.push(Frontier {
-                    orbit_count: primary.orbit_count + 1,
-                    name: secondary.clone(),
-                })
-            }
-        }
-    }
-
-    total_orbit_count
+    parse(input).expect("invalid input").sum_of_depths() as u32
 }
 
-fn primaries_from(primaries: &HashMap<String, String>, secondary: &str) -> Vec<String> {
-    let mut result = Vec::new();
-    let mut curr = secondary;
-    while let Some(next) = primaries.get(curr) {
-        result.push(next.clone());
-        curr = next;
+// Transfers are needed between the objects YOU and SAN orbit, which are one
+// step short of each end of the path between them.  If one orbits the other,
+// even indirectly, there is no such path.
+fn part_two(input: &str) -> Result<u32> {
+    let tree = parse(input)?;
+    let (you, san) = (&"YOU", &"SAN");
+    let ancestor = tree
+        .lowest_common_ancestor(you, san)
+        .ok_or_else(|| anyhow!("YOU and SAN are not both in the map"))?;
+    if ancestor == you || ancestor == san {
+        bail!("one of YOU and SAN orbits the other");
     }
-    result
-}
-
-fn part_two(input: &str) -> u32 {
-    let orbits = parse(input).expect("invalid input");
-
-    let mut primaries = HashMap::new();
-    for (primary, secondary) in &orbits {
-        primaries.insert(secondary.clone(), primary.clone());
-    }
-
-    let you_path = primaries_from(&primaries, "YOU");
-    let san_path = primaries_from(&primaries, "SAN");
-
-    let mut common_suffix = 0;
-    for (a, b) in std::iter::zip(you_path.iter().rev(), san_path.iter().rev()) {
-        if a != b {
-            break;
-        }
-        common_suffix += 1;
-    }
-
-    let orbital_transfer = you_path.len() + san_path.len() - 2 * common_suffix;
-    orbital_transfer as u32
+    // Neither is the common ancestor, so each is at least one step below it.
+    let distance = tree.distance(you, san).unwrap();
+    Ok((distance - 2) as u32)
 }
 
 fn main() {
     let input = include_str!("../inputs/06.txt").trim();
     let one = part_one(input);
-    let two = part_two(input);
+    let two = part_two(input).expect("invalid input");
     assert_eq!(one, 333679);
     assert_eq!(two, 370);
 }
@@ -117,7 +59,15 @@ mod tests {
     #[test]
     fn test_part_two() {
         let input = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN";
-        assert_eq!(part_two(input), 4);
+        assert_eq!(part_two(input).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_part_two_direct_orbit() {
+        let input = "COM)B\nB)YOU\nYOU)SAN";
+        assert!(part_two(input).is_err());
+        let input = "COM)SAN\nSAN)B\nB)C\nC)YOU";
+        assert!(part_two(input).is_err());
     }
 
     #[test]
@@ -8,6 +8,7 @@ fn run_computer(computer: &Computer, phase: i64, signal: i64, _trace: bool) -> i
         RunState::Finished => panic!("computer error: finished prematurely"),
         RunState::BlockedOnInput => panic!("computer error: blocked on input unexpectedly"),
         RunState::BlockedOnOutput => computer.take_output().unwrap(),
+        RunState::Trapped => unreachable!("no extensions are registered"),
     }
 }
 
@@ -71,6 +72,7 @@ fn max_thruster_signal2(program_text: &str) -> i64 {
                     RunState::Finished => {
                         break 'until_finished;
                     }
+                    RunState::Trapped => unreachable!("no extensions are registered"),
                 }
             }
         }
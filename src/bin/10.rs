@@ -1,199 +1,55 @@
-use ordered_float::OrderedFloat;
-use std::{
-    collections::BTreeMap,
-    ops::{Add, Div, Mul, Sub},
-};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub struct Point {
-    pub x: i32,
-    pub y: i32,
-}
-
-impl std::fmt::Display for Point {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({}, {})", self.x, self.y)
-    }
-}
-
-impl Add for Point {
-    type Output = Self;
-
-    fn add(self, other: Self) -> Self {
-        Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
-    }
-}
+use aoc2019::point::{Grid, Point2D, Rect, YAxis};
 
-impl Sub for Point {
-    type Output = Self;
-
-    fn sub(self, other: Self) -> Self {
-        Self {
-            x: self.x - other.x,
-            y: self.y - other.y,
-        }
-    }
-}
+type Point = Point2D<i32>;
 
 fn point(x: i32, y: i32) -> Point {
     Point { x, y }
 }
 
-impl Mul<i32> for Point {
-    type Output = Self;
-
-    fn mul(self, scalar: i32) -> Self {
-        Self {
-            x: self.x * scalar,
-            y: self.y * scalar,
-        }
-    }
-}
-
-impl Div<i32> for Point {
-    type Output = Self;
-
-    fn div(self, scalar: i32) -> Self {
-        Self {
-            x: self.x / scalar,
-            y: self.y / scalar,
-        }
-    }
-}
-
-fn distance(p1: Point, p2: Point) -> f32 {
-    let dx = (p2.x - p1.x) as f32;
-    let dy = (p2.y - p1.y) as f32;
-
-    // Calculate the distance between the two points.
-    (dx * dx + dy * dy).sqrt()
-}
-
-#[derive(PartialEq, Clone)]
-struct BoolGrid {
-    grid: Vec<Vec<bool>>,
-}
-
-impl BoolGrid {
-    fn new(grid: Vec<Vec<bool>>) -> BoolGrid {
-        if grid.is_empty() {
-            panic!("empty grids are invalid")
-        }
-        if grid.iter().any(|v| v.is_empty()) {
-            panic!("empty grid rows are invalid")
-        }
-        if grid.iter().any(|v| v.len() != grid[0].len()) {
-            panic!("grid rows of unequal length are invalid")
-        }
-        BoolGrid { grid }
-    }
-
-    fn height(&self) -> i32 {
-        self.grid.len() as i32
-    }
-
-    fn width(&self) -> i32 {
-        self.grid[0].len() as i32
-    }
-
-    fn get(&self, p: Point) -> bool {
-        self.grid[p.y as usize][p.x as usize]
-    }
-
-    fn clear(&mut self, p: Point) {
-        self.grid[p.y as usize][p.x as usize] = false;
-    }
-}
-
-impl std::fmt::Debug for BoolGrid {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        write!(f, "BoolGrid {{")?;
-        for row in self.grid.iter() {
-            for cell in row.iter() {
-                write!(
-                    f,
-                    "{}",
-                    match cell {
-                        true => '#',
-                        false => '.',
-                    }
-                )?;
-            }
-            write!(f, " ")?;
-        }
-        write!(f, "}}")?;
-        Ok(())
-    }
-}
+type BoolGrid = Grid<bool>;
 
-fn parse_line(line: &str) -> Vec<bool> {
-    line.trim()
-        .chars()
-        .map(|char| match char {
-            '#' => true,
-            '.' => false,
-            _ => panic!("Invalid character in input string: {}", char),
-        })
-        .collect()
+/// The rectangle covering the whole grid.
+fn rect(grid: &BoolGrid) -> Rect<i32> {
+    let width = grid.width() as i32;
+    let height = grid.height() as i32;
+    Rect::new(point(0, 0), point(width - 1, height - 1))
 }
 
 fn parse_grid(string: &str) -> BoolGrid {
-    BoolGrid::new(string.trim().split('\n').map(parse_line).collect())
-}
-
-fn gcd(a: i32, b: i32) -> i32 {
-    if a == 0 {
-        b.abs()
-    } else if b == 0 {
-        a.abs()
-    } else {
-        gcd(b % a.abs(), a.abs())
+    let lines: Vec<&str> = string.trim().lines().map(str::trim).collect();
+    let grid = Grid::parse(&lines.join("\n"), |ch| match ch {
+        '#' => Some(true),
+        '.' => Some(false),
+        _ => None,
+    })
+    .unwrap_or_else(|e| panic!("invalid grid: {}", e));
+    if grid.width() == 0 {
+        panic!("empty grids are invalid")
     }
+    grid
 }
 
-fn erase_line(grid: &mut BoolGrid, start: Point, exemplar: Point) {
-    let delta = exemplar - start;
-    let gcd = gcd(delta.x, delta.y);
-    let step = delta / gcd;
-    let mut curr = start;
-    while curr.x >= 0 && curr.x < grid.width() && curr.y >= 0 && curr.y < grid.height() {
-        grid.clear(curr);
-        curr = curr + step
-    }
+/// The asteroids in the grid, other than `center`.
+fn asteroids(grid: &BoolGrid, center: Point) -> impl Iterator<Item = Point> + '_ {
+    rect(grid).points().filter(move |&p| p != center && grid[p])
 }
 
-fn count_asteroids(center: Point, mut grid: BoolGrid) -> i32 {
-    grid.clear(center);
-    let mut sum = 0;
-    for x in 0..grid.width() {
-        for y in 0..grid.height() {
-            let p = Point { x, y };
-            if !grid.get(p) {
-                continue;
-            }
-            sum += 1;
-            erase_line(&mut grid, center, p);
-        }
-    }
-    sum
+fn count_asteroids(center: Point, grid: &BoolGrid) -> i32 {
+    // Asteroids in the same direction hide each other, so count directions.
+    let directions: HashSet<Point> = asteroids(grid, center)
+        .map(|p| (p - center).primitive_direction())
+        .collect();
+    directions.len() as i32
 }
 
 fn part_one_counts(grid: &BoolGrid) -> Vec<Vec<i32>> {
-    let mut counts: Vec<Vec<i32>> = Vec::new();
-    for y in 0..grid.height() {
-        counts.push(Vec::new());
-        for x in 0..grid.width() {
-            let p = Point { x, y };
-            let count = if grid.get(p) {
-                count_asteroids(p, grid.clone())
-            } else {
-                0
-            };
-            counts[y as usize].push(count)
-        }
+    let rect = rect(grid);
+    let mut counts = vec![vec![0; rect.width() as usize]; rect.height() as usize];
+    for p in rect.points().filter(|&p| grid[p]) {
+        counts[p.y as usize][p.x as usize] = count_asteroids(p, grid);
     }
     counts
 }
@@ -209,18 +65,6 @@ pub fn part_one(input: &str) -> i32 {
     part_one_best_count(input)
 }
 
-fn angle(p1: Point, p2: Point) -> f32 {
-    let vector = p2 - p1;
-    let vector = point(-vector.y, vector.x);
-
-    let radians = match (vector.y as f32).atan2(vector.x as f32) {
-        x if x < 0.0 => x + 2.0 * std::f32::consts::PI,
-        x => x,
-    };
-
-    radians * 180.0 / std::f32::consts::PI
-}
-
 fn part_two_compute(input: &str) -> Point {
     let grid = parse_grid(input);
     let counts = part_one_counts(&grid);
@@ -238,30 +82,26 @@ fn part_two_compute(input: &str) -> Point {
         }
     }
 
-    type Distances = BTreeMap<OrderedFloat<f32>, Point>;
-    type Angles = BTreeMap<OrderedFloat<f32>, Distances>;
-
-    let mut angles: Angles = BTreeMap::new();
-
-    for y in 0..grid.height() {
-        for x in 0..grid.width() {
-            let p = Point { x, y };
-            if p == max_point || !grid.get(p) {
-                continue;
-            }
-            let angle = angle(max_point, p);
-            let distance = distance(max_point, p);
-            angles
-                .entry(OrderedFloat(angle))
-                .or_default()
-                .insert(OrderedFloat(distance), p);
-        }
+    // Group the asteroids by direction from the station, each group
+    // ordered farthest first so the nearest can be popped off the end.
+    let mut rays: HashMap<Point, Vec<Point>> = HashMap::new();
+    for p in asteroids(&grid, max_point) {
+        rays.entry((p - max_point).primitive_direction())
+            .or_default()
+            .push(p);
+    }
+    for ray in rays.values_mut() {
+        ray.sort_by_key(|p| Reverse(p.manhattan_distance(max_point)));
     }
 
+    // The laser starts pointing up and rotates clockwise.
+    let mut directions: Vec<Point> = rays.keys().copied().collect();
+    directions.sort_by(|a, b| a.clockwise_cmp(b, point(0, -1), YAxis::Down));
+
     let mut count = 0;
     while count < 200 {
-        for distances in angles.values_mut() {
-            if let Some((_, point)) = distances.pop_first() {
+        for direction in directions.iter() {
+            if let Some(point) = rays.get_mut(direction).unwrap().pop() {
                 count += 1;
                 if count == 200 {
                     return point;
@@ -288,8 +128,6 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use float_eq::assert_float_eq;
-
     use super::*;
 
     #[test]
@@ -297,12 +135,13 @@ mod tests {
         let input = " .. \n## \n#.\n.#\n  \n";
         assert_eq!(
             parse_grid(input),
-            BoolGrid::new(vec![
+            Grid::from_rows(vec![
                 vec![false, false],
                 vec![true, true],
                 vec![true, false],
                 vec![false, true]
             ])
+            .unwrap()
         );
     }
 
@@ -350,19 +189,10 @@ mod tests {
     }
 
     #[test]
-    fn test_angle() {
-        assert_float_eq!(
-            angle(Point { x: 26, y: 28 }, Point { x: 26, y: 9 }),
-            0.0,
-            abs <= f32::EPSILON
-        );
-        assert_float_eq!(
-            angle(Point { x: 26, y: 28 }, Point { x: 5, y: 28 }),
-            270.0,
-            abs <= f32::EPSILON
-        );
-        assert_float_eq!(angle(point(0, 0), point(1, -1)), 45.0, abs <= f32::EPSILON);
-        assert_float_eq!(angle(point(1, 2), point(2, 1)), 45.0, abs <= f32::EPSILON);
+    fn test_count_asteroids() {
+        let grid = parse_grid("#.#.#\n.....\n#.#.#");
+        assert_eq!(count_asteroids(point(0, 0), &grid), 4);
+        assert_eq!(count_asteroids(point(2, 0), &grid), 5);
     }
 
     #[test]
@@ -390,8 +220,8 @@ mod tests {
 ###.##.####.##.#..##
 "#;
         let grid = parse_grid(input);
-        assert!(!grid.get(point(12, 0)));
-        assert!(grid.get(point(12, 1)));
+        assert!(!grid[point(12, 0)]);
+        assert!(grid[point(12, 1)]);
         assert_eq!(part_two(input), 802);
     }
 
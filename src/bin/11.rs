@@ -1,36 +1,9 @@
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
-use std::ops::Add;
-
 use anyhow::bail;
 use anyhow::Result;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub struct Point {
-    pub x: i32,
-    pub y: i32,
-}
-
-impl std::fmt::Display for Point {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({}, {})", self.x, self.y)
-    }
-}
-
-impl Add for Point {
-    type Output = Self;
-
-    fn add(self, other: Self) -> Self {
-        Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
-    }
-}
+use aoc2019::point::{CardinalDirection, Point2D, SparseGrid, Turn, Turtle};
 
-fn point(x: i32, y: i32) -> Point {
-    Point { x, y }
-}
+type Point = Point2D<i32>;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum Opcode {
@@ -405,30 +378,14 @@ enum Color {
     White,
 }
 
-fn compute(input: &str, default_color: Color) -> HashMap<Point, Color> {
+fn compute(input: &str, default_color: Color) -> SparseGrid<Color> {
     let mut computer = parse_program(input);
 
-    #[derive(Copy, Clone, Debug)]
-    enum CardinalDirection {
-        North,
-        South,
-        East,
-        West,
-    }
-
-    #[derive(Copy, Clone, Debug)]
-    enum Direction {
-        Left,
-        Right,
-    }
-
-    let mut position = point(0, 0);
-    let mut direction = CardinalDirection::North;
-    let mut panel: HashMap<Point, Color> = HashMap::new();
+    let mut robot = Turtle::new(Point::new(0, 0), CardinalDirection::North);
+    let mut panel = SparseGrid::new(default_color);
 
     while !computer.finished {
-        let input_color = panel.get(&position).unwrap_or(&default_color);
-        computer.input = match *input_color {
+        computer.input = match *panel.get(robot.position()) {
             Color::Black => 0,
             Color::White => 1,
         };
@@ -440,78 +397,40 @@ fn compute(input: &str, default_color: Color) -> HashMap<Point, Color> {
             break;
         }
 
-        let (output_color, direction_change) = match computer.output[..] {
+        let (output_color, turn) = match computer.output[..] {
             [color_number, direction_number] => {
                 let color = match color_number {
                     0 => Color::Black,
                     1 => Color::White,
                     output => panic!("Invalid program output color: {:?}", output),
                 };
-                let direction_change = match direction_number {
-                    0 => Direction::Left,
-                    1 => Direction::Right,
+                let turn = match direction_number {
+                    0 => Turn::Left,
+                    1 => Turn::Right,
                     num => panic!("Invalid program output direction: {}", num),
                 };
 
-                (color, direction_change)
+                (color, turn)
             }
             _ => panic!("Invalid program output: {:?}", computer.output),
         };
 
-        match panel.entry(position) {
-            Entry::Occupied(entry) => {
-                *entry.into_mut() = output_color;
-            }
-            Entry::Vacant(entry) => {
-                entry.insert(output_color);
-            }
-        }
-
-        direction = match (direction, direction_change) {
-            (CardinalDirection::North, Direction::Left) => CardinalDirection::West,
-            (CardinalDirection::North, Direction::Right) => CardinalDirection::East,
-            (CardinalDirection::East, Direction::Left) => CardinalDirection::North,
-            (CardinalDirection::East, Direction::Right) => CardinalDirection::South,
-            (CardinalDirection::West, Direction::Left) => CardinalDirection::South,
-            (CardinalDirection::West, Direction::Right) => CardinalDirection::North,
-            (CardinalDirection::South, Direction::Left) => CardinalDirection::East,
-            (CardinalDirection::South, Direction::Right) => CardinalDirection::West,
-        };
-
-        position = position
-            + match direction {
-                CardinalDirection::North => point(0, -1),
-                CardinalDirection::South => point(0, 1),
-                CardinalDirection::East => point(1, 0),
-                CardinalDirection::West => point(-1, 0),
-            }
+        panel.set(robot.position(), output_color);
+        robot.turn(turn);
+        robot.forward(1);
     }
 
     panel
 }
 
-fn print_panel(panel: &HashMap<Point, Color>, default_color: Color) -> String {
-    let mut output = String::new();
-    let print = |color: Color, output: &mut String| {
-        let ch = match color {
-            Color::Black => ' ',
-            Color::White => '#',
-        };
-        output.push(ch);
-    };
-
-    let min_x = panel.keys().map(|p| p.x).min().unwrap();
-    let max_x = panel.keys().map(|p| p.x).max().unwrap();
-    let min_y = panel.keys().map(|p| p.y).min().unwrap();
-    let max_y = panel.keys().map(|p| p.y).max().unwrap();
-
-    for y in (min_y - 1)..=(max_y + 1) {
-        for x in min_x..=max_x {
-            let color = panel.get(&point(x, y)).unwrap_or(&default_color);
-            print(*color, &mut output);
-        }
-        output.push('\n');
-    }
+fn print_panel(panel: &SparseGrid<Color>) -> String {
+    // Leave a row of unpainted panels above and below the registration
+    // identifier.
+    let region = panel.bounds().unwrap().expand(0, 1).unwrap();
+    let output = panel.render_region(region, |color| match color {
+        Color::Black => ' ',
+        Color::White => '#',
+    });
     print!("{}", output);
     output
 }
@@ -523,7 +442,7 @@ pub fn part_one(input: &str) -> usize {
 
 pub fn part_two(input: &str) -> String {
     let panel = compute(input, Color::White);
-    print_panel(&panel, Color::White)
+    print_panel(&panel)
 }
 
 fn main() {
@@ -1,27 +1,19 @@
 use itertools::Itertools;
 
-fn parse_value(input: &str, variable: &str) -> i32 {
-    match input.find(variable) {
-        None => panic!("failed to find variable in \"{}\"", input),
-        Some(index) => {
-            let rest = &input[index + variable.len()..];
-            match rest.split_once(['>', ',']) {
-                None => panic!("failed to parse integer in \"{}\" error: {}", rest, rest),
-                Some((prefix, _)) => prefix.parse::<i32>().unwrap(),
-            }
-        }
-    }
-}
-
-fn parse_line(input: &str) -> (i32, i32, i32) {
-    let x = parse_value(input, "x=");
-    let y = parse_value(input, "y=");
-    let z = parse_value(input, "z=");
-    (x, y, z)
-}
-
-fn parse_input(input: &str) -> Vec<(i32, i32, i32)> {
-    input.trim().split('\n').map(parse_line).collect_vec()
+use aoc2019::math;
+use aoc2019::point::{AXES, Axis, Point3D};
+
+type Vector = Point3D<i32>;
+
+fn parse_input(input: &str) -> Vec<Vector> {
+    input
+        .trim()
+        .lines()
+        .map(|line| {
+            line.parse()
+                .unwrap_or_else(|e| panic!("failed to parse \"{}\": {}", line, e))
+        })
+        .collect_vec()
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -42,27 +34,29 @@ impl MoonAxis {
 
 #[derive(Clone, Debug)]
 struct Moon {
-    x: MoonAxis,
-    y: MoonAxis,
-    z: MoonAxis,
+    position: Vector,
+    velocity: Vector,
 }
 
 impl Moon {
     fn apply_gravity_from(&mut self, other: &Moon) {
-        self.x.apply_gravity_from(other.x);
-        self.y.apply_gravity_from(other.y);
-        self.z.apply_gravity_from(other.z);
+        self.velocity = self.velocity + (other.position - self.position).signum();
     }
 
     fn apply_velocity(&mut self) {
-        self.x.apply_velocity();
-        self.y.apply_velocity();
-        self.z.apply_velocity();
+        self.position = self.position + self.velocity;
+    }
+
+    fn axis(&self, axis: Axis) -> MoonAxis {
+        MoonAxis {
+            position: self.position.axis(axis),
+            velocity: self.velocity.axis(axis),
+        }
     }
 
     fn total_energy(&self) -> i32 {
-        (self.x.position.abs() + self.y.position.abs() + self.z.position.abs())
-            * (self.x.velocity.abs() + self.y.velocity.abs() + self.z.velocity.abs())
+        let origin = Vector::default();
+        self.position.manhattan_distance(origin) * self.velocity.manhattan_distance(origin)
     }
 }
 
@@ -70,18 +64,8 @@ fn parse_moons(input: &str) -> Vec<Moon> {
     parse_input(input)
         .into_iter()
         .map(|position| Moon {
-            x: MoonAxis {
-                position: position.0,
-                velocity: 0,
-            },
-            y: MoonAxis {
-                position: position.1,
-                velocity: 0,
-            },
-            z: MoonAxis {
-                position: position.2,
-                velocity: 0,
-            },
+            position,
+            velocity: Vector::default(),
         })
         .collect_vec()
 }
@@ -143,35 +127,16 @@ fn find_period(axes: &[MoonAxis]) -> i64 {
     period
 }
 
-fn get_axes<F, T>(moons: &[Moon], get: F) -> Vec<T>
-where
-    F: Fn(&Moon) -> T,
-{
-    moons.iter().map(get).collect()
-}
-
-fn gcd(mut m: i64, mut n: i64) -> i64 {
-    while m != 0 {
-        let old_m = m;
-        m = n % m;
-        n = old_m;
-    }
-
-    n
-}
-
-fn lcm(a: i64, b: i64) -> i64 {
-    a * b / gcd(a, b)
-}
-
 fn compute_part_two(input: &str) -> i64 {
     let moons = parse_moons(input);
 
-    let x_period = find_period(&get_axes(&moons, |axis| axis.x));
-    let y_period = find_period(&get_axes(&moons, |axis| axis.y));
-    let z_period = find_period(&get_axes(&moons, |axis| axis.z));
-
-    lcm(x_period, lcm(y_period, z_period))
+    // Each axis evolves independently, so the whole system repeats once
+    // every axis has.
+    let periods = AXES
+        .iter()
+        .map(|&axis| find_period(&moons.iter().map(|moon| moon.axis(axis)).collect_vec()))
+        .collect_vec();
+    math::lcm(&periods)
 }
 
 pub fn part_one(input: &str) -> i32 {
@@ -207,18 +172,23 @@ mod tests {
     "#;
 
     #[test]
-    fn test_parse_value() {
-        let str = "<x=-1, y=0, z=2>";
-        assert_eq!(parse_value(str, "x="), -1);
-        assert_eq!(parse_value(str, "y="), 0);
-        assert_eq!(parse_value(str, "z="), 2);
+    fn test_parse_line() {
+        let moon: Vector = "<x=-1, y=0, z=2>".parse().unwrap();
+        assert_eq!(moon.axis(Axis::X), -1);
+        assert_eq!(moon.axis(Axis::Y), 0);
+        assert_eq!(moon.axis(Axis::Z), 2);
     }
 
     #[test]
     fn test_parse_input() {
         assert_eq!(
             parse_input(EXAMPLE1),
-            vec![(-1, 0, 2), (2, -10, -7), (4, -8, 8), (3, 5, -1)]
+            vec![
+                Vector::new(-1, 0, 2),
+                Vector::new(2, -10, -7),
+                Vector::new(4, -8, 8),
+                Vector::new(3, 5, -1)
+            ]
         )
     }
 
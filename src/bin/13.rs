@@ -1,522 +1,527 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
 
-use anyhow::bail;
-use anyhow::Result;
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-enum Opcode {
-    Add,
-    Multiply,
-    Input,
-    Output,
-    JumpIfTrue,
-    JumpIfFalse,
-    LessThan,
-    Equals,
-    Finished,
-    AdjustRelativeBase,
+use aoc2019::intcode::{Computer, RunState};
+use aoc2019::point::Point2D;
+
+type Point = Point2D<i64>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tile {
+    Empty,
+    Wall,
+    Block,
+    Paddle,
+    Ball,
 }
 
-impl Opcode {
-    fn from(i: i64) -> anyhow::Result<Opcode> {
-        match i {
-            1 => anyhow::Ok(Opcode::Add),
-            2 => anyhow::Ok(Opcode::Multiply),
-            3 => anyhow::Ok(Opcode::Input),
-            4 => anyhow::Ok(Opcode::Output),
-            5 => anyhow::Ok(Opcode::JumpIfTrue),
-            6 => anyhow::Ok(Opcode::JumpIfFalse),
-            7 => anyhow::Ok(Opcode::LessThan),
-            8 => anyhow::Ok(Opcode::Equals),
-            9 => anyhow::Ok(Opcode::AdjustRelativeBase),
-            99 => anyhow::Ok(Opcode::Finished),
-            _ => anyhow::bail!("Invalid opcode: {}", i),
+impl Tile {
+    fn id_to_tile(id: i64) -> Tile {
+        match id {
+            0 => Tile::Empty,
+            1 => Tile::Wall,
+            2 => Tile::Block,
+            3 => Tile::Paddle,
+            4 => Tile::Ball,
+            _ => panic!("Invalid tile id."),
+        }
+    }
+
+    fn glyph(&self) -> char {
+        match self {
+            Tile::Empty => ' ',
+            Tile::Wall => '#',
+            Tile::Block => '=',
+            Tile::Paddle => '-',
+            Tile::Ball => 'o',
         }
     }
 }
 
-#[derive(Clone, Debug)]
-enum ParameterMode {
-    Position,
-    Immediate,
-    RelativePosition,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Joystick {
+    Left,
+    Neutral,
+    Right,
 }
 
-impl ParameterMode {
-    fn from(i: i64) -> Result<ParameterMode> {
-        let mode = match i {
-            0 => ParameterMode::Position,
-            1 => ParameterMode::Immediate,
-            2 => ParameterMode::RelativePosition,
-            _ => bail!("Invalid parameter mode: {}", i),
-        };
-        Ok(mode)
+impl Joystick {
+    fn toward(from: i64, to: i64) -> Joystick {
+        match (to - from).signum() {
+            -1 => Joystick::Left,
+            1 => Joystick::Right,
+            _ => Joystick::Neutral,
+        }
     }
 }
 
-#[derive(Clone, Debug)]
-struct Parameter {
-    mode: ParameterMode,
-    value: i64,
+impl From<Joystick> for i64 {
+    fn from(joystick: Joystick) -> Self {
+        match joystick {
+            Joystick::Left => -1,
+            Joystick::Neutral => 0,
+            Joystick::Right => 1,
+        }
+    }
 }
 
-#[derive(Clone, Debug)]
-enum Instruction {
-    Add(Parameter, Parameter, Parameter),
-    Multiply(Parameter, Parameter, Parameter),
-    Input(Parameter),
-    Output(Parameter),
-    JumpIfTrue(Parameter, Parameter),
-    JumpIfFalse(Parameter, Parameter),
-    LessThan(Parameter, Parameter, Parameter),
-    Equals(Parameter, Parameter, Parameter),
-    Finished,
-    AdjustRelativeBase(Parameter),
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    WaitingForJoystick,
+    GameOver,
 }
 
-#[derive(Debug)]
-struct Memory {
-    vec: Vec<i64>,
-    trace: bool,
+/// The arcade cabinet: the game program plus everything it has drawn.
+///
+/// The program draws a frame, then waits for the joystick.  [`Pong::advance`]
+/// runs one frame and [`Pong::tilt`] supplies the joystick for the next.
+struct Pong {
+    computer: Computer,
+    output_buffer: Vec<i64>,
+    screen: HashMap<Point, Tile>,
+    blocks: usize,
+    ball: Option<Point>,
+    ball_velocity: Option<Point>,
+    paddle: Option<Point>,
+    score: Option<i64>,
+    frames: usize,
 }
 
-impl Memory {
-    fn new(trace: bool) -> Self {
-        Memory {
-            vec: Vec::new(),
-            trace,
+impl Pong {
+    fn new(program_text: &str) -> Self {
+        Pong {
+            computer: Computer::parse(program_text),
+            output_buffer: Vec::new(),
+            screen: HashMap::new(),
+            blocks: 0,
+            ball: None,
+            ball_velocity: None,
+            paddle: None,
+            score: None,
+            frames: 0,
         }
     }
 
-    fn get(&self, index: i64) -> i64 {
-        if index < 0 {
-            panic!("invalid index: {}", index);
-        }
-        let value = if index >= self.vec.len() as i64 {
-            0
-        } else {
-            self.vec[index as usize]
-        };
-        if self.trace {
-            println!("        mem[{}] -> {}", index, value);
-        }
-        value
+    /// Starts a game that can be played to the end rather than just drawing
+    /// the initial screen.
+    fn with_quarters(program_text: &str) -> Self {
+        let mut pong = Pong::new(program_text);
+        pong.computer.poke(0, 2); // insert infinite quarters, per the problem instructions
+        pong
     }
 
-    fn set(&mut self, index: i64, value: i64) {
-        if !(0..(100 * 1000)).contains(&index) {
-            panic!("invalid index: {}", index);
-        }
-        while index >= self.vec.len() as i64 {
-            self.vec.push(0);
-        }
-        self.vec[index as usize] = value;
-        if self.trace {
-            println!("        mem[{}] <- {}", index, value);
-        }
+    fn tile(&self, point: Point) -> Tile {
+        self.screen.get(&point).copied().unwrap_or(Tile::Empty)
     }
 
-    fn push(&mut self, value: i64) {
-        self.vec.push(value);
+    fn blocks_left(&self) -> usize {
+        self.blocks
     }
-}
-
-fn parse_parameter(
-    param: i64,
-    instruction: i64,
-    pc: i64,
-    memory: &Memory,
-) -> anyhow::Result<Parameter> {
-    if !(0..=2).contains(&param) {
-        panic!("Invalid parameter index: {}", param);
-    }
-
-    let mut tmp = instruction / 100;
-    for _ in 0..param {
-        tmp /= 10;
-    }
-    let immediate_value = memory.get(pc + 1 + param);
-    let mode = ParameterMode::from(tmp % 10)?;
-    let parameter = Parameter {
-        mode,
-        value: immediate_value,
-    };
-    Ok(parameter)
-}
 
-fn parse_instruction(pc: i64, memory: &Memory) -> anyhow::Result<Instruction> {
-    let instruction = memory.get(pc);
-    let op = Opcode::from(instruction % 100)?;
-    let instr = match op {
-        Opcode::Add => {
-            let a = parse_parameter(0, instruction, pc, memory)?;
-            let b = parse_parameter(1, instruction, pc, memory)?;
-            let c = parse_parameter(2, instruction, pc, memory)?;
-            Instruction::Add(a, b, c)
-        }
-        Opcode::Multiply => {
-            let a = parse_parameter(0, instruction, pc, memory)?;
-            let b = parse_parameter(1, instruction, pc, memory)?;
-            let c = parse_parameter(2, instruction, pc, memory)?;
-            Instruction::Multiply(a, b, c)
-        }
-        Opcode::Input => {
-            let a = parse_parameter(0, instruction, pc, memory)?;
-            Instruction::Input(a)
-        }
-        Opcode::Output => {
-            let a = parse_parameter(0, instruction, pc, memory)?;
-            Instruction::Output(a)
-        }
-        Opcode::JumpIfTrue => {
-            let a = parse_parameter(0, instruction, pc, memory)?;
-            let b = parse_parameter(1, instruction, pc, memory)?;
-            Instruction::JumpIfTrue(a, b)
-        }
-        Opcode::JumpIfFalse => {
-            let a = parse_parameter(0, instruction, pc, memory)?;
-            let b = parse_parameter(1, instruction, pc, memory)?;
-            Instruction::JumpIfFalse(a, b)
+    fn draw(&mut self, point: Point, tile: Tile) {
+        let previous = self.screen.insert(point, tile).unwrap_or(Tile::Empty);
+        if previous == Tile::Block {
+            self.blocks -= 1;
         }
-        Opcode::LessThan => {
-            let a = parse_parameter(0, instruction, pc, memory)?;
-            let b = parse_parameter(1, instruction, pc, memory)?;
-            let c = parse_parameter(2, instruction, pc, memory)?;
-            Instruction::LessThan(a, b, c)
-        }
-        Opcode::Equals => {
-            let a = parse_parameter(0, instruction, pc, memory)?;
-            let b = parse_parameter(1, instruction, pc, memory)?;
-            let c = parse_parameter(2, instruction, pc, memory)?;
-            Instruction::Equals(a, b, c)
-        }
-        Opcode::Finished => Instruction::Finished,
-        Opcode::AdjustRelativeBase => {
-            let a = parse_parameter(0, instruction, pc, memory)?;
-            Instruction::AdjustRelativeBase(a)
+        match tile {
+            Tile::Ball => {
+                if let Some(ball) = self.ball {
+                    self.ball_velocity = Some(point - ball);
+                }
+                self.ball = Some(point);
+            }
+            Tile::Paddle => self.paddle = Some(point),
+            Tile::Block => self.blocks += 1,
+            Tile::Empty | Tile::Wall => {}
         }
-    };
-    Ok(instr)
-}
-
-// Input and output for a computer.
-//
-// Originally I had input and output as separate traits, but ran into issues
-// with mutability and borrow checking when handling both traits on one
-// object.
-trait ComputerIO {
-    fn input(&mut self) -> i64;
-    fn output(&mut self, value: i64);
-}
-
-struct Computer<'a> {
-    pc: i64,
-    memory: Memory,
-    relative_base: i64,
-    io: &'a mut dyn ComputerIO,
-    finished: bool,
-    trace: bool,
-    step: i32,
-}
+    }
 
-impl Computer<'_> {
-    fn new(io: &mut dyn ComputerIO) -> Computer<'_> {
-        Computer {
-            pc: 0,
-            memory: Memory::new(false),
-            relative_base: 0,
-            io,
-            finished: false,
-            trace: false,
-            step: 0,
+    fn output(&mut self, value: i64) {
+        self.output_buffer.push(value);
+        if self.output_buffer.len() == 3 {
+            let point = Point::new(self.output_buffer[0], self.output_buffer[1]);
+            let arg = self.output_buffer[2];
+            self.output_buffer.clear();
+            if point == Point::new(-1, 0) {
+                self.score = Some(arg);
+            } else {
+                self.draw(point, Tile::id_to_tile(arg));
+            }
         }
     }
 
-    fn store(&mut self, param: Parameter, value: i64) {
-        let address = match param.mode {
-            ParameterMode::Position => param.value,
-            ParameterMode::Immediate => {
-                panic!("storing to an immediate parameter is not not implemented")
+    /// Runs the program until it has drawn the next frame.
+    fn advance(&mut self) -> Status {
+        loop {
+            match self.computer.run() {
+                RunState::BlockedOnOutput => {
+                    let value = self.computer.take_output().unwrap();
+                    self.output(value);
+                }
+                RunState::BlockedOnInput => {
+                    self.frames += 1;
+                    return Status::WaitingForJoystick;
+                }
+                RunState::Finished => {
+                    self.frames += 1;
+                    return Status::GameOver;
+                }
+                RunState::Trapped => unreachable!("no extensions are registered"),
             }
-            ParameterMode::RelativePosition => self.relative_base + param.value,
-        };
-        self.store_to_address(address, value);
+        }
     }
 
-    fn store_to_address(&mut self, address: i64, value: i64) {
-        if self.trace {
-            println!("    store: mem[{}] <- {}", address, value);
-        }
-        if !(0..=(128 * 1024)).contains(&address) {
-            panic!("Invalid address: {}", address);
-        }
-        self.memory.set(address, value);
+    fn tilt(&mut self, joystick: Joystick) {
+        self.computer.append_input(&[joystick.into()]);
     }
 
-    fn load(&self, param: Parameter) -> i64 {
-        if self.trace {
-            println!("    load: param={:?}", param);
-        }
-        match param.mode {
-            ParameterMode::Position => self.memory.get(param.value),
-            ParameterMode::Immediate => param.value,
-            ParameterMode::RelativePosition => self.memory.get(self.relative_base + param.value),
-        }
+    /// Plays until the program finishes and returns the final score.
+    fn play(&mut self, controller: &mut dyn Controller) -> Option<i64> {
+        self.play_with(controller, &mut |_, _| {})
     }
 
-    fn step(&mut self) -> anyhow::Result<()> {
-        self.step += 1;
-        if self.step > 1_000_000 {
-            bail!("Too many steps");
-        }
-        if self.trace {
-            println!("step {}: pc={}", self.step, self.pc);
+    /// Like [`Pong::play`], calling `on_frame` after every frame is drawn.
+    fn play_with(
+        &mut self,
+        controller: &mut dyn Controller,
+        on_frame: &mut dyn FnMut(&Pong, Status),
+    ) -> Option<i64> {
+        loop {
+            let status = self.advance();
+            on_frame(self, status);
+            if status == Status::GameOver {
+                break;
+            }
+            let joystick = controller.joystick(self);
+            self.tilt(joystick);
         }
+        self.score
+    }
 
-        let instruction = parse_instruction(self.pc, &self.memory)?;
-        if self.trace {
-            println!("  instruction: {:?}", instruction);
+    /// The column where the ball will reach the paddle's row, following
+    /// its current velocity and bouncing off walls.  Blocks are ignored, so
+    /// the answer changes when the ball hits one.  Returns `None` when the
+    /// ball is not heading down.
+    fn predict_landing(&self) -> Option<i64> {
+        let (mut ball, mut velocity, paddle) = (self.ball?, self.ball_velocity?, self.paddle?);
+        if velocity.y <= 0 {
+            return None;
         }
-
-        self.pc += match instruction {
-            Instruction::Add(a, b, c) => {
-                let value = self.load(a) + self.load(b);
-                self.store(c, value);
-                4
-            }
-            Instruction::Multiply(a, b, c) => {
-                let value = self.load(a) * self.load(b);
-                self.store(c, value);
-                4
-            }
-            Instruction::Input(a) => {
-                let value = self.io.input();
-                self.store(a, value);
-                2
-            }
-            Instruction::Output(a) => {
-                let value = self.load(a);
-                if self.trace {
-                    println!(" output: {}", value);
-                }
-                self.io.output(value);
-                2
-            }
-            Instruction::JumpIfTrue(a, b) => {
-                let value = self.load(a);
-                if value != 0 {
-                    self.pc = self.load(b);
-                    0
-                } else {
-                    3
-                }
-            }
-            Instruction::JumpIfFalse(a, b) => {
-                let value = self.load(a);
-                if value == 0 {
-                    self.pc = self.load(b);
-                    0
-                } else {
-                    3
-                }
-            }
-            Instruction::LessThan(a, b, c) => {
-                let less = self.load(a) < self.load(b);
-                self.store(c, if less { 1 } else { 0 });
-                4
-            }
-            Instruction::Equals(a, b, c) => {
-                let equals = self.load(a) == self.load(b);
-                self.store(c, if equals { 1 } else { 0 });
-                4
-            }
-            Instruction::AdjustRelativeBase(a) => {
-                let value = self.load(a);
-                if self.trace {
-                    println!(
-                        "    relative-base <= {} + {} <= {}",
-                        self.relative_base,
-                        value,
-                        self.relative_base + value
-                    );
-                }
-                self.relative_base += value;
-                2
-            }
-            Instruction::Finished => {
-                if self.trace {
-                    println!("FINISHED");
-                }
-                self.finished = true;
-                0
+        while ball.y < paddle.y - 1 {
+            if self.tile(Point::new(ball.x + velocity.x, ball.y)) == Tile::Wall {
+                velocity.x = -velocity.x;
             }
+            ball = ball + velocity;
+        }
+        Some(ball.x)
+    }
+}
+
+/// Renders the score line followed by the screen.
+impl fmt::Display for Pong {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Score: {}  Blocks: {}",
+            self.score.unwrap_or(0),
+            self.blocks_left()
+        )?;
+        let (Some(min_x), Some(max_x)) = (
+            self.screen.keys().map(|p| p.x).min(),
+            self.screen.keys().map(|p| p.x).max(),
+        ) else {
+            return Ok(());
         };
+        let min_y = self.screen.keys().map(|p| p.y).min().unwrap();
+        let max_y = self.screen.keys().map(|p| p.y).max().unwrap();
+        for y in min_y..=max_y {
+            let row: String = (min_x..=max_x)
+                .map(|x| self.tile(Point::new(x, y)).glyph())
+                .collect();
+            writeln!(f, "{}", row.trim_end())?;
+        }
         Ok(())
     }
 }
 
-struct VectorOutputer {
-    vec: Vec<i64>,
+/// Collects rendered frames and writes them as an asciicast v2 recording,
+/// which `asciinema play` can replay.
+struct Recorder {
+    frames: Vec<(f64, String)>,
 }
 
-impl ComputerIO for VectorOutputer {
-    fn input(&mut self) -> i64 {
-        panic!("VectorOutputer::input() not implemented");
+impl Recorder {
+    fn new() -> Self {
+        Recorder { frames: Vec::new() }
     }
 
-    fn output(&mut self, value: i64) {
-        self.vec.push(value);
+    /// Records `frame`, shown `time` seconds into the recording.
+    fn record(&mut self, time: f64, frame: &str) {
+        self.frames.push((time, frame.to_string()));
     }
-}
 
-fn parse_program<'a>(text: &'a str, io: &'a mut dyn ComputerIO) -> Computer<'a> {
-    let mut computer = Computer::new(io);
-    for number in text.trim_end().split(',') {
-        computer.memory.push(number.parse().unwrap_or_else(|e| {
-            panic!("Invalid numerical string: \"{}\" error: {}", number, e);
-        }));
+    fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let lines = || self.frames.iter().flat_map(|(_, frame)| frame.lines());
+        let width = lines().map(|line| line.chars().count()).max().unwrap_or(0);
+        let height = self
+            .frames
+            .iter()
+            .map(|(_, frame)| frame.lines().count())
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            w,
+            "{{\"version\": 2, \"width\": {}, \"height\": {}}}",
+            width, height
+        )?;
+        for (time, frame) in &self.frames {
+            // Home the cursor and clear the screen, then draw the frame with
+            // the carriage returns a raw terminal needs.
+            let data = format!("\x1b[H\x1b[2J{}", frame.replace('\n', "\r\n"));
+            writeln!(w, "[{:.6}, \"o\", {}]", time, json_string(&data))?;
+        }
+        Ok(())
     }
-    computer
 }
 
-fn run_program(program_text: &str, trace: bool) -> Result<Vec<i64>> {
-    let mut output = VectorOutputer { vec: Vec::new() };
-    let mut computer = parse_program(program_text, &mut output as &mut dyn ComputerIO);
-    computer.trace = trace;
-    computer.memory.trace = trace;
-    while !computer.finished {
-        computer.step()?;
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
     }
-    Ok(output.vec)
+    out.push('"');
+    out
 }
 
-fn part_one(input: &str) -> i32 {
-    let output = run_program(input, false).unwrap();
+trait Controller {
+    fn joystick(&mut self, pong: &Pong) -> Joystick;
+}
 
-    let mut tiles: HashMap<(i64, i64), i64> = HashMap::new();
+/// Keeps the paddle under the ball.
+struct BallTracker;
 
-    assert_eq!(output.len() % 3, 0);
-    for chunk in output.chunks_exact(3) {
-        match chunk {
-            [x, y, tile_id] => {
-                *tiles.entry((*x, *y)).or_insert(*tile_id) = *tile_id;
-            }
-            _ => {
-                panic!("Invalid output {:?}", chunk);
-            }
+impl Controller for BallTracker {
+    fn joystick(&mut self, pong: &Pong) -> Joystick {
+        match (pong.ball, pong.paddle) {
+            (Some(ball), Some(paddle)) => Joystick::toward(paddle.x, ball.x),
+            _ => panic!("invalid program state"),
         }
     }
+}
 
-    let mut block_tiles = 0;
-    for v in tiles.values() {
-        if *v == 2 {
-            block_tiles += 1;
-        }
+/// Moves the paddle to where the ball will land, and otherwise follows the
+/// ball.
+struct Predictor;
+
+impl Controller for Predictor {
+    fn joystick(&mut self, pong: &Pong) -> Joystick {
+        let (Some(ball), Some(paddle)) = (pong.ball, pong.paddle) else {
+            panic!("invalid program state");
+        };
+        let target = pong.predict_landing().unwrap_or(ball.x);
+        Joystick::toward(paddle.x, target)
     }
-    block_tiles
 }
 
-#[derive(PartialEq, Eq)]
-enum Tile {
-    Empty,
-    Wall,
-    Block,
-    Paddle,
-    Ball,
+/// Reads one line per frame: `a` tilts left, `d` tilts right, anything else
+/// (such as a space or an empty line) leaves the joystick neutral.  Stays
+/// neutral once the input runs out.
+struct Human<R> {
+    input: R,
 }
 
-impl Tile {
-    fn id_to_tile(id: i64) -> Tile {
-        match id {
-            0 => Tile::Empty,
-            1 => Tile::Wall,
-            2 => Tile::Block,
-            3 => Tile::Paddle,
-            4 => Tile::Ball,
-            _ => panic!("Invalid tile id."),
+impl<R: BufRead> Controller for Human<R> {
+    fn joystick(&mut self, _pong: &Pong) -> Joystick {
+        let mut line = String::new();
+        self.input
+            .read_line(&mut line)
+            .expect("failed to read joystick");
+        match line.trim_end_matches(['\r', '\n']) {
+            "a" => Joystick::Left,
+            "d" => Joystick::Right,
+            _ => Joystick::Neutral,
         }
     }
 }
 
-struct Pong {
-    output_buffer: Vec<i64>,
-    ball: Option<(i64, i64)>,
-    paddle: Option<(i64, i64)>,
-    score: Option<i64>,
+fn part_one(input: &str) -> usize {
+    let mut pong = Pong::new(input);
+    assert_eq!(pong.advance(), Status::GameOver);
+    pong.blocks_left()
 }
 
-impl Pong {
-    fn new() -> Self {
-        Pong {
-            output_buffer: Vec::new(),
-            ball: None,
-            paddle: None,
-            score: None,
-        }
+fn part_two(input: &str) -> i64 {
+    let mut pong = Pong::with_quarters(input);
+    pong.play(&mut BallTracker).unwrap()
+}
+
+// Without arguments, checks both answers.  Otherwise plays one game:
+//
+//   --controller tracker|predictor|human   who moves the joystick
+//   --play                                 shorthand for --controller human
+//                                          --render
+//   --render                               print every frame
+//   --record FILE                          save the frames as an asciicast
+fn main() {
+    let input = include_str!("../inputs/13.txt");
+    let mut args = pico_args::Arguments::from_env();
+    let play = args.contains("--play");
+    let render = play || args.contains("--render");
+    let record: Option<String> = args.opt_value_from_str("--record").unwrap();
+    let controller: Option<String> = args.opt_value_from_str("--controller").unwrap();
+    let controller = controller.or_else(|| play.then(|| "human".to_string()));
+    if controller.is_none() && !render && record.is_none() {
+        assert_eq!(part_one(input), 277);
+        assert_eq!(part_two(input), 12856);
+        return;
     }
+    let human = controller.as_deref() == Some("human");
+    let mut controller: Box<dyn Controller> = match controller.as_deref() {
+        None | Some("tracker") => Box::new(BallTracker),
+        Some("predictor") => Box::new(Predictor),
+        Some("human") => Box::new(Human {
+            input: std::io::stdin().lock(),
+        }),
+        Some(other) => panic!("unknown controller \"{}\"", other),
+    };
 
-    fn draw(&mut self, point: (i64, i64), tile: Tile) {
-        match tile {
-            Tile::Ball => self.ball = Some(point),
-            Tile::Paddle => self.paddle = Some(point),
-            Tile::Empty | Tile::Block | Tile::Wall => {
-                // Handling these is not necessary. The problem statement
-                // says that we should record the score "after breaking all
-                // the blocks", but apparently the intcode program tracks
-                // this state for us and records a score only when it is
-                // appropriate to do so.
+    let mut recorder = Recorder::new();
+    let start = Instant::now();
+    let mut pong = Pong::with_quarters(input);
+    let score = pong.play_with(controller.as_mut(), &mut |pong, status| {
+        let frame = pong.to_string();
+        if render {
+            print!("{}", frame);
+            if human && status == Status::WaitingForJoystick {
+                print!("joystick (a = left, d = right, enter = stay)? ");
             }
+            io::stdout().flush().unwrap();
         }
+        if record.is_some() {
+            // Bots play at 30 frames per second; people at their own pace.
+            let time = if human {
+                start.elapsed().as_secs_f64()
+            } else {
+                (pong.frames - 1) as f64 / 30.0
+            };
+            recorder.record(time, &frame);
+        }
+    });
+    if let Some(path) = record {
+        let mut file = io::BufWriter::new(std::fs::File::create(&path).unwrap());
+        recorder.write(&mut file).unwrap();
     }
+    println!(
+        "score {:?} after {} frames, {} blocks left",
+        score,
+        pong.frames,
+        pong.blocks_left()
+    );
 }
 
-impl ComputerIO for Pong {
-    fn input(&mut self) -> i64 {
-        match (self.ball, self.paddle) {
-            (Some(ball), Some(paddle)) => {
-                // Tilt the joystick toward the ball.
-                // -1 for left, 1 for right, 0 for neutral.
-                (ball.0 - paddle.0).signum()
-            }
-            _ => panic!("invalid program state"),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../inputs/13.txt");
+
+    #[test]
+    fn test_first_frame() {
+        let mut pong = Pong::with_quarters(INPUT);
+        assert_eq!(pong.advance(), Status::WaitingForJoystick);
+        assert_eq!(pong.frames, 1);
+        assert_eq!(pong.blocks_left(), 277);
+        assert_eq!(pong.score, Some(0));
+        let ball = pong.ball.unwrap();
+        assert_eq!(pong.tile(ball), Tile::Ball);
+        let paddle = pong.paddle.unwrap();
+        assert_eq!(pong.tile(paddle), Tile::Paddle);
+        assert_eq!(pong.tile(Point::new(0, 0)), Tile::Wall);
     }
 
-    fn output(&mut self, value: i64) {
-        self.output_buffer.push(value);
-        if self.output_buffer.len() == 3 {
-            let point = (self.output_buffer[0], self.output_buffer[1]);
-            let arg = self.output_buffer[2];
-            self.output_buffer.clear();
-            if point == (-1, 0) {
-                self.score = Some(arg);
-            } else {
-                let tile = Tile::id_to_tile(arg);
-                self.draw(point, tile);
-            }
+    #[test]
+    fn test_predictor() {
+        let mut pong = Pong::with_quarters(INPUT);
+        assert_eq!(pong.play(&mut Predictor), Some(12856));
+        assert_eq!(pong.blocks_left(), 0);
+    }
+
+    #[test]
+    fn test_human() {
+        let mut pong = Pong::with_quarters(INPUT);
+        pong.advance();
+        let paddle = pong.paddle.unwrap();
+        let mut human = Human {
+            input: "a\na\n\nd\n".as_bytes(),
+        };
+        for _ in 0..4 {
+            let joystick = human.joystick(&pong);
+            pong.tilt(joystick);
+            pong.advance();
         }
+        assert_eq!(pong.paddle.unwrap(), paddle + Point::new(-1, 0));
+        // Out of input: neutral from here on, so the ball is soon lost.
+        assert_eq!(pong.play(&mut human), Some(0));
     }
-}
 
-fn part_two(input: &str) -> i64 {
-    let mut pong = Pong::new();
-    let mut computer = parse_program(input, &mut pong as &mut dyn ComputerIO);
-    computer.memory.vec[0] = 2; // insert infinite quarters, per the problem instructions
-    while !computer.finished {
-        computer.step().unwrap();
+    #[test]
+    fn test_render_first_frame() {
+        let mut pong = Pong::with_quarters(INPUT);
+        pong.advance();
+        let frame = pong.to_string();
+        let lines: Vec<&str> = frame.lines().collect();
+        assert_eq!(lines[0], "Score: 0  Blocks: 277");
+        assert!(lines[1].chars().all(|ch| ch == '#'));
+        let screen = &lines[1..];
+        assert_eq!(screen.iter().filter(|line| line.contains('o')).count(), 1);
+        assert_eq!(screen.iter().filter(|line| line.contains('-')).count(), 1);
+        assert_eq!(frame.matches('=').count(), 277);
     }
-    pong.score.unwrap()
-}
 
-fn main() {
-    let input = include_str!("../inputs/13.txt");
-    assert_eq!(part_one(input), 277);
-    assert_eq!(part_two(input), 12856);
-}
+    #[test]
+    fn test_recorder() {
+        let mut recorder = Recorder::new();
+        recorder.record(0.0, "ab\n");
+        recorder.record(0.5, "\"c\"\nde\\f\n");
+        let mut out = Vec::new();
+        recorder.write(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "{\"version\": 2, \"width\": 4, \"height\": 2}\n",
+                "[0.000000, \"o\", \"\\u001b[H\\u001b[2Jab\\r\\n\"]\n",
+                "[0.500000, \"o\", \"\\u001b[H\\u001b[2J\\\"c\\\"\\r\\nde\\\\f\\r\\n\"]\n",
+            )
+        );
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_record_game() {
+        let mut recorder = Recorder::new();
+        let mut pong = Pong::with_quarters(INPUT);
+        pong.play_with(&mut Predictor, &mut |pong, _| {
+            recorder.record(pong.frames as f64, &pong.to_string())
+        });
+        assert_eq!(recorder.frames.len(), pong.frames);
+        assert!(
+            recorder
+                .frames
+                .last()
+                .unwrap()
+                .1
+                .starts_with("Score: 12856  Blocks: 0\n")
+        );
+    }
 
     #[test]
     fn test_main() {
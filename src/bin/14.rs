@@ -1,6 +1,8 @@
 use core::fmt;
 use std::{cmp::max, collections::HashMap, error::Error};
 
+use aoc2019::graph::{Graph, NodeId};
+
 #[derive(Debug, PartialEq)]
 struct Chemical {
     name: u32,
@@ -33,6 +35,7 @@ impl fmt::Display for Reaction {
 
 struct NameTable {
     names_to_index: HashMap<String, u32>,
+    index_to_names: Vec<String>,
 }
 
 const NAME_TABLE_FUEL: u32 = 0;
@@ -40,18 +43,27 @@ const NAME_TABLE_ORE: u32 = 1;
 
 impl NameTable {
     fn new() -> Self {
-        let mut names_to_index = HashMap::new();
-        names_to_index.insert("FUEL".to_string(), NAME_TABLE_FUEL);
-        names_to_index.insert("ORE".to_string(), NAME_TABLE_ORE);
-        Self { names_to_index }
+        let mut names = Self {
+            names_to_index: HashMap::new(),
+            index_to_names: Vec::new(),
+        };
+        assert_eq!(names.insert("FUEL".to_string()), NAME_TABLE_FUEL);
+        assert_eq!(names.insert("ORE".to_string()), NAME_TABLE_ORE);
+        names
     }
 
     fn insert(&mut self, name: String) -> u32 {
-        let len = self.names_to_index.len();
-        *self
-            .names_to_index
-            .entry(name)
-            .or_insert_with(|| len as u32)
+        if let Some(&index) = self.names_to_index.get(&name) {
+            return index;
+        }
+        let index = self.index_to_names.len() as u32;
+        self.index_to_names.push(name.clone());
+        self.names_to_index.insert(name, index);
+        index
+    }
+
+    fn name(&self, index: usize) -> &str {
+        &self.index_to_names[index]
     }
 }
 
@@ -82,7 +94,10 @@ fn parse_chemical_reaction(input: &str, names: &mut NameTable) -> Result<Reactio
     })
 }
 
-fn parse_input(input: &str) -> Result<Vec<Option<Reaction>>, Box<dyn Error>> {
+/// The reaction producing each chemical, if any, indexed by name.
+type Reactions = Vec<Option<Reaction>>;
+
+fn parse_input(input: &str) -> Result<(Reactions, NameTable), Box<dyn Error>> {
     let mut names = NameTable::new();
     let mut reactions = Vec::new();
     for line in input.trim().lines() {
@@ -101,68 +116,110 @@ fn parse_input(input: &str) -> Result<Vec<Option<Reaction>>, Box<dyn Error>> {
             }
         }
     }
-    Ok(reactions)
+    Ok((reactions, names))
 }
 
-// TODO use u64::div_ceil instead, if it graduates out of nightly experimental.
-fn div_ceil64(x: u64, y: u64) -> u64 {
-    let (q, r) = (x / y, x % y);
-    if r != 0 {
-        q + 1
-    } else {
-        q
-    }
+/// The reactions as a graph with a node for each chemical, holding how much
+/// of it one reaction produces (none for ORE), and an edge from each
+/// reaction's output to each of its inputs, holding how much is consumed.
+struct ReactionGraph {
+    graph: Graph<Option<u64>, u64>,
+    fuel: NodeId,
+    ore: NodeId,
+    // Every chemical comes before the chemicals it is made from.
+    order: Vec<NodeId>,
 }
 
-fn ore_required_recur(
-    reactions: &Vec<Option<Reaction>>,
-    surplus: &mut Vec<u64>,
-    target: u32,
-    target_amount: u64,
-) -> u64 {
-    if target == NAME_TABLE_ORE {
-        return target_amount;
-    }
+impl ReactionGraph {
+    /// Builds the graph, checking that FUEL can be made from ORE alone, with
+    /// no chemical made from itself and no reaction left unused.
+    fn new(
+        reactions: &[Option<Reaction>],
+        names: &NameTable,
+    ) -> Result<ReactionGraph, Box<dyn Error>> {
+        let chemical_count = reactions
+            .iter()
+            .flatten()
+            .flat_map(|reaction| reaction.inputs.iter().chain([&reaction.output]))
+            .map(|chemical| chemical.name)
+            .chain([NAME_TABLE_FUEL, NAME_TABLE_ORE])
+            .max()
+            .unwrap() as usize
+            + 1;
+        let mut graph = Graph::new();
+        let ids: Vec<NodeId> = (0..chemical_count)
+            .map(|name| {
+                let produced = reactions.get(name).and_then(|reaction| reaction.as_ref());
+                graph.add_node(produced.map(|reaction| reaction.output.amount as u64))
+            })
+            .collect();
+        for reaction in reactions.iter().flatten() {
+            for input in &reaction.inputs {
+                let output = ids[reaction.output.name as usize];
+                graph.add_edge(output, ids[input.name as usize], input.amount as u64);
+            }
+        }
 
-    let needed_target_amount = {
-        let surplus_amount = &mut surplus[target as usize];
-        if target_amount <= *surplus_amount {
-            *surplus_amount -= target_amount;
-            return 0;
+        let fuel = ids[NAME_TABLE_FUEL as usize];
+        let ore = ids[NAME_TABLE_ORE as usize];
+        if graph.get_node(ore).unwrap().is_some() {
+            return Err("ORE must not be produced by a reaction".into());
         }
-        let new_target = target_amount - *surplus_amount;
-        *surplus_amount = 0;
-        new_target
-    };
-
-    let reaction = &reactions[target as usize].as_ref().unwrap();
-    let copies = div_ceil64(needed_target_amount, reaction.output.amount as u64);
-    let mut ore = 0;
-    for chem in reaction.inputs.iter() {
-        let input_amount = copies * chem.amount as u64;
-        ore += ore_required_recur(reactions, surplus, chem.name, input_amount);
+        let name = |id| names.name(ids.iter().position(|&other| other == id).unwrap());
+        let order = graph.topological_sort().map_err(|error| {
+            let cycle: Vec<&str> = error.cycle.iter().map(|&id| name(id)).collect();
+            format!("chemicals {:?} are made from each other", cycle)
+        })?;
+        let needed = graph.reachable_from([fuel]);
+        for &id in &ids {
+            let produced = graph.get_node(id).unwrap().is_some();
+            if !needed.contains(&id) {
+                return Err(format!("chemical {} is not needed to make FUEL", name(id)).into());
+            }
+            if !produced && id != ore {
+                return Err(format!("no reaction produces chemical {}", name(id)).into());
+            }
+        }
+        Ok(ReactionGraph {
+            graph,
+            fuel,
+            ore,
+            order,
+        })
     }
 
-    let produced = copies * reaction.output.amount as u64;
-    surplus[target as usize] += produced - needed_target_amount;
-
-    ore
-}
-
-fn ore_required(reactions: &Vec<Option<Reaction>>, target: u32, target_amount: u64) -> u64 {
-    let mut surplus = (0..reactions.len()).map(|_| 0).collect();
-    ore_required_recur(reactions, &mut surplus, target, target_amount)
+    /// The ORE needed to make `fuel_amount` FUEL.  Visiting chemicals in
+    /// topological order means every use of a chemical is known before
+    /// working out how many reactions must produce it.
+    fn ore_required(&self, fuel_amount: u64) -> u64 {
+        let mut needed = HashMap::from([(self.fuel, fuel_amount)]);
+        for &chemical in &self.order {
+            let (Some(&amount), Some(produced)) = (
+                needed.get(&chemical),
+                self.graph.get_node(chemical).unwrap(),
+            ) else {
+                continue;
+            };
+            let copies = amount.div_ceil(*produced);
+            for (&input_amount, input) in self.graph.successors(chemical) {
+                *needed.entry(input).or_default() += copies * input_amount;
+            }
+        }
+        needed[&self.ore]
+    }
 }
 
 fn part_one(input: &str) -> u64 {
-    let reactions = parse_input(input).expect("bad input");
-    ore_required(&reactions, NAME_TABLE_FUEL, 1)
+    let (reactions, names) = parse_input(input).expect("bad input");
+    let reactions = ReactionGraph::new(&reactions, &names).expect("bad reactions");
+    reactions.ore_required(1)
 }
 
 fn part_two(input: &str) -> u64 {
-    let reactions = parse_input(input).expect("bad input");
+    let (reactions, names) = parse_input(input).expect("bad input");
+    let reactions = ReactionGraph::new(&reactions, &names).expect("bad reactions");
 
-    let compute = |target_amount| ore_required(&reactions, NAME_TABLE_FUEL, target_amount);
+    let compute = |target_amount| reactions.ore_required(target_amount);
 
     let target: u64 = 1_000_000_000_000;
     let mut high: u64 = 0;
@@ -283,6 +340,29 @@ mod tests {
         assert_eq!(part_two(input), 460664);
     }
 
+    #[test]
+    fn test_invalid_reactions() {
+        let check = |input| {
+            let (reactions, names) = parse_input(input).unwrap();
+            ReactionGraph::new(&reactions, &names)
+                .err()
+                .map(|error| error.to_string())
+        };
+        assert_eq!(check("1 ORE => 1 A\n1 A => 1 FUEL"), None);
+        assert_eq!(
+            check("1 ORE, 1 B => 1 A\n1 A => 1 B\n1 A => 1 FUEL"),
+            Some("chemicals [\"B\", \"A\"] are made from each other".to_string())
+        );
+        assert_eq!(
+            check("1 ORE => 1 A\n1 ORE => 1 B\n1 A => 1 FUEL"),
+            Some("chemical B is not needed to make FUEL".to_string())
+        );
+        assert_eq!(
+            check("1 ORE, 1 B => 1 FUEL"),
+            Some("no reaction produces chemical B".to_string())
+        );
+    }
+
     #[test]
     fn test_main() {
         main();
@@ -1,7 +1,8 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashSet, VecDeque};
 
+use aoc2019::explore::{self, Observation, Outcome, Robot};
 use aoc2019::intcode::{self, RunState};
-use aoc2019::point::Point2D;
+use aoc2019::point::{CARDINAL_DIRECTIONS, CardinalDirection, Point2D, SparseGrid};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum Terrain {
@@ -23,35 +24,18 @@ impl TryFrom<i64> for Terrain {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Command {
-    North = 1,
-    South = 2,
-    West = 3,
-    East = 4,
-}
-
-const COMMANDS: [Command; 4] = [Command::North, Command::South, Command::East, Command::West];
-
-impl Command {
-    fn apply_to(&self, p: Point) -> Point {
-        match *self {
-            Command::North => Point::new(p.x, p.y - 1),
-            Command::South => Point::new(p.x, p.y + 1),
-            Command::West => Point::new(p.x - 1, p.y),
-            Command::East => Point::new(p.x + 1, p.y),
-        }
+/// The movement command that sends the droid one step in `dir`.
+fn movement_command(dir: CardinalDirection) -> i64 {
+    match dir {
+        CardinalDirection::North => 1,
+        CardinalDirection::South => 2,
+        CardinalDirection::West => 3,
+        CardinalDirection::East => 4,
     }
 }
 
-impl From<Command> for i64 {
-    fn from(movement_command: Command) -> Self {
-        movement_command as i64
-    }
-}
-
-fn move_droid(computer: &mut intcode::Computer, command: Command) -> Terrain {
-    computer.append_input(&[command.into()]);
+fn move_droid(computer: &mut intcode::Computer, dir: CardinalDirection) -> Terrain {
+    computer.append_input(&[movement_command(dir)]);
     if let RunState::BlockedOnOutput = computer.run() {
         return computer.take_output().unwrap().try_into().unwrap();
     }
@@ -60,18 +44,71 @@ fn move_droid(computer: &mut intcode::Computer, command: Command) -> Terrain {
 
 type Point = Point2D<i32>;
 
+/// The repair droid, which keeps track of where it is.
+#[derive(Clone)]
+struct Droid {
+    computer: intcode::Computer,
+    pos: Point,
+}
+
+impl Robot for Droid {
+    type Place = Point;
+    type Move = CardinalDirection;
+    type Tile = Terrain;
+
+    fn observe(&mut self) -> Observation<Point, CardinalDirection, Terrain> {
+        Observation {
+            place: self.pos,
+            tile: Terrain::Open,
+            moves: CARDINAL_DIRECTIONS.to_vec(),
+        }
+    }
+
+    fn try_move(&mut self, dir: CardinalDirection) -> Outcome<Point, CardinalDirection, Terrain> {
+        let terrain = move_droid(&mut self.computer, dir);
+        if terrain == Terrain::Wall {
+            return Outcome::Blocked;
+        }
+        self.pos = self.pos.cardinal_neighbor(dir);
+        Outcome::Moved(Observation {
+            place: self.pos,
+            tile: terrain,
+            moves: CARDINAL_DIRECTIONS.to_vec(),
+        })
+    }
+
+    fn destination(&self, dir: CardinalDirection) -> Option<Point> {
+        Some(self.pos.cardinal_neighbor(dir))
+    }
+}
+
 #[derive(Debug)]
 struct ShipMap {
-    terrain: HashMap<Point, Terrain>,
+    terrain: SparseGrid<Option<Terrain>>,
 }
 
 impl ShipMap {
     fn get_terrain(&self, point: Point) -> Option<Terrain> {
-        self.terrain.get(&point).copied()
+        *self.terrain.get(point)
     }
 
-    fn set_terrain(&mut self, point: Point, terrain: Terrain) {
-        self.terrain.insert(point, terrain);
+    /// Renders the explored map, marking the droid's starting point with `D`.
+    fn render(&self) -> String {
+        let start = Point::default() - self.terrain.bounds().unwrap().min;
+        let mut rows: Vec<String> = self
+            .terrain
+            .render(|terrain| match terrain {
+                None => ' ',
+                Some(Terrain::Wall) => '#',
+                Some(Terrain::Open) => '.',
+                Some(Terrain::OxygenSystem) => 'O',
+            })
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let x = start.x as usize;
+        rows[start.y as usize].replace_range(x..x + 1, "D");
+        rows.iter().map(|row| format!("{}\n", row)).collect()
     }
 }
 
@@ -82,56 +119,24 @@ struct ExploreResult {
 }
 
 fn explore_ship(program_text: &str) -> ExploreResult {
-    let mut oxygen_system = None;
-    let mut oxygen_system_distance = u32::MAX;
-    let mut map = ShipMap {
-        terrain: HashMap::new(),
-    };
-
-    struct FrontierState {
-        pos: Point,
-        distance: u32,
-        computer: intcode::Computer,
-    }
-
-    let start = Point::default();
-    map.set_terrain(start, Terrain::Open);
-
-    let mut queue = VecDeque::new();
-    queue.push_back(FrontierState {
-        pos: start,
-        distance: 0,
+    let droid = Droid {
         computer: intcode::Computer::parse(program_text),
-    });
+        pos: Point::default(),
+    };
+    let world = explore::explore_by_cloning(droid);
 
-    while let Some(state) = queue.pop_front() {
-        for command in COMMANDS.iter() {
-            let dest = command.apply_to(state.pos);
-            if map.get_terrain(dest).is_some() {
-                continue;
-            }
-            let mut computer = state.computer.clone();
-            let terrain = move_droid(&mut computer, *command);
-            map.set_terrain(dest, terrain);
-            if terrain == Terrain::Wall {
-                continue;
-            }
-            let frontier = FrontierState {
-                pos: dest,
-                distance: state.distance + 1,
-                computer,
-            };
-            if terrain == Terrain::OxygenSystem {
-                oxygen_system = Some(dest);
-                oxygen_system_distance = frontier.distance;
-            }
-            queue.push_back(frontier);
-        }
+    let mut terrain = world.to_sparse_grid(None, |&terrain| Some(terrain));
+    for (id, dir) in world.blocked() {
+        terrain.set(world.place(id).cardinal_neighbor(dir), Some(Terrain::Wall));
     }
 
-    let oxygen_system = oxygen_system.expect("Search should find the oxygen system");
+    let (oxygen_id, &oxygen_system, _) = world
+        .places()
+        .find(|&(_, _, &terrain)| terrain == Terrain::OxygenSystem)
+        .expect("Search should find the oxygen system");
+    let oxygen_system_distance = world.route(world.start(), oxygen_id).unwrap().len() as u32;
     ExploreResult {
-        map,
+        map: ShipMap { terrain },
         oxygen_system,
         oxygen_system_distance,
     }
@@ -177,6 +182,24 @@ fn part_two(input: &str) -> i32 {
 
 fn main() {
     let input = include_str!("../inputs/15.txt");
+    let mut args = pico_args::Arguments::from_env();
+    let show_map = args.contains("--map");
+    let pbm: Option<String> = args.opt_value_from_str("--pbm").unwrap();
+    if show_map || pbm.is_some() {
+        let explored = explore_ship(input);
+        if show_map {
+            print!("{}", explored.map.render());
+        }
+        if let Some(path) = pbm {
+            let mut file = std::fs::File::create(&path).unwrap();
+            explored
+                .map
+                .terrain
+                .write_pbm(&mut file, |terrain| *terrain == Some(Terrain::Wall))
+                .unwrap();
+        }
+        return;
+    }
     assert_eq!(part_one(input), 240);
     assert_eq!(part_two(input), 322);
 }
@@ -185,6 +208,26 @@ fn main() {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_render_map() {
+        let explored = explore_ship(include_str!("../inputs/15.txt"));
+        let map = explored.map.render();
+        let lines: Vec<&str> = map.lines().collect();
+        assert_eq!(lines.len(), 41);
+        assert!(lines.iter().all(|line| line.len() == 41));
+        assert!(lines[0].chars().all(|ch| ch == '#' || ch == ' '));
+        assert_eq!(map.matches('D').count(), 1);
+        assert_eq!(map.matches('O').count(), 1);
+
+        let mut pbm = Vec::new();
+        explored
+            .map
+            .terrain
+            .write_pbm(&mut pbm, |terrain| *terrain == Some(Terrain::Wall))
+            .unwrap();
+        assert!(pbm.starts_with(b"P1\n41 41\n"));
+    }
+
     #[test]
     fn test_main() {
         main();
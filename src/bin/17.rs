@@ -1,147 +1,65 @@
 use core::fmt;
 
 use aoc2019::intcode::{Computer, RunState};
-use aoc2019::point::Point2D;
+use aoc2019::point::{CardinalDirection, Grid, Pattern, Point2D, Turn, YAxis};
 
 type Point = Point2D<i32>;
 
-fn is_scaffold(scaffold: &[Vec<bool>], pos: Point) -> bool {
-    if pos.x < 0 || pos.y < 0 {
-        return false;
-    }
-    if let Some(row) = scaffold.get(pos.y as usize)
-        && let Some(bit) = row.get(pos.x as usize) {
-            return *bit;
-        }
-    false
-}
-
-fn is_alignment_parameter(scaffold: &[Vec<bool>], pos: Point) -> bool {
-    is_scaffold(scaffold, pos)
-        && is_scaffold(scaffold, pos + Direction::North.delta())
-        && is_scaffold(scaffold, pos + Direction::South.delta())
-        && is_scaffold(scaffold, pos + Direction::East.delta())
-        && is_scaffold(scaffold, pos + Direction::West.delta())
+fn is_scaffold(scaffold: &Grid<bool>, pos: Point) -> bool {
+    scaffold.get(pos).copied().unwrap_or(false)
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Direction {
-    North,
-    South,
-    East,
-    West,
+/// Scaffold intersections: a scaffold cell with scaffold on all four sides.
+fn intersections(scaffold: &Grid<bool>) -> Vec<Point> {
+    let plus = Pattern::parse(".#.\n###\n.#.", '.', |ch| (ch == '#').then_some(true)).unwrap();
+    scaffold
+        .find_pattern(&plus, false)
+        .into_iter()
+        .map(|placement| placement.position + Point::new(1, 1))
+        .collect()
 }
 
-impl Direction {
-    fn as_char(&self) -> char {
-        match *self {
-            Direction::North => '^',
-            Direction::South => 'v',
-            Direction::East => '>',
-            Direction::West => '<',
-        }
-    }
-
-    fn delta(&self) -> Point {
-        match *self {
-            Direction::North => Point::new(0, -1),
-            Direction::South => Point::new(0, 1),
-            Direction::East => Point::new(1, 0),
-            Direction::West => Point::new(-1, 0),
-        }
-    }
-
-    fn turn(&self, turn: Turn) -> Direction {
-        match turn {
-            Turn::Left => self.left(),
-            Turn::Right => self.right(),
-        }
-    }
-
-    fn left(&self) -> Direction {
-        match *self {
-            Direction::North => Direction::West,
-            Direction::South => Direction::East,
-            Direction::East => Direction::North,
-            Direction::West => Direction::South,
-        }
-    }
-
-    fn right(&self) -> Direction {
-        match *self {
-            Direction::North => Direction::East,
-            Direction::South => Direction::West,
-            Direction::East => Direction::South,
-            Direction::West => Direction::North,
-        }
-    }
-}
-
-fn get_map(input: &str) -> (Vec<Vec<bool>>, Point, Direction) {
+fn get_map(input: &str) -> (Grid<bool>, Point, CardinalDirection) {
     let mut c = Computer::parse(input);
-    let mut scaffold = Vec::new();
-    let mut robot_pos = None;
-    let mut robot_dir = None;
-
-    let mut row = Vec::new();
+    let mut text = String::new();
     while let RunState::BlockedOnOutput = c.run() {
         let raw_value = c.take_output().unwrap();
         if !(0..128).contains(&raw_value) {
             panic!("unexpected output from computer: {}", raw_value);
         }
         let value = raw_value as u8 as char;
-        match value {
-            '#' => {
-                // scaffold
-                row.push(true);
-            }
-            '.' => {
-                // space
-                row.push(false);
-            }
-            '\n' => {
-                // new line
-                if row.is_empty() {
-                    break; // end of output
-                }
-                scaffold.push(row);
-                row = Vec::new();
-            }
-            '^' => {
-                // robot facing up
-                robot_dir = Some(Direction::North);
-                robot_pos = Some(Point::new(row.len() as i32, scaffold.len() as i32));
-                row.push(true);
-            }
-            _ => panic!("unexpected output from computer: {}", value),
+        if value == '\n' && text.ends_with('\n') {
+            break; // end of output
         }
+        text.push(value);
     }
 
-    (scaffold, robot_pos.unwrap(), robot_dir.unwrap())
+    let map = Grid::parse(&text, |ch| match ch {
+        '#' | '.' | '^' | 'v' | '<' | '>' => Some(ch),
+        _ => None,
+    })
+    .unwrap_or_else(|e| panic!("unexpected output from computer: {}", e));
+    // The robot stands on scaffold.
+    let robot_pos = map.find(|&ch| !matches!(ch, '#' | '.')).unwrap();
+    let robot_dir = CardinalDirection::try_from(map[robot_pos]).unwrap();
+    let scaffold = map.map(|&ch| ch != '.');
+
+    (scaffold, robot_pos, robot_dir)
 }
 
 fn part_one(input: &str) -> i32 {
     let (scaffold, _pos, _dir) = get_map(input);
 
-    let mut sum: i32 = 0;
-    for (y, row) in scaffold.iter().enumerate() {
-        let y: i32 = y.try_into().unwrap();
-        for (x, bit) in row.iter().enumerate() {
-            if *bit {
-                let x: i32 = x.try_into().unwrap();
-                if is_alignment_parameter(&scaffold, Point::new(x, y)) {
-                    sum += x * y;
-                }
-            }
-        }
-    }
-    sum
+    intersections(&scaffold)
+        .iter()
+        .map(|pos| pos.x * pos.y)
+        .sum()
 }
 
-fn walk_forward(scaffold: &[Vec<bool>], pos: &Point, dir: &Direction) -> Point {
+fn walk_forward(scaffold: &Grid<bool>, pos: &Point, dir: &CardinalDirection) -> Point {
     let mut pos = *pos;
     loop {
-        let next = pos + dir.delta();
+        let next = pos + dir.delta(YAxis::Down);
         if !is_scaffold(scaffold, next) {
             return pos;
         }
@@ -149,35 +67,16 @@ fn walk_forward(scaffold: &[Vec<bool>], pos: &Point, dir: &Direction) -> Point {
     }
 }
 
-fn manhattan_distance(a: Point, b: Point) -> i32 {
-    (a.x - b.x).abs() + (a.y - b.y).abs()
-}
-
-fn pick_turn(scaffold: &[Vec<bool>], pos: Point, dir: &Direction) -> Option<Turn> {
-    if is_scaffold(scaffold, pos + dir.left().delta()) {
+fn pick_turn(scaffold: &Grid<bool>, pos: Point, dir: &CardinalDirection) -> Option<Turn> {
+    if is_scaffold(scaffold, pos + dir.turn_left().delta(YAxis::Down)) {
         return Some(Turn::Left);
     }
-    if is_scaffold(scaffold, pos + dir.right().delta()) {
+    if is_scaffold(scaffold, pos + dir.turn_right().delta(YAxis::Down)) {
         return Some(Turn::Right);
     }
     None
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Turn {
-    Left,
-    Right,
-}
-
-impl fmt::Display for Turn {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            Turn::Left => write!(f, "L"),
-            Turn::Right => write!(f, "R"),
-        }
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Movement {
     Distance(u8),
@@ -189,7 +88,10 @@ impl fmt::Display for Movement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Movement::Distance(distance) => write!(f, "{}", distance),
-            Movement::Turn(turn) => write!(f, "{}", turn),
+            Movement::Turn(Turn::Left) => write!(f, "L"),
+            Movement::Turn(Turn::Right) => write!(f, "R"),
+            // The robot only knows left and right, so turn left twice.
+            Movement::Turn(Turn::Around) => write!(f, "L,L"),
             Movement::Routine(num) => {
                 let ch = match num {
                     0 => 'A',
@@ -204,26 +106,13 @@ impl fmt::Display for Movement {
 }
 
 #[allow(dead_code)]
-fn draw(scaffold: &[Vec<bool>], pos: Point, dir: Direction) {
-    for (y, row) in scaffold.iter().enumerate() {
-        let y: i32 = y.try_into().unwrap();
-        for (x, bit) in row.iter().enumerate() {
-            let x: i32 = x.try_into().unwrap();
-            let point = Point::new(x, y);
-            let ch = if point == pos {
-                dir.as_char()
-            } else if *bit {
-                '#'
-            } else {
-                '.'
-            };
-            print!("{}", ch);
-        }
-        println!();
-    }
+fn draw(scaffold: &Grid<bool>, pos: Point, dir: CardinalDirection) {
+    let mut map = scaffold.map(|&bit| if bit { '#' } else { '.' });
+    map[pos] = dir.arrow();
+    print!("{}", map);
 }
 
-fn stumble_around(scaffold: &[Vec<bool>], pos: Point, dir: Direction) -> Vec<Movement> {
+fn stumble_around(scaffold: &Grid<bool>, pos: Point, dir: CardinalDirection) -> Vec<Movement> {
     // Walk the map using a simple heuristic:
     //
     // a) walk forward until we can't
@@ -242,7 +131,7 @@ fn stumble_around(scaffold: &[Vec<bool>], pos: Point, dir: Direction) -> Vec<Mov
         // println!("loop");
         // println!("  pos {:?} dir {:?}", pos, dir);
         let next = walk_forward(scaffold, &pos, &dir);
-        let distance = manhattan_distance(pos, next);
+        let distance = pos.manhattan_distance(next);
         // println!("  walked {} squares to {:?}", distance, next);
         if distance > 0 {
             let d: u8 = distance.try_into().unwrap();
@@ -252,7 +141,7 @@ fn stumble_around(scaffold: &[Vec<bool>], pos: Point, dir: Direction) -> Vec<Mov
         pos = next;
         if let Some(turn) = pick_turn(scaffold, next, &dir) {
             route.push(Movement::Turn(turn));
-            dir = dir.turn(turn);
+            dir = dir.rotate(turn);
         } else {
             break;
         }
@@ -422,6 +311,17 @@ fn main() {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_encode_route() {
+        let route = [
+            Movement::Turn(Turn::Right),
+            Movement::Distance(12),
+            Movement::Turn(Turn::Around),
+            Movement::Routine(1),
+        ];
+        assert_eq!(encode_route(&route), "R,12,L,L,B");
+    }
+
     #[test]
     fn test_main() {
         main();
@@ -1,116 +1,32 @@
 // Code could be much cleaner.  I stopped after it worked.
 //
-// I optimized this until the tests passed in 0.02s, which took a multi-pass
-// approach.
+// I optimized this until the tests passed in well under a second, even in
+// a debug build, which took a multi-pass approach.
 //
 // 1) Create the graph as faithful representation of the input, with length=1
-//    edges to all non-wall positions.
-// 2) Remove all empty nodes from the graph, leaving only the start, key, and
-//    door nodes.  Call this set of nodes N.  This is done by running a BFS
-//    from each node in N until another node in N is reached, recording
-//    the distance to each.
-// 3) Now, call the set of start nodes S and the set of target nodes T where
-//    T is N - S.  Construct a new graph for nodes N where every node has an
-//    edge to T.  Each edge has a distance, the set of keys found while
-//    traversing the edge, and the set of keys required to traverse it.
-// 4) Run Djikstra's shortest path over the graph constructed in 3.
+//    edges to all non-wall positions.  An edge into a door needs that door's
+//    key.
+// 2) Remove the empty nodes that just pass through, leaving the start, key
+//    and door nodes and the junctions between them, by contracting each
+//    corridor into one edge that needs every key its edges did.
+// 3) Hand the graph to the key collection solver, with one agent per
+//    entrance.  It finds the routes from each start and key to each other
+//    key, keeping a longer one only if it needs fewer keys, then runs A*
+//    over the keys held and the agents' positions.
 //
-// Of these steps by far the most important is step 2.  Step 3 is an
-// an optimization that is less important.
+// Of these steps by far the most important is step 2.
 //
-use std::collections::BTreeMap;
-use std::collections::HashSet;
-use std::collections::VecDeque;
-use std::fmt;
-use std::hash::Hash;
+use std::collections::HashMap;
 use std::time::Instant;
 
-use aoc2019::point::Point2D;
-use pathfinding::prelude::dijkstra;
+use aoc2019::graph::{Graph, NodeId};
+use aoc2019::key_collection::{KeyEdge, KeyNode, KeySet, Solver};
+use aoc2019::point::{self, Point2D};
 
 const INPUT: &str = include_str!("../inputs/18.txt");
 
 type Point = Point2D<i32>;
-type Grid = Vec<Vec<Cell>>;
-
-#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
-struct KeySet {
-    mask: u32,
-}
-
-impl fmt::Debug for KeySet {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{{")?;
-        for ch in 'A'..='Z' {
-            let key: KeySet = ch.try_into().unwrap();
-            if self.contains(key) {
-                write!(f, "{}", ch)?
-            }
-        }
-        write!(f, "}}")
-    }
-}
-
-impl KeySet {
-    fn len(&self) -> u32 {
-        self.mask.count_ones()
-    }
-
-    fn is_empty(&self) -> bool {
-        self.mask == 0
-    }
-
-    fn union(&self, other: KeySet) -> KeySet {
-        KeySet {
-            mask: self.mask | other.mask,
-        }
-    }
-
-    fn intersection(&self, other: KeySet) -> KeySet {
-        KeySet {
-            mask: self.mask & other.mask,
-        }
-    }
-
-    fn difference(&self, other: KeySet) -> KeySet {
-        KeySet {
-            mask: self.mask & !other.mask,
-        }
-    }
-
-    fn contains(&self, other: KeySet) -> bool {
-        self.intersection(other) == other
-    }
-}
-
-impl TryFrom<u32> for KeySet {
-    type Error = &'static str;
-
-    fn try_from(mask: u32) -> Result<Self, Self::Error> {
-        let max_mask = 1 << 26;
-        if mask.count_ones() == 1 && mask <= max_mask {
-            Ok(KeySet { mask })
-        } else {
-            Err("invalid key mask value")
-        }
-    }
-}
-
-impl TryFrom<char> for KeySet {
-    type Error = &'static str;
-
-    fn try_from(ch: char) -> Result<Self, Self::Error> {
-        let offset = match ch {
-            'A'..='Z' => (ch as u32) - ('A' as u32),
-            'a'..='z' => (ch as u32) - ('a' as u32),
-            _ => return Err("invalid key character"),
-        };
-        assert!(offset < 26);
-        let mask = 1 << offset;
-        let key: KeySet = mask.try_into()?;
-        Ok(key)
-    }
-}
+type Grid = point::Grid<Cell>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 enum Cell {
@@ -137,6 +53,19 @@ impl Cell {
     }
 }
 
+impl Cell {
+    fn glyph(&self) -> char {
+        let letter = |key: &KeySet, base: u8| (base + key.iter().next().unwrap() as u8) as char;
+        match self {
+            Cell::Key(key) => letter(key, b'a'),
+            Cell::Door(key) => letter(key, b'A'),
+            Cell::Entrance => '@',
+            Cell::Open => '.',
+            Cell::Wall => '#',
+        }
+    }
+}
+
 impl TryFrom<char> for Cell {
     type Error = &'static str;
 
@@ -145,139 +74,65 @@ impl TryFrom<char> for Cell {
             '@' => Ok(Cell::Entrance),
             '#' => Ok(Cell::Wall),
             '.' => Ok(Cell::Open),
-            'A'..='Z' => {
-                let mask: KeySet = (1_u32 << ((ch as u32) - ('A' as u32))).try_into()?;
-                Ok(Cell::Door(mask))
-            }
-            'a'..='z' => {
-                let mask: KeySet = (1_u32 << ((ch as u32) - ('a' as u32))).try_into()?;
-                Ok(Cell::Key(mask))
-            }
+            'A'..='Z' => Ok(Cell::Door(KeySet::single((ch as usize) - ('A' as usize)))),
+            'a'..='z' => Ok(Cell::Key(KeySet::single((ch as usize) - ('a' as usize)))),
             _ => Err("invalid character for cell"),
         }
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
-struct Agent {
-    keys: KeySet,
-    positions: Vec<EdgeNode>,
-}
-
-impl Agent {
-    fn new(keys: KeySet, positions: &[EdgeNode]) -> Agent {
-        Agent {
-            keys,
-            positions: positions.into(),
-        }
-    }
-}
-
-impl fmt::Display for Agent {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // Write strictly the first element into the supplied output
-        // stream: `f`. Returns `fmt::Result` which indicates whether the
-        // operation succeeded or failed. Note that `write!` uses syntax which
-        // is very similar to `println!`.
-        write!(f, "<{:?} ", self.positions)?;
-        write!(f, "{:?}", self.keys)?;
-        write!(f, ">")
-    }
-}
-
 fn parse_grid(input: &str) -> Grid {
     let trace = false;
     if trace {
         println!("input:\n{}", input);
     }
-    let grid: Grid = input
-        .trim()
-        .split_ascii_whitespace()
-        .map(|line| {
-            line.chars()
-                .map(|ch| {
-                    let cell: Cell = ch.try_into().unwrap();
-                    cell
-                })
-                .collect::<Vec<Cell>>()
-        })
-        .collect();
+    Grid::parse(input, |ch| Cell::try_from(ch).ok()).unwrap()
+}
 
-    grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct EdgeNode {
+    pos: Point,
+    cell: Cell,
 }
 
-fn grid_get(grid: &Grid, pos: Point) -> Cell {
-    if pos.y < 0 && pos.x < 0 {
-        return Cell::Wall;
+impl KeyNode for EdgeNode {
+    fn keys(&self) -> KeySet {
+        self.cell.floor_key()
     }
-    grid.get(pos.y as usize)
-        .and_then(|row| row.get(pos.x as usize).copied())
-        .unwrap_or(Cell::Wall)
 }
 
 #[derive(Debug, Clone, Copy)]
-struct ReachableKey {
-    node_id: EdgeNode,
+struct Passage {
     distance: u32,
-    keys: KeySet,
     required_keys: KeySet,
 }
 
-#[derive(Debug)]
-struct GraphEdges {
-    reachable: Vec<ReachableKey>,
-}
-
-type GraphPoint = EdgeNode;
-
-#[derive(Default)]
-struct Graph {
-    nodes: BTreeMap<GraphPoint, GraphEdges>,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
-struct EdgeNode {
-    pos: Point,
-    cell: Cell,
-}
-
-type EdgesFromNodeVec = Vec<(EdgeNode, u32)>;
-type EdgeNodeMap = BTreeMap<EdgeNode, EdgesFromNodeVec>;
-
-impl Graph {
-    fn entry_points(&self) -> Vec<GraphPoint> {
-        self.nodes
-            .keys()
-            .filter_map(|pos| match pos.cell {
-                Cell::Entrance => Some(*pos),
-                _ => None,
-            })
-            .collect()
+impl KeyEdge for Passage {
+    fn distance(&self) -> u32 {
+        self.distance
     }
 
-    fn num_keys(&self) -> u32 {
-        self.nodes
-            .keys()
-            .filter(|node| matches!(node.cell, Cell::Key(_)))
-            .count()
-            .try_into()
-            .expect("key count fits in u32")
+    fn required_keys(&self) -> KeySet {
+        self.required_keys
     }
 }
 
+// The maze as a graph of cells.
+type MazeGraph = Graph<EdgeNode, Passage>;
+
 #[allow(dead_code)]
-fn print_nodes(edges: &EdgeNodeMap, phase: &str) {
+fn print_nodes(edges: &MazeGraph, phase: &str) {
     println!("\nEDGES for phase {}:", phase);
-    for (node, node_edges) in edges.iter() {
+    for (id, node) in edges.nodes() {
         println!("{:?}", node);
-        for edge in node_edges {
-            println!("            {:?}", edge);
+        for (weight, dest) in edges.successors(id) {
+            println!("            {:?}", (edges.get_node(dest).unwrap(), weight));
         }
     }
 }
 
-fn compute_all_edges(grid: &Grid) -> EdgeNodeMap {
-    let mut nodes = BTreeMap::new();
+fn compute_all_edges(grid: &Grid) -> MazeGraph {
+    let mut nodes = MazeGraph::new();
 
     let directions: [Point; 4] = [
         Point::new(0, -1),
@@ -286,32 +141,24 @@ fn compute_all_edges(grid: &Grid) -> EdgeNodeMap {
         Point::new(1, 0),
     ];
 
-    for (y, row) in grid.iter().enumerate() {
-        for (x, &cell) in row.iter().enumerate() {
-            if matches!(cell, Cell::Wall) {
-                continue;
-            }
-            let pos = Point::new(x.try_into().unwrap(), y.try_into().unwrap());
-            let node = EdgeNode { pos, cell };
-            let mut edges = EdgesFromNodeVec::default();
-
-            for dest_pos in directions.iter().map(|dir| pos + *dir) {
-                let dest_cell = grid_get(grid, dest_pos);
-                if matches!(dest_cell, Cell::Wall) {
-                    continue;
-                }
-
-                edges.push((
-                    EdgeNode {
-                        pos: dest_pos,
-                        cell: dest_cell,
-                    },
-                    1,
-                ));
-            }
+    let mut ids = HashMap::new();
+    for (pos, &cell) in grid.cells() {
+        if !matches!(cell, Cell::Wall) {
+            ids.insert(pos, nodes.add_node(EdgeNode { pos, cell }));
+        }
+    }
 
-            if !edges.is_empty() {
-                nodes.insert(node, edges);
+    for (pos, &cell) in grid.cells() {
+        if matches!(cell, Cell::Wall) {
+            continue;
+        }
+        for dest_pos in directions.iter().map(|dir| pos + *dir) {
+            if let Some(&dest) = ids.get(&dest_pos) {
+                let passage = Passage {
+                    distance: 1,
+                    required_keys: grid[dest_pos].door_key(),
+                };
+                nodes.add_edge(ids[&pos], dest, passage);
             }
         }
     }
@@ -319,261 +166,212 @@ fn compute_all_edges(grid: &Grid) -> EdgeNodeMap {
     nodes
 }
 
-fn compress_edges(nodes: &EdgeNodeMap) -> EdgeNodeMap {
-    let keep_nodes: Vec<EdgeNode> = nodes
-        .keys()
-        .filter(|e| e.cell != Cell::Open)
-        .copied()
-        .collect();
-    let mut compressed_nodes = EdgeNodeMap::new();
-    for keep_node in keep_nodes {
-        let mut edges = EdgesFromNodeVec::new();
-        let mut queue = VecDeque::new();
-        let mut visited = HashSet::new();
-        queue.push_back((keep_node, 0_u32));
-        while let Some((src, src_weight)) = queue.pop_front() {
-            visited.insert(src);
-            for (dst, dst_weight) in nodes.get(&src).unwrap().iter() {
-                if visited.contains(dst) {
-                    continue;
-                }
-                let weight = src_weight + dst_weight;
-                if dst.cell == Cell::Open || dst.cell == Cell::Entrance {
-                    queue.push_back((*dst, weight));
-                } else {
-                    edges.push((*dst, weight));
-                }
-            }
-        }
-        compressed_nodes.insert(keep_node, edges);
-    }
+fn compress_edges(nodes: &MazeGraph) -> MazeGraph {
+    // Keep the entrances, keys and doors; junctions stay too.
+    let combine = |a: &Passage, b: &Passage| Passage {
+        distance: a.distance + b.distance,
+        required_keys: a.required_keys.union(b.required_keys),
+    };
+    let (compressed_nodes, _) = nodes.contract(|_, node| node.cell != Cell::Open, combine);
     compressed_nodes
 }
 
-fn compute_edges(grid: &Grid) -> EdgeNodeMap {
-    let edges = compute_all_edges(grid);
-    compress_edges(&edges)
+/// One robot's walk to the next key it collects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Leg {
+    robot: usize,
+    key: char,
+    distance: u32,
+    /// Every cell walked through, from where the robot stood to the key.
+    path: Vec<Point>,
 }
 
-fn compute_reachable(edges: &EdgeNodeMap, start_node: &EdgeNode) -> Vec<ReachableKey> {
-    let start = ReachableKey {
-        node_id: *start_node,
-        keys: start_node.cell.floor_key(),
-        distance: 0,
-        required_keys: KeySet::default(),
-    };
-    let mut queue = VecDeque::new();
-    queue.push_back(start);
-
-    let mut explored = HashSet::new();
-    explored.insert(start.node_id.pos);
-
-    let mut reachable = Vec::new();
-    while let Some(node) = queue.pop_front() {
-        if !node.node_id.cell.floor_key().is_empty() {
-            reachable.push(node);
-        }
-        let node_edges = edges.get(&node.node_id).unwrap();
-
-        for (edge_node, weight) in node_edges.iter() {
-            if !explored.insert(edge_node.pos) {
-                continue;
-            }
-
-            let cell = edge_node.cell;
-            let floor_keys = node.keys.union(cell.floor_key());
-            let required_keys = node.required_keys.union(cell.door_key());
-            queue.push_back(ReachableKey {
-                node_id: *edge_node,
-                keys: floor_keys,
-                distance: node.distance + weight,
-                required_keys,
-            })
-        }
-    }
-
-    reachable
+/// How the robots collect every key in the vault, one leg at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Plan {
+    distance: u32,
+    /// Where each robot starts.
+    starts: Vec<Point>,
+    legs: Vec<Leg>,
 }
 
-fn compute_graph(edges: &EdgeNodeMap) -> Graph {
-    let mut graph: Graph = Graph::default();
-
-    for node in edges.keys() {
-        if matches!(node.cell, Cell::Entrance | Cell::Key(_)) {
-            let reachable = compute_reachable(edges, node);
-            graph.nodes.insert(*node, GraphEdges { reachable });
-        }
-    }
-
-    let trace = false;
-    if trace {
-        println!("as graph:");
-        for (pos, node) in &graph.nodes {
-            println!("   pos: {:?}", pos);
-            for reachable in &node.reachable {
-                println!("      {:?}", reachable);
-            }
-        }
+impl Plan {
+    fn key_order(&self) -> String {
+        self.legs.iter().map(|leg| leg.key).collect()
     }
 
-    graph
-}
-
-fn solve_graph(graph: &Graph) -> u32 {
-    let entry_points = graph.entry_points();
-    let start = Agent::new(KeySet::default(), &entry_points);
-    let num_keys = graph.num_keys();
-
-    let mut successors_call_count = 0;
-    let mut successors_count = 0;
-    let successors = |agent: &Agent| -> Vec<(Agent, u32)> {
-        successors_call_count += 1;
-        if successors_call_count % 100_000 == 0 {
-            println!("[{}] successors of {}", successors_call_count, agent);
+    /// Describes the plan, one line per leg.
+    fn explain(&self) -> String {
+        let mut text = format!(
+            "{} keys in {} steps: {}\n",
+            self.legs.len(),
+            self.distance,
+            self.key_order()
+        );
+        for (i, leg) in self.legs.iter().enumerate() {
+            text += &format!(
+                "{:3}. robot {} walks {} from {} to key {}\n",
+                i + 1,
+                leg.robot,
+                leg.distance,
+                leg.path[0],
+                leg.key
+            );
         }
-        let mut successors = Vec::new();
-        for (positions_index, &position) in agent.positions.iter().enumerate() {
-            let node = graph.nodes.get(&position).unwrap();
-            for reachable in node.reachable.iter() {
-                // Ignore positions where we lack the required keys to get there from here.
-                if !agent.keys.contains(reachable.required_keys) {
-                    continue;
-                }
-
-                // Minmize the state transitions to those that gain one key. If
-                // we gain no keys there is no point in moving to the position. If we gain more
-                // than one key there must be an alternative position that gains only one.
-                let added_keys = reachable.keys.difference(agent.keys);
-                if added_keys.len() != 1 {
-                    continue;
+        text
+    }
+
+    /// Draws the vault with the route overlaid: each cell walked shows the
+    /// last digit of the number of the last leg through it, counting from 1.
+    fn render_overlay(&self, grid: &Grid) -> String {
+        let mut map = grid.map(Cell::glyph);
+        for (i, leg) in self.legs.iter().enumerate() {
+            let digit = char::from_digit((i as u32 + 1) % 10, 10).unwrap();
+            for &pos in &leg.path {
+                if map[pos] == '.' || map[pos].is_ascii_digit() {
+                    map[pos] = digit;
                 }
-
-                let mut successor_agent = agent.clone();
-                successor_agent.keys = agent.keys.union(reachable.keys);
-                successor_agent.positions[positions_index] = reachable.node_id;
-                let distance = reachable.distance;
-                successors.push((successor_agent, distance));
             }
         }
-        successors_count += successors.len();
-        successors
-    };
-
-    let success = |character: &Agent| character.keys.len() == num_keys;
-
-    let path_len = {
-        let (path, path_len) = dijkstra(&start, successors, success).expect("no path found");
-        if false {
-            for character in path {
-                println!("path = {}", character)
+        map.render(|&ch| ch)
+    }
+
+    /// Draws the vault after the first `step` legs: keys collected and the
+    /// doors they open are cleared, the last leg's path is marked with `*`
+    /// and each robot is drawn as `@`.
+    fn render_step(&self, grid: &Grid, step: usize) -> String {
+        let legs = &self.legs[..step];
+        let held: String = legs.iter().map(|leg| leg.key).collect();
+        let mut map = grid.map(|cell| match cell.glyph() {
+            '@' => '.',
+            ch if held.contains(ch.to_ascii_lowercase()) => '.',
+            ch => ch,
+        });
+        if let Some(last) = legs.last() {
+            for &pos in &last.path {
+                map[pos] = '*';
             }
         }
-        path_len
-    };
-    if true {
-        println!(
-            "path length: {:?}\ntook {} calls to successors() and {} states",
-            path_len, successors_call_count, successors_count
-        );
+        let mut robots = self.starts.clone();
+        for leg in legs {
+            robots[leg.robot] = *leg.path.last().unwrap();
+        }
+        for pos in robots {
+            map[pos] = '@';
+        }
+        map.render(|&ch| ch)
     }
-
-    path_len
 }
 
-// Return the grid as a vector of (Point, Cell). This simplifies some code
-// of performance.  When rust stabilizes generators they will be preferable
-// in situations like this.  It is also possible to return an iterator, but
-// the code is much more complex.  See:
-// https://stackoverflow.com/a/30685840/2442218
-fn grid_points(grid: &Grid) -> Vec<(Point, Cell)> {
-    grid.iter()
-        .enumerate()
-        .flat_map(|(y, row)| {
-            row.iter().enumerate().map(move |(x, cell)| {
-                (
-                    Point::new(x.try_into().unwrap(), y.try_into().unwrap()),
-                    *cell,
-                )
-            })
-        })
-        .collect()
-}
-
-fn fix_for_part_two(grid: &mut Grid) {
-    let entrances: Vec<Point> = grid_points(grid)
+/// Plans the collection of every key, where `maze` is the vault as a graph
+/// of cells and `compressed` is the same graph with corridors contracted.
+fn solve_graph(maze: &MazeGraph, compressed: &MazeGraph) -> Plan {
+    let entry_points: Vec<NodeId> = compressed
+        .nodes()
+        .filter(|(_, node)| node.cell == Cell::Entrance)
+        .map(|(id, _)| id)
+        .collect();
+    let solver = Solver::new(compressed, entry_points.iter().copied()).with_heuristic(true);
+    let solution = solver.solve().expect("no path found");
+
+    // Walk each contracted corridor in the full maze to recover the cells.
+    let position = |id| compressed.get_node(id).unwrap().pos;
+    let cell_ids: HashMap<Point, NodeId> = maze.nodes().map(|(id, node)| (node.pos, id)).collect();
+    let legs = solution
+        .moves
         .iter()
-        .filter_map(|(pos, cell)| {
-            if matches!(*cell, Cell::Entrance) {
-                Some(*pos)
-            } else {
-                None
+        .map(|step| {
+            let mut path = vec![position(step.from())];
+            for pair in step.path.windows(2) {
+                let to = cell_ids[&position(pair[1])];
+                let corridor = maze
+                    .bfs(cell_ids[&position(pair[0])], |id| id == to)
+                    .expect("corridor exists");
+                path.extend(
+                    corridor.nodes[1..]
+                        .iter()
+                        .map(|&id| maze.get_node(id).unwrap().pos),
+                );
+            }
+            Leg {
+                robot: step.agent,
+                key: compressed.get_node(step.to()).unwrap().cell.glyph(),
+                distance: step.distance,
+                path,
             }
         })
         .collect();
+    Plan {
+        distance: solution.distance,
+        starts: entry_points.into_iter().map(position).collect(),
+        legs,
+    }
+}
+
+fn fix_for_part_two(grid: &mut Grid) {
+    let entrances: Vec<Point> = grid.find_all(|cell| matches!(cell, Cell::Entrance));
 
     // Do the transform only if there is a single entrance.
     if entrances.len() == 1 {
         let entry_point = entrances[0];
 
         let mut set = |x: i32, y: i32, cell: Cell| {
-            let x: usize = x.try_into().unwrap();
-            let y: usize = y.try_into().unwrap();
-            grid[y][x] = cell;
+            grid[entry_point + Point::new(x, y)] = cell;
         };
 
-        let x = entry_point.x;
-        let y = entry_point.y;
-
-        set(y - 1, x - 1, Cell::Entrance);
-        set(y - 1, x, Cell::Wall);
-        set(y - 1, x + 1, Cell::Entrance);
-        set(y, x - 1, Cell::Wall);
-        set(y, x, Cell::Wall);
-        set(y, x + 1, Cell::Wall);
-        set(y + 1, x - 1, Cell::Entrance);
-        set(y + 1, x, Cell::Wall);
-        set(y + 1, x + 1, Cell::Entrance);
+        set(-1, -1, Cell::Entrance);
+        set(0, -1, Cell::Wall);
+        set(1, -1, Cell::Entrance);
+        set(-1, 0, Cell::Wall);
+        set(0, 0, Cell::Wall);
+        set(1, 0, Cell::Wall);
+        set(-1, 1, Cell::Entrance);
+        set(0, 1, Cell::Wall);
+        set(1, 1, Cell::Entrance);
     }
 }
 
+#[derive(Clone, Copy)]
 enum Part {
     One,
     Two,
 }
 
-fn solve_part(input: &str, part: Part) -> u32 {
-    let mut start = Instant::now();
+fn parse_vault(input: &str, part: Part) -> Grid {
     let mut grid = parse_grid(input);
     if matches!(part, Part::Two) {
         fix_for_part_two(&mut grid);
     }
+    grid
+}
+
+fn solve_part(input: &str, part: Part) -> Plan {
+    let mut start = Instant::now();
+    let grid = parse_vault(input, part);
     let parse_duration = start.elapsed();
 
     start = Instant::now();
-    let nodes = compute_edges(&grid);
+    let maze = compute_all_edges(&grid);
+    let nodes = compress_edges(&maze);
     let edges_duration = start.elapsed();
 
     start = Instant::now();
-    let graph = compute_graph(&nodes);
-    let graph_duration = start.elapsed();
-
-    start = Instant::now();
-    let path_length = solve_graph(&graph);
+    let plan = solve_graph(&maze, &nodes);
     let solve_duration = start.elapsed();
 
     println!(
-        "parse_duration {:#?} edges_duration {:?} graph_duration {:?} solve_duration {:?}",
-        parse_duration, edges_duration, graph_duration, solve_duration
+        "parse_duration {:#?} edges_duration {:?} solve_duration {:?}",
+        parse_duration, edges_duration, solve_duration
     );
-    path_length
+    plan
 }
 
 fn part_one(input: &str) -> u32 {
-    solve_part(input, Part::One)
+    solve_part(input, Part::One).distance
 }
 
 fn part_two(input: &str) -> u32 {
-    solve_part(input, Part::Two)
+    solve_part(input, Part::Two).distance
 }
 
 fn run_part_one() {
@@ -585,18 +383,82 @@ fn run_part_two() {
 }
 
 fn main() {
+    let mut args = pico_args::Arguments::from_env();
+    let explain = args.contains("--explain");
+    let steps = args.contains("--steps");
+    if explain || steps {
+        for part in [Part::One, Part::Two] {
+            let grid = parse_vault(INPUT, part);
+            let plan = solve_part(INPUT, part);
+            print!("{}", plan.explain());
+            if steps {
+                for step in 0..=plan.legs.len() {
+                    println!("\nAfter {} legs:", step);
+                    print!("{}", plan.render_step(&grid, step));
+                }
+            } else {
+                print!("{}", plan.render_overlay(&grid));
+            }
+        }
+        return;
+    }
     run_part_one();
     run_part_two();
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use super::*;
 
     #[test]
-    fn test_key_set_default() {
-        assert_eq!(KeySet::default().mask, 0);
-        assert_eq!(format!("{:?}", KeySet::default()), "{}");
+    fn test_plan() {
+        let input = "#########\n#b.A.@.a#\n#########";
+        let grid = parse_vault(input, Part::One);
+        let plan = solve_part(input, Part::One);
+        assert_eq!(plan.distance, 8);
+        assert_eq!(plan.key_order(), "ab");
+        assert_eq!(plan.starts, vec![Point::new(5, 1)]);
+        assert_eq!(plan.legs[1].path.first(), Some(&Point::new(7, 1)));
+        assert_eq!(plan.legs[1].path.last(), Some(&Point::new(1, 1)));
+        for leg in &plan.legs {
+            assert_eq!(leg.distance as usize, leg.path.len() - 1);
+        }
+        assert_eq!(
+            plan.explain(),
+            "2 keys in 8 steps: ab\n  \
+               1. robot 0 walks 2 from (5, 1) to key a\n  \
+               2. robot 0 walks 6 from (7, 1) to key b\n"
+        );
+        assert_eq!(
+            plan.render_overlay(&grid),
+            "#########\n#b2A2@2a#\n#########\n"
+        );
+        assert_eq!(
+            plan.render_step(&grid, 0),
+            "#########\n#b.A.@.a#\n#########\n"
+        );
+        assert_eq!(
+            plan.render_step(&grid, 1),
+            "#########\n#b...**@#\n#########\n"
+        );
+        assert_eq!(
+            plan.render_step(&grid, 2),
+            "#########\n#@******#\n#########\n"
+        );
+    }
+
+    #[test]
+    fn test_plan_robots() {
+        let input = include_str!("../examples/18d.txt");
+        let plan = solve_part(input, Part::Two);
+        assert_eq!(plan.starts.len(), 4);
+        assert_eq!(plan.key_order().len(), 4);
+        let robots: HashSet<usize> = plan.legs.iter().map(|leg| leg.robot).collect();
+        assert_eq!(robots.len(), 4);
+        let total: u32 = plan.legs.iter().map(|leg| leg.distance).sum();
+        assert_eq!(total, plan.distance);
     }
 
     #[test]
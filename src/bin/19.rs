@@ -1,17 +1,18 @@
 use rand::prelude::*;
-use std::{
-    collections::{HashMap, VecDeque},
-    ops::Range,
-};
+use std::collections::{HashMap, VecDeque};
 
 use aoc2019::{
     intcode::{Computer, RunState},
-    point::Point2D,
+    point::{Grid, Pattern, Point2D, Rect},
 };
 
 const INPUT: &str = include_str!("../inputs/19.txt");
 
-const PART_ONE_RANGE: Range<i64> = 0..50;
+/// The area scanned in part one.
+const PART_ONE_AREA: Rect<i64> = Rect {
+    min: Point2D { x: 0, y: 0 },
+    max: Point2D { x: 49, y: 49 },
+};
 
 type Point = Point2D<i64>;
 
@@ -74,8 +75,8 @@ fn part_one(prober: &mut BeamProber) {
     let mut queue = VecDeque::new();
     while queue.is_empty() {
         let point = Point::new(
-            rng.random_range(PART_ONE_RANGE),
-            rng.random_range(PART_ONE_RANGE),
+            rng.random_range(PART_ONE_AREA.min.x..=PART_ONE_AREA.max.x),
+            rng.random_range(PART_ONE_AREA.min.y..=PART_ONE_AREA.max.y),
         );
         if !prober.seen(&point) && prober.engaged(&point) {
             queue.push_back(point);
@@ -85,10 +86,7 @@ fn part_one(prober: &mut BeamProber) {
     while let Some(point) = queue.pop_front() {
         if prober.engaged(&point) {
             for neighbor in point.neighbors() {
-                if PART_ONE_RANGE.contains(&neighbor.x)
-                    && PART_ONE_RANGE.contains(&neighbor.y)
-                    && !prober.seen(&neighbor)
-                {
+                if PART_ONE_AREA.contains(&neighbor) && !prober.seen(&neighbor) {
                     queue.push_front(neighbor);
                 }
             }
@@ -100,11 +98,11 @@ fn part_one(prober: &mut BeamProber) {
 
 fn part_two(prober: &mut BeamProber) {
     let low_intercept = {
-        let bottom_row = PART_ONE_RANGE.map(|x| Point::new(x, PART_ONE_RANGE.end - 1));
-        let right_column = PART_ONE_RANGE
-            .rev()
-            .skip(1)
-            .map(|y| Point::new(PART_ONE_RANGE.end - 1, y));
+        // Walk the bottom edge of the part one area left to right, then
+        // its right edge upward.
+        let Rect { min, max } = PART_ONE_AREA;
+        let bottom_row = (min.x..=max.x).map(|x| Point::new(x, max.y));
+        let right_column = (min.y..max.y).rev().map(|y| Point::new(max.x, y));
         bottom_row
             .chain(right_column)
             .find(|point| prober.engaged(point))
@@ -135,9 +133,18 @@ fn part_two(prober: &mut BeamProber) {
         y
     };
 
+    // The beam is convex, so the square fits if both its bottom-left
+    // corner, which the search keeps on the beam's lower edge, and its
+    // top-right corner are in the beam.  The pattern leaves every other cell
+    // a wildcard, so matching it probes just those two.
+    let mut square = Grid::new(100, 100, None);
+    square.set(Point::new(0, 99), Some(true));
+    square.set(Point::new(99, 0), Some(true));
+    let square = Pattern::new(square);
     let square_fits = |prober: &mut BeamProber, bottom_left: &Point| -> bool {
-        let top_right = *bottom_left + Point::new(99, -99);
-        top_right.y >= 0 && prober.engaged(&top_right)
+        square.matches_at(*bottom_left + Point::new(0, -99), |p| {
+            (p.y >= 0).then(|| prober.engaged(&p))
+        })
     };
 
     let mut low_x = low_intercept.x;
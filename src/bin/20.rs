@@ -1,8 +1,9 @@
 use std::collections::HashMap;
+use std::io;
 
+use aoc2019::graph::layered::{Layered, LevelNode};
 use aoc2019::graph::{Graph, NodeId};
-use aoc2019::point::{CardinalDirection, Point2D, CARDINAL_DIRECTIONS};
-use pathfinding::prelude::{dijkstra, dijkstra_all};
+use aoc2019::point::{CARDINAL_DIRECTIONS, CardinalDirection, Grid, Point2D};
 
 type Point = Point2D<i32>;
 
@@ -18,11 +19,8 @@ struct Label {
     name: (char, char),
 }
 
-fn read_label(chars: &[Vec<char>], anchor: &Point, dir: CardinalDirection) -> Option<Label> {
-    let get = |pos: Point| -> Option<char> {
-        let (x, y) = pos.as_usize_pair()?;
-        Some(*chars.get(y)?.get(x)?)
-    };
+fn read_label(chars: &Grid<char>, anchor: &Point, dir: CardinalDirection) -> Option<Label> {
+    let get = |pos: Point| chars.get(pos).copied();
 
     let p1 = anchor.cardinal_neighbor(dir);
     let p2 = p1.cardinal_neighbor(dir);
@@ -32,8 +30,8 @@ fn read_label(chars: &[Vec<char>], anchor: &Point, dir: CardinalDirection) -> Op
                 CardinalDirection::North | CardinalDirection::West => (ch1, ch2) = (ch2, ch1),
                 CardinalDirection::South | CardinalDirection::East => {}
             }
-            let max_x: i32 = (chars[0].len() - 1).try_into().unwrap();
-            let max_y: i32 = (chars.len() - 1).try_into().unwrap();
+            let max_x: i32 = (chars.width() - 1).try_into().unwrap();
+            let max_y: i32 = (chars.height() - 1).try_into().unwrap();
             let outer = p2.x == 0 || p2.x == max_x || p2.y == 0 || p2.y == max_y;
             Some(Label {
                 outer,
@@ -64,16 +62,55 @@ impl Maze {
             }
         }
     }
+
+    fn node_label(&self, id: NodeId) -> String {
+        if id == self.start_id {
+            "AA".to_string()
+        } else if id == self.end_id {
+            "ZZ".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    fn edge_label(edge: &Edge) -> String {
+        match edge.height_change {
+            0 => edge.distance.to_string(),
+            change => format!("{} ({:+})", edge.distance, change),
+        }
+    }
+
+    /// Writes the maze as a Graphviz graph, with each edge labelled by its
+    /// distance and, for portals, the change in height.
+    fn write_dot(&self, w: &mut impl io::Write) -> io::Result<()> {
+        self.graph.write_dot(
+            w,
+            |id, _| self.node_label(id),
+            |_, edge| Self::edge_label(edge),
+        )
+    }
+
+    fn write_json(&self, w: &mut impl io::Write) -> io::Result<()> {
+        self.graph.write_json(
+            w,
+            |id, _| self.node_label(id),
+            |_, edge| Self::edge_label(edge),
+        )
+    }
 }
 
 fn parse_maze(input: &str) -> Maze {
     let mut graph: Graph<(), Edge> = Graph::default();
 
-    let chars: Vec<Vec<char>> = input
-        .trim_end()
-        .lines()
-        .map(|line| line.chars().collect::<Vec<char>>())
+    // Editors may strip the spaces that end a line, so pad the lines back
+    // out to the same length.
+    let lines: Vec<&str> = input.trim_end_matches('\n').lines().collect();
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    let padded: Vec<String> = lines
+        .iter()
+        .map(|line| format!("{:<width$}", line))
         .collect();
+    let chars = Grid::parse(&padded.join("\n"), Some).unwrap();
 
     let find_attached_label = |point| {
         for dir in CARDINAL_DIRECTIONS.iter() {
@@ -92,29 +129,26 @@ fn parse_maze(input: &str) -> Maze {
     let mut labels: HashMap<(char, char), Jumps> = HashMap::new();
     let mut nodes: HashMap<Point, NodeId> = HashMap::new();
 
-    for (y, line) in chars.iter().enumerate() {
-        for (x, ch) in line.iter().enumerate() {
-            match ch {
-                ' ' | '#' => {}
-                '.' => {
-                    let point = Point::usize_new(x, y).unwrap();
-                    let node_id = graph.add_node(());
-                    nodes.insert(point, node_id);
-                    if let Some(label) = find_attached_label(point) {
-                        let e = labels.entry(label.name).or_default();
-                        if label.outer {
-                            e.outer_node = Some(node_id);
-                        } else {
-                            e.inner_node = Some(node_id);
-                        }
+    for (point, ch) in chars.cells::<i32>() {
+        match ch {
+            ' ' | '#' => {}
+            '.' => {
+                let node_id = graph.add_node(());
+                nodes.insert(point, node_id);
+                if let Some(label) = find_attached_label(point) {
+                    let e = labels.entry(label.name).or_default();
+                    if label.outer {
+                        e.outer_node = Some(node_id);
+                    } else {
+                        e.inner_node = Some(node_id);
                     }
                 }
-                ch if ch.is_ascii_uppercase() => {
-                    // Handled in read_label() above.
-                }
-                _ => {
-                    unreachable!("unexpected input '{}", ch);
-                }
+            }
+            ch if ch.is_ascii_uppercase() => {
+                // Handled in read_label() above.
+            }
+            _ => {
+                unreachable!("unexpected input '{}", ch);
             }
         }
     }
@@ -191,103 +225,78 @@ fn parse_maze(input: &str) -> Maze {
 
 fn compress_maze(original_maze: &Maze) -> Maze {
     let original_graph = &original_maze.graph;
-    let mut compressed_graph = MazeGraph::new();
-
-    // Map NodeId from the original to the compressed graph.
-    let translate_map = {
-        let mut translate_map = HashMap::new();
-        let mut translate = |graph: &mut MazeGraph, node_id| {
-            *translate_map
-                .entry(node_id)
-                .or_insert_with(|| graph.add_node(()))
-        };
-
-        translate(&mut compressed_graph, original_maze.start_id);
-        translate(&mut compressed_graph, original_maze.end_id);
-        for (source_id, _source_node) in original_graph.nodes() {
-            for (edge, dest_id) in original_graph.successors(source_id) {
-                if edge.height_change != 0 {
-                    let source = translate(&mut compressed_graph, source_id);
-                    let dest = translate(&mut compressed_graph, dest_id);
-                    compressed_graph.add_edge(source, dest, *edge);
-                }
-            }
-        }
-        translate_map
+    // Keep the start, the end and both ends of every portal.
+    let keep = |id: NodeId, _: &()| {
+        id == original_maze.start_id
+            || id == original_maze.end_id
+            || original_graph
+                .successors(id)
+                .any(|(edge, _)| edge.height_change != 0)
     };
-
-    // Consider only those successors that don't traverse a portal.
-    let successors = |id: &NodeId| -> Vec<(NodeId, u32)> {
-        original_graph
-            .successors(*id)
-            .filter(|(edge, _node_id)| edge.height_change == 0)
-            .map(|(edge, node_id)| (node_id, edge.distance))
-            .collect()
+    let combine = |a: &Edge, b: &Edge| Edge {
+        distance: a.distance + b.distance,
+        height_change: a.height_change + b.height_change,
     };
-
-    for (original_from_id, compressed_from_id) in translate_map.iter() {
-        let reachable = dijkstra_all(original_from_id, successors);
-        for (original_to_id, (_, distance)) in reachable.iter() {
-            if let Some(compressed_to_id) = translate_map.get(original_to_id) {
-                let edge = Edge {
-                    distance: *distance,
-                    height_change: 0,
-                };
-                compressed_graph.add_edge(*compressed_from_id, *compressed_to_id, edge);
-            }
-        }
-    }
+    let (graph, translate_map) = original_graph.contract(keep, combine);
 
     Maze {
-        start_id: *translate_map.get(&original_maze.start_id).unwrap(),
-        end_id: *translate_map.get(&original_maze.end_id).unwrap(),
-        graph: compressed_graph,
+        start_id: translate_map[&original_maze.start_id],
+        end_id: translate_map[&original_maze.end_id],
+        graph,
     }
 }
 
 fn shortest_path_part_one(maze: &Maze) -> u32 {
-    let start = maze.start_id;
-    let successors = |id: &NodeId| -> Vec<(NodeId, u32)> {
+    let end = maze.end_id;
+    if let Some(path) =
         maze.graph
-            .successors(*id)
-            .map(|(edge, node_id)| (node_id, edge.distance))
-            .collect()
-    };
-    let success = |id: &NodeId| *id == maze.end_id;
-    if let Some((_path, distance)) = dijkstra(&start, successors, success) {
-        distance
+            .dijkstra(maze.start_id, |edge| Some(edge.distance), |id| id == end)
+    {
+        path.cost
     } else {
         unreachable!("no shortest path found");
     }
 }
 
 fn shortest_path_part_two(maze: &Maze) -> u32 {
-    let start = (0, maze.start_id);
-    let end = (0, maze.end_id);
-    type PartTwoNode = (i32, NodeId);
-    let successors = |(z, node_id): &PartTwoNode| {
-        maze
-            .graph
-            .successors(*node_id)
-            .filter_map(|(edge, successor_node_id)| {
-                let z = z + edge.height_change;
-                if z >= 0 {
-                    Some(((z, successor_node_id), edge.distance))
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<(PartTwoNode, u32)>>()
-    };
-    let success = |n: &PartTwoNode| *n == end;
-    if let Some((_path, distance)) = dijkstra(&start, successors, success) {
-        distance
+    // Each level is a copy of the maze, with the outermost at height 0.
+    // The search needs a cap on the depth to end when there is no path.
+    // Capping it at the number of portals is a heuristic, not a proven
+    // bound: it holds for the puzzle inputs, but a maze whose only way out
+    // goes deeper would be reported as having no path.
+    let portals = maze
+        .graph
+        .edges()
+        .filter(|(_, _, edge, _)| edge.height_change > 0)
+        .count();
+    let levels = Layered::new(&maze.graph, |edge: &Edge| edge.height_change)
+        .min_level(0)
+        .max_level(portals as i32);
+    let start = LevelNode::new(0, maze.start_id);
+    let end = LevelNode::new(0, maze.end_id);
+    if let Some(path) = levels.dijkstra(start, |edge| Some(edge.distance), end) {
+        path.cost
     } else {
-        unreachable!("No shortest path found!");
+        unreachable!("no path found within {} levels", portals);
     }
 }
 
 pub fn main() {
+    let mut args = pico_args::Arguments::from_env();
+    let dot: Option<String> = args.opt_value_from_str("--dot").unwrap();
+    let json: Option<String> = args.opt_value_from_str("--json").unwrap();
+    if dot.is_some() || json.is_some() {
+        let maze = compress_maze(&parse_maze(include_str!("../inputs/20.txt")));
+        if let Some(path) = dot {
+            let mut file = std::fs::File::create(&path).unwrap();
+            maze.write_dot(&mut file).unwrap();
+        }
+        if let Some(path) = json {
+            let mut file = std::fs::File::create(&path).unwrap();
+            maze.write_json(&mut file).unwrap();
+        }
+        return;
+    }
     const EXAMPLE_SMALL: &str = include_str!("../examples/20.small.txt");
     let maze = parse_maze(EXAMPLE_SMALL);
     let maze = compress_maze(&maze);
@@ -305,6 +314,19 @@ mod test {
     const EXAMPLE: &str = include_str!("../examples/20.txt");
     const EXAMPLE_INTERESTING: &str = include_str!("../examples/20.interesting.txt");
 
+    #[test]
+    fn test_write_dot() {
+        let maze = compress_maze(&parse_maze(EXAMPLE_SMALL));
+        let mut dot = Vec::new();
+        maze.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph {\n"));
+        assert_eq!(dot.matches("label=\"AA\"").count(), 1);
+        assert_eq!(dot.matches("label=\"ZZ\"").count(), 1);
+        assert_eq!(dot.matches(" -> ").count(), maze.graph.edge_count());
+        assert!(dot.contains("(+1)") && dot.contains("(-1)"));
+    }
+
     #[test]
     fn test_part_one_small_example() {
         let maze = parse_maze(EXAMPLE_SMALL);
@@ -465,6 +465,7 @@ fn intcode_interpret_springscript(program: &str, speed: Speed) -> i64 {
             RunState::Finished => {
                 panic!("springscript failed: program:\n{}", program);
             }
+            RunState::Trapped => unreachable!("no extensions are registered"),
         }
     }
 }
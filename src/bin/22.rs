@@ -1,3 +1,5 @@
+use aoc2019::math;
+
 const INPUT: &str = include_str!("../inputs/22.txt");
 
 enum Deal {
@@ -34,6 +36,90 @@ impl Deal {
     }
 }
 
+/// A shuffle as a map from each card's position before it to its position
+/// after: `x -> a * x + b` modulo the number of cards.  Every technique is
+/// such a map, and so is any sequence of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Shuffle {
+    a: i128,
+    b: i128,
+    len: i128,
+}
+
+impl Shuffle {
+    /// The shuffle that leaves a deck of `len` cards alone.
+    fn identity(len: u64) -> Shuffle {
+        Shuffle {
+            a: 1,
+            b: 0,
+            len: len as i128,
+        }
+    }
+
+    fn new(a: i128, b: i128, len: i128) -> Shuffle {
+        Shuffle {
+            a: a.rem_euclid(len),
+            b: b.rem_euclid(len),
+            len,
+        }
+    }
+
+    fn from_deal(deal: &Deal, len: u64) -> Shuffle {
+        let len = len as i128;
+        match *deal {
+            Deal::IntoNewStack => Shuffle::new(-1, -1, len),
+            Deal::WithIncrement(increment) => Shuffle::new(increment as i128, 0, len),
+            Deal::Cut(n) => Shuffle::new(1, -(n as i128), len),
+        }
+    }
+
+    fn from_deals(deals: &[Deal], len: u64) -> Shuffle {
+        deals.iter().fold(Shuffle::identity(len), |shuffle, deal| {
+            shuffle.then(Shuffle::from_deal(deal, len))
+        })
+    }
+
+    /// This shuffle followed by `next`.
+    fn then(self, next: Shuffle) -> Shuffle {
+        assert_eq!(self.len, next.len, "shuffles of different decks");
+        Shuffle::new(next.a * self.a, next.a * self.b + next.b, self.len)
+    }
+
+    /// This shuffle done `times` times over, by repeated squaring.
+    fn repeat(self, mut times: u64) -> Shuffle {
+        let mut result = Shuffle::identity(self.len as u64);
+        let mut square = self;
+        while times > 0 {
+            if times & 1 == 1 {
+                result = result.then(square);
+            }
+            square = square.then(square);
+            times >>= 1;
+        }
+        result
+    }
+
+    /// The shuffle that undoes this one.  Panics if this one is not a
+    /// permutation, which happens when dealing with an increment that shares
+    /// a factor with the number of cards.
+    fn inverse(self) -> Shuffle {
+        let a = math::mod_inv(self.a, self.len)
+            .unwrap_or_else(|| panic!("{} has no inverse modulo {}", self.a, self.len));
+        Shuffle::new(a, -a * self.b, self.len)
+    }
+
+    /// Where `card` ends up, for a deck that starts in factory order.
+    fn position_of(&self, card: u64) -> u64 {
+        ((self.a * card as i128 + self.b) % self.len) as u64
+    }
+
+    /// Which card ends up at `position`, for a deck that starts in factory
+    /// order.
+    fn card_at(&self, position: u64) -> u64 {
+        self.inverse().position_of(position)
+    }
+}
+
 fn parse_deals(input: &str) -> Vec<Deal> {
     input
         .lines()
@@ -72,12 +158,22 @@ fn part_one() {
         .find(|(_, e)| **e == 2019)
         .map(|(i, _)| i);
     assert_eq!(pos, Some(1234));
+
+    let shuffle = Shuffle::from_deals(&parse_deals(INPUT), 10_007);
+    assert_eq!(shuffle.position_of(2019), 1234);
+}
+
+fn part_two() -> u64 {
+    const CARDS: u64 = 119_315_717_514_047;
+    const SHUFFLES: u64 = 101_741_582_076_661;
+    Shuffle::from_deals(&parse_deals(INPUT), CARDS)
+        .repeat(SHUFFLES)
+        .card_at(2020)
 }
 
 fn main() {
     part_one();
-
-    // Didn't do part two, which was more about math than programming.
+    assert_eq!(part_two(), 7_757_787_935_983);
 }
 
 #[cfg(test)]
@@ -135,8 +231,48 @@ cut -1
         assert_eq!(result, vec![9, 2, 5, 8, 1, 4, 7, 0, 3, 6]);
     }
 
+    /// Checks the shuffle against the simulation of `input` on `len` cards.
+    fn check_shuffle(len: i16, input: &str) {
+        let deck = run_shuffles(len, input);
+        let shuffle = Shuffle::from_deals(&parse_deals(input), len as u64);
+        for (position, &card) in deck.iter().enumerate() {
+            assert_eq!(shuffle.card_at(position as u64), card as u64);
+            assert_eq!(shuffle.position_of(card as u64), position as u64);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_examples() {
+        check_shuffle(
+            10,
+            "deal with increment 7\ndeal into new stack\ndeal into new stack\n",
+        );
+        check_shuffle(10, "cut 6\ndeal with increment 7\ndeal into new stack\n");
+        check_shuffle(10, "deal with increment 7\ndeal with increment 9\ncut -2\n");
+        check_shuffle(10_007, INPUT);
+    }
+
+    #[test]
+    fn test_shuffle_repeat() {
+        let deals = parse_deals(INPUT);
+        let mut deck: Vec<i16> = (0..10_007).collect();
+        let shuffle = Shuffle::from_deals(&deals, 10_007);
+        for times in 0..5 {
+            assert_eq!(shuffle.repeat(times).card_at(2020), deck[2020] as u64);
+            for deal in &deals {
+                deck = deal.deal(&deck);
+            }
+        }
+        assert_eq!(shuffle.then(shuffle.inverse()), Shuffle::identity(10_007));
+    }
+
     #[test]
     fn test_part_one() {
         part_one();
     }
+
+    #[test]
+    fn test_part_two() {
+        assert_eq!(part_two(), 7_757_787_935_983);
+    }
 }
@@ -59,6 +59,7 @@ fn compute_part_one() -> i64 {
             RunState::Finished => {
                 panic!("Computer {} finished unexpectedly.", i);
             }
+            RunState::Trapped => unreachable!("no extensions are registered"),
         }
     }
     unreachable!();
@@ -120,6 +121,7 @@ fn compute_part_two() -> i64 {
                 RunState::Finished => {
                     panic!("Computer {} finished unexpectedly.", i);
                 }
+                RunState::Trapped => unreachable!("no extensions are registered"),
             }
         }
 
@@ -1,8 +1,13 @@
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     ops::Range,
+    rc::Rc,
 };
 
+use aoc2019::graph::layered::{Layered, LevelNode};
+use aoc2019::graph::{Graph, NodeId};
+use aoc2019::point::{CARDINAL_DIRECTIONS, Point2D, Point3D, Rect, YAxis};
+
 const INPUT: &str = include_str!("../inputs/24.txt");
 const TILE_RANGE: Range<u32> = 0..5;
 
@@ -130,38 +135,60 @@ impl Eris {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Pos {
-    x: i32,
-    y: i32,
-    z: i32,
+/// A tile position (x, y) on recursion level z.
+type Pos = Point3D<i32>;
+
+/// The tiles of one level as a graph, with an edge to each adjacent tile.
+/// The middle tile is missing: edges into it lead instead to the row or
+/// column of the level nested inside (z + 1) that touches it, and edges off
+/// the outer edge lead to the tile next to the middle of the level outside
+/// (z - 1).  Edge data is the change in level.
+struct TileGraph {
+    graph: Graph<Point2D<i32>, i32>,
+    ids: HashMap<Point2D<i32>, NodeId>,
 }
 
-impl Pos {
-    fn new(x: i32, y: i32, z: i32) -> Pos {
-        Pos { x, y, z }
-    }
-
-    fn north(&self) -> Pos {
-        Pos::new(self.x, self.y - 1, self.z)
-    }
-
-    fn south(&self) -> Pos {
-        Pos::new(self.x, self.y + 1, self.z)
-    }
-
-    fn west(&self) -> Pos {
-        Pos::new(self.x - 1, self.y, self.z)
+impl TileGraph {
+    fn new() -> TileGraph {
+        let bounds = Rect::new(Point2D::new(0, 0), Point2D::new(4, 4));
+        let middle = Point2D::new(2, 2);
+        let mut graph = Graph::new();
+        let ids: HashMap<Point2D<i32>, NodeId> = bounds
+            .points()
+            .filter(|&pos| pos != middle)
+            .map(|pos| (pos, graph.add_node(pos)))
+            .collect();
+        for pos in bounds.points().filter(|&pos| pos != middle) {
+            for dir in CARDINAL_DIRECTIONS {
+                let delta: Point2D<i32> = dir.delta(YAxis::Down);
+                let next = pos + delta;
+                if next == middle {
+                    // The edge of the nested grid on the side we came from.
+                    for inner in bounds
+                        .points()
+                        .filter(|&inner| !bounds.contains(&(inner - delta)))
+                    {
+                        graph.add_edge(ids[&pos], ids[&inner], 1);
+                    }
+                } else if bounds.contains(&next) {
+                    graph.add_edge(ids[&pos], ids[&next], 0);
+                } else {
+                    graph.add_edge(ids[&pos], ids[&(middle + delta)], -1);
+                }
+            }
+        }
+        TileGraph { graph, ids }
     }
 
-    fn east(&self) -> Pos {
-        Pos::new(self.x + 1, self.y, self.z)
+    fn levels(&self) -> Layered<'_, Point2D<i32>, i32, impl Fn(&i32) -> i32> {
+        Layered::new(&self.graph, |&level_change: &i32| level_change)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 struct RecursiveEris {
     levels: BTreeMap<i32, Tile>,
+    tiles: Rc<TileGraph>,
 }
 
 impl std::fmt::Display for RecursiveEris {
@@ -179,7 +206,10 @@ impl RecursiveEris {
         let tile = Tile::parse(input);
         let mut levels = BTreeMap::new();
         levels.insert(0, tile);
-        RecursiveEris { levels }
+        RecursiveEris {
+            levels,
+            tiles: Rc::new(TileGraph::new()),
+        }
     }
 
     fn step(&self) -> RecursiveEris {
@@ -187,31 +217,30 @@ impl RecursiveEris {
 
         // Add an empty level above and below the current levels.
         if let Some((level, tile)) = next.levels.first_key_value()
-            && *tile != Tile::new() {
-                next.levels.insert(level - 1, Tile::new());
-            }
+            && *tile != Tile::new()
+        {
+            next.levels.insert(level - 1, Tile::new());
+        }
         if let Some((level, tile)) = next.levels.last_key_value()
-            && *tile != Tile::new() {
-                next.levels.insert(level + 1, Tile::new());
-            }
+            && *tile != Tile::new()
+        {
+            next.levels.insert(level + 1, Tile::new());
+        }
 
+        let tiles = &self.tiles;
+        let levels = tiles.levels();
         let keys: Vec<i32> = next.levels.keys().copied().collect();
         for z in keys.into_iter() {
-            for y in 0..5 {
-                for x in 0..5 {
-                    if x == 2 && y == 2 {
-                        continue;
-                    }
-                    let pos = Pos::new(x, y, z);
-                    let count = self.count_adjacent(pos);
-                    let bug = self.is_bug(pos);
-                    // println!("{:?} count:{} bug:{}", pos, count, bug);
-                    if bug && count != 1 {
-                        next.clear_bug(pos);
-                    }
-                    if !bug && (1..=2).contains(&count) {
-                        next.spawn_bug(pos);
-                    }
+            for (&xy, &id) in tiles.ids.iter() {
+                let pos = Pos::new(xy.x, xy.y, z);
+                let count = self.count_adjacent(&levels, LevelNode::new(z, id));
+                let bug = self.is_bug(pos);
+                // println!("{:?} count:{} bug:{}", pos, count, bug);
+                if bug && count != 1 {
+                    next.clear_bug(pos);
+                }
+                if !bug && (1..=2).contains(&count) {
+                    next.spawn_bug(pos);
                 }
             }
         }
@@ -222,53 +251,21 @@ impl RecursiveEris {
         self.levels.values().map(|level| level.count_bugs()).sum()
     }
 
-    fn count_adjacent(&self, pos: Pos) -> u32 {
-        let get = |pos| {
-            if self.is_bug(pos) {
-                1
-            } else {
-                0
-            }
-        };
-        let mut sum = 0;
-        let outer_z = pos.z - 1;
-        let inner_z = pos.z + 1;
-        if pos.y == 0 {
-            sum += get(Pos::new(2, 1, outer_z));
-        } else {
-            sum += get(pos.north());
-        }
-        if pos.y == 4 {
-            sum += get(Pos::new(2, 3, outer_z));
-        } else {
-            sum += get(pos.south());
-        }
-        if pos.x == 0 {
-            sum += get(Pos::new(1, 2, outer_z));
-        } else {
-            sum += get(pos.west());
-        }
-        if pos.x == 4 {
-            sum += get(Pos::new(3, 2, outer_z));
-        } else {
-            sum += get(pos.east());
-        }
-        match (pos.x, pos.y) {
-            (2, 1) => {
-                sum += (0..5).map(|xx| get(Pos::new(xx, 0, inner_z))).sum::<u32>();
-            }
-            (1, 2) => {
-                sum += (0..5).map(|yy| get(Pos::new(0, yy, inner_z))).sum::<u32>();
-            }
-            (2, 3) => {
-                sum += (0..5).map(|xx| get(Pos::new(xx, 4, inner_z))).sum::<u32>();
-            }
-            (3, 2) => {
-                sum += (0..5).map(|yy| get(Pos::new(4, yy, inner_z))).sum::<u32>();
-            }
-            _ => {}
-        }
-        sum
+    fn count_adjacent<F: Fn(&i32) -> i32>(
+        &self,
+        levels: &Layered<'_, Point2D<i32>, i32, F>,
+        tile: LevelNode,
+    ) -> u32 {
+        let graph = levels.graph();
+        levels
+            .neighbors(tile)
+            .filter(|neighbor| {
+                let xy = graph.get_node(neighbor.node).unwrap();
+                self.is_bug(Pos::new(xy.x, xy.y, neighbor.level))
+            })
+            .count()
+            .try_into()
+            .unwrap()
     }
 
     fn clear_bug(&mut self, pos: Pos) {
@@ -1,27 +1,15 @@
-use std::{
-    collections::{BTreeMap, BTreeSet},
-    io,
-};
+use std::{collections::BTreeSet, io};
 
 use aoc2019::{
-    graph::{Graph, NodeId},
+    explore::{self, Backtrack, Observation, Outcome, Robot, WorldMap},
     intcode::{Computer, RunState},
     point::CardinalDirection,
 };
 use itertools::Itertools;
-use pathfinding::prelude::bfs;
 use regex::Regex;
 
 const INTCODE_PROGRAM: &str = include_str!("../inputs/25.txt");
 
-fn readline() -> String {
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .expect("failed reading stdin");
-    input
-}
-
 #[derive(Debug, Default)]
 struct RoomContents {
     name: String,
@@ -64,51 +52,13 @@ fn parse_output(output: &str) -> Vec<RoomContents> {
     ret
 }
 
-type DroidGraph = Graph<(), CardinalDirection>;
-
-fn compute_next_move(
-    graph: &DroidGraph,
-    node: NodeId,
-    target_node: NodeId,
-) -> (CardinalDirection, NodeId) {
-    println!("computing path to {:?}", target_node);
-    let path = bfs(
-        &None,
-        |nn| {
-            let id = match *nn {
-                None => node,
-                Some((_, id)) => id,
-            };
-            // println!("BFS: successors of {:?} (aka {:?})", nn, id);
-            graph.successors(id).map(|(&dir, id)| Some((dir, id)))
-        },
-        |nn| {
-            if let Some((_, id)) = *nn {
-                id == target_node
-            } else {
-                false
-            }
-        },
-    )
-    .expect("shortest path exists");
-    println!("path is: {:?}", path);
-
-    path.iter()
-        .find_map(|nn| if nn.is_some() { *nn } else { None })
-        .unwrap()
-}
-
-type Movement = (NodeId, CardinalDirection);
+/// The rooms of the ship, each holding the items first seen there.
+type ShipMap = WorldMap<String, CardinalDirection, Vec<String>>;
 
-struct PartOneDroid {
+/// The droid, which picks up every item it safely can as it explores.
+struct Droid {
     computer: Computer,
-    nodes: BTreeMap<String, NodeId>,
-    graph: Graph<(), CardinalDirection>,
-    unexplored: Vec<Movement>,
-    from: Option<Movement>,
-    security_checkpoints: BTreeSet<NodeId>,
     inventory: BTreeSet<String>,
-    leave_items: bool,
 }
 
 fn print_output(output: &str) {
@@ -117,36 +67,19 @@ fn print_output(output: &str) {
     }
 }
 
-impl PartOneDroid {
+impl Droid {
     fn new() -> Self {
         Self {
             computer: Computer::parse(INTCODE_PROGRAM),
-            nodes: BTreeMap::new(),
-            graph: Graph::new(),
-            unexplored: Vec::new(),
-            from: None,
-            security_checkpoints: BTreeSet::new(),
             inventory: BTreeSet::new(),
-            leave_items: false,
-        }
-    }
-
-    fn add_edge(&mut self, from: NodeId, to: NodeId, dir: CardinalDirection) {
-        // Avoid adding duplicate edges.
-        for (&d, id) in self.graph.successors(from) {
-            assert_eq!(dir == d, to == id);
-            if id == to {
-                return;
-            }
         }
-        self.graph.add_edge(from, to, dir);
     }
 
     fn issue_command(&mut self, command: &str) -> String {
         println!("Issue command: {}", command);
         self.computer.append_str(command);
         match self.computer.run() {
-            state @ (RunState::BlockedOnInput | RunState::Finished) => {
+            state @ (RunState::BlockedOnInput | RunState::Finished | RunState::Trapped) => {
                 unreachable!("unexpected run state after issue_command: {:?}", state)
             }
             RunState::BlockedOnOutput => {
@@ -209,99 +142,27 @@ impl PartOneDroid {
         self.inventory.remove(item);
     }
 
-    fn handle_output(&mut self, str: &str) -> Option<i32> {
-        let contents = parse_output(str);
-        println!("{:?}", contents);
-
-        for contents in contents {
-            let current_node_id = *self
-                .nodes
-                .entry(contents.name)
-                .or_insert_with(|| self.graph.add_node(()));
-
-            if contents.ejected_back {
-                self.security_checkpoints.insert(current_node_id);
-            }
-            if let Some((from_node, from_dir)) = self.from {
-                self.add_edge(from_node, current_node_id, from_dir);
-                self.add_edge(current_node_id, from_node, from_dir.negate());
-                self.from = None;
-            }
-
-            let explored_dirs: Vec<CardinalDirection> = self
-                .graph
-                .successors(current_node_id)
-                .map(|(dir, _)| *dir)
-                .collect();
-
-            if contents.ejected_back {
-                continue;
-            }
-
-            // Greedily pick everything up as soon as we see it, unless
-            // we're cracking security
-            if !self.leave_items {
-                for item in contents.items {
-                    self.take(&item);
-                }
-            }
-
-            for dir in contents.doors.iter().copied() {
-                if !explored_dirs.contains(&dir)
-                    && !self.unexplored.contains(&(current_node_id, dir))
-                {
-                    println!("Push as unexplored: {:?}", (current_node_id, dir));
-                    self.unexplored.push((current_node_id, dir));
-                    continue;
-                }
-            }
-
-            println!("unexplored: {:?}", self.unexplored);
-            println!("at: {:?}", current_node_id);
-
-            let dir = if let Some(&(target_node, target_dir)) = self.unexplored.last() {
-                if target_node == current_node_id {
-                    // We are already there so just move in the desired
-                    // direction.
-                    self.unexplored.pop();
-                    target_dir
-                } else {
-                    let (dir, id) = compute_next_move(&self.graph, current_node_id, target_node);
-                    if id == target_node {
-                        self.unexplored.pop();
-                    }
-                    dir
-                }
-            } else {
-                assert_eq!(1, self.security_checkpoints.len());
-                let security_id = *self.security_checkpoints.first().unwrap();
-                let (dir, id) = compute_next_move(&self.graph, current_node_id, security_id);
-                if id == security_id {
-                    // Exploration is done and we've reached the room adjacent
-                    // to the pressure plate. Hack it and get the code.
-                    return Some(self.hack_pressure_plate(current_node_id, dir, security_id));
-                }
-                dir
-            };
-            let cmd = match dir {
-                CardinalDirection::North => "north\n",
-                CardinalDirection::South => "south\n",
-                CardinalDirection::East => "east\n",
-                CardinalDirection::West => "west\n",
-            };
-            println!("Issue command: {}", cmd);
-            self.computer.append_str(cmd);
-            self.from = Some((current_node_id, dir))
+    /// Handles the droid's report after it enters a room, picking up what
+    /// is there unless the droid was ejected back.
+    fn arrive(&mut self, output: &str) -> Outcome<String, CardinalDirection, Vec<String>> {
+        let mut rooms = parse_output(output);
+        if rooms.iter().any(|room| room.ejected_back) {
+            return Outcome::Blocked;
         }
-        None
+        let room = rooms.pop().unwrap();
+        for item in &room.items {
+            self.take(item);
+        }
+        Outcome::Moved(Observation {
+            place: room.name,
+            tile: room.items,
+            moves: room.doors,
+        })
     }
 
-    fn hack_pressure_plate(
-        &mut self,
-        _current_node_id: NodeId,
-        dir: CardinalDirection,
-        _security_id: NodeId,
-    ) -> i32 {
+    /// Tries carrying each combination of items across the pressure plate
+    /// beyond the security checkpoint, which lies in direction `dir`.
+    fn hack_pressure_plate(&mut self, dir: CardinalDirection) -> i32 {
         let code_re = Regex::new("by typing (\\d+) on the keypad").unwrap();
 
         for permutation in self.inventory.clone().into_iter().powerset() {
@@ -338,32 +199,90 @@ impl PartOneDroid {
     }
 }
 
-fn part_one() -> i32 {
-    let mut this = PartOneDroid::new();
+impl Robot for Droid {
+    type Place = String;
+    type Move = CardinalDirection;
+    type Tile = Vec<String>;
+
+    fn observe(&mut self) -> Observation<String, CardinalDirection, Vec<String>> {
+        assert_eq!(self.computer.run(), RunState::BlockedOnOutput);
+        let output = self.computer.read_ascii_string().unwrap();
+        print_output(&output);
+        match self.arrive(&output) {
+            Outcome::Moved(observation) => observation,
+            Outcome::Blocked => unreachable!("the droid starts outside security"),
+        }
+    }
 
-    loop {
-        match this.computer.run() {
-            RunState::BlockedOnInput => {
-                this.computer.append_str(&readline());
-            }
-            RunState::BlockedOnOutput => {
-                if let Some(str) = this.computer.read_ascii_string() {
-                    print_output(&str);
-                    if let Some(code) = this.handle_output(&str) {
-                        return code;
-                    }
-                } else {
-                    unreachable!("Non-ASCII output emitted by computer, unexpectedly!");
-                }
-            }
-            RunState::Finished => {
-                panic!("Computer finished unexpectedly.");
+    fn try_move(
+        &mut self,
+        dir: CardinalDirection,
+    ) -> Outcome<String, CardinalDirection, Vec<String>> {
+        let output = self.issue_walk(dir);
+        self.arrive(&output)
+    }
+}
+
+impl Backtrack for Droid {
+    fn reverse(&self, dir: CardinalDirection) -> CardinalDirection {
+        dir.negate()
+    }
+}
+
+/// Writes the rooms explored as a Graphviz graph, with the security
+/// checkpoint marked.
+fn write_dot(map: &ShipMap, w: &mut impl io::Write) -> io::Result<()> {
+    let checkpoints: BTreeSet<&str> = map
+        .blocked()
+        .map(|(id, _)| map.place(id).as_str())
+        .collect();
+    map.write_dot(
+        w,
+        |name, _| {
+            if checkpoints.contains(name.as_str()) {
+                format!("{} (checkpoint)", name)
+            } else {
+                name.to_string()
             }
+        },
+        |dir| format!("{:?}", dir).to_lowercase(),
+    )
+}
+
+fn part_one() -> i32 {
+    crack_security().0
+}
+
+/// Explores the ship and passes the pressure plate, returning the code and
+/// the map of the ship.
+fn crack_security() -> (i32, ShipMap) {
+    let mut droid = Droid::new();
+    let map = explore::explore_by_backtracking(&mut droid);
+
+    // The only way the droid is refused is past the security checkpoint.
+    let blocked = map.blocked().collect_vec();
+    assert_eq!(1, blocked.len());
+    let (checkpoint, dir) = blocked[0];
+    for step in map
+        .route(map.start(), checkpoint)
+        .expect("checkpoint is reachable")
+    {
+        if let Outcome::Blocked = droid.try_move(step) {
+            panic!("the droid could not retrace its steps");
         }
     }
+    (droid.hack_pressure_plate(dir), map)
 }
 
 fn main() {
+    let mut args = pico_args::Arguments::from_env();
+    let dot: Option<String> = args.opt_value_from_str("--dot").unwrap();
+    if let Some(path) = dot {
+        let (_, map) = crack_security();
+        let mut file = std::fs::File::create(&path).unwrap();
+        write_dot(&map, &mut file).unwrap();
+        return;
+    }
     assert_eq!(part_one(), 16410);
 }
 
@@ -0,0 +1,76 @@
+// Finds values for unknown memory cells or inputs of an Intcode program that
+// make it produce a target value.  For example, day 2 part two is:
+//
+//   cargo run --bin intcode-goal -- --program src/inputs/02.txt \
+//       --unknown mem:1=0..=99 --unknown mem:2=0..=99 \
+//       --observe mem:0 --target 19690720
+
+use std::ffi::OsString;
+
+use anyhow::{Result, bail};
+use aoc2019::goal_seek::{self, Observation, Unknown};
+use aoc2019::intcode::Computer;
+use aoc2019::intcode::image::ProgramImage;
+
+const USAGE: &str = "\
+usage: intcode-goal --program FILE --unknown LOC=LOW..=HIGH [--unknown ...]
+                    --observe OBS --target N
+
+  LOC is mem:<address> or in:<n> (the n-th input value, from 0)
+  OBS is mem:<address> (after the program halts) or out:<n>";
+
+fn run(args: Vec<OsString>) -> Result<String> {
+    let mut args = pico_args::Arguments::from_vec(args);
+    if args.contains(["-h", "--help"]) {
+        return Ok(USAGE.to_string());
+    }
+    let program_path: String = args.value_from_str("--program")?;
+    let unknowns: Vec<Unknown> = args.values_from_str("--unknown")?;
+    let observation: Observation = args.value_from_str("--observe")?;
+    let target: i64 = args.value_from_str("--target")?;
+    let remaining = args.finish();
+    if !remaining.is_empty() {
+        bail!("unexpected arguments: {:?}\n\n{}", remaining, USAGE);
+    }
+
+    let image = ProgramImage::load(&std::fs::read(&program_path)?)?;
+    let program = Computer::from_image(&image);
+    let Some(solution) = goal_seek::solve(&program, &unknowns, observation, target)? else {
+        bail!("no values within the given ranges produce {}", target);
+    };
+
+    let mut out = format!("solved by {:?}\n", solution.method);
+    for (unknown, value) in unknowns.iter().zip(&solution.values) {
+        out += &format!("{} = {}\n", unknown.location, value);
+    }
+    Ok(out)
+}
+
+fn main() -> Result<()> {
+    print!("{}", run(std::env::args_os().skip(1).collect())?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<OsString> {
+        s.split_whitespace().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_day_two() {
+        let out = run(args(
+            "--program src/inputs/02.txt --unknown mem:1=0..=99 --unknown mem:2=0..=99 \
+             --observe mem:0 --target 19690720",
+        ))
+        .unwrap();
+        assert_eq!(out, "solved by Affine\nmem:1 = 64\nmem:2 = 17\n");
+    }
+
+    #[test]
+    fn test_missing_target() {
+        assert!(run(args("--program src/inputs/02.txt --observe mem:0")).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+// Converts an Intcode program between the formats in
+// `aoc2019::intcode::image`.  The input format is detected automatically.
+//
+//   cargo run --bin intcode-image -- --to binary src/inputs/25.txt 25.icb
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use aoc2019::intcode::image::ProgramImage;
+
+const USAGE: &str = "\
+usage: intcode-image --to comma|commented|binary INPUT OUTPUT";
+
+fn convert(bytes: &[u8], format: &str) -> Result<Vec<u8>> {
+    let image = ProgramImage::load(bytes)?;
+    let mut out = Vec::new();
+    match format {
+        "comma" => image.write_comma(&mut out)?,
+        "commented" => image.write_commented(&mut out, 8)?,
+        "binary" => image.write_binary(&mut out)?,
+        _ => bail!("unknown format \"{}\"\n\n{}", format, USAGE),
+    }
+    Ok(out)
+}
+
+fn run(args: Vec<OsString>) -> Result<()> {
+    let mut args = pico_args::Arguments::from_vec(args);
+    if args.contains(["-h", "--help"]) {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let format: String = args.value_from_str("--to")?;
+    let input: PathBuf = args.free_from_str()?;
+    let output: PathBuf = args.free_from_str()?;
+    let remaining = args.finish();
+    if !remaining.is_empty() {
+        bail!("unexpected arguments: {:?}\n\n{}", remaining, USAGE);
+    }
+
+    let bytes = std::fs::read(&input).with_context(|| format!("reading {}", input.display()))?;
+    let converted =
+        convert(&bytes, &format).with_context(|| format!("converting {}", input.display()))?;
+    std::fs::write(&output, converted).with_context(|| format!("writing {}", output.display()))
+}
+
+fn main() -> Result<()> {
+    run(std::env::args_os().skip(1).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_round_trip() {
+        let input = include_bytes!("../inputs/25.txt");
+        let binary = convert(input, "binary").unwrap();
+        let commented = convert(&binary, "commented").unwrap();
+        let comma = convert(&commented, "comma").unwrap();
+        assert_eq!(comma, input);
+        assert!(convert(input, "hex").is_err());
+    }
+}
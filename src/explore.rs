@@ -0,0 +1,456 @@
+// Exploring an unknown world through a robot that can only be told to move
+// and report what it finds, as in days 15 and 25.
+//
+// A `Robot` describes where it stands as an `Observation`: the place, what is
+// there, and the moves worth trying from it.  The explorer tries every move
+// from every place it reaches, recording places as the nodes of a `Graph`,
+// the moves between them as edges, and the moves the robot refused as
+// blocked.  There are two ways to get back to a place with moves left to try:
+//
+// - `explore_by_cloning` keeps a copy of the robot at each place and explores
+//   breadth first, so places are found in order of distance from the start.
+// - `explore_by_backtracking` drives a single robot depth first and walks
+//   back along the reverse of each move, which a `Backtrack` robot supplies.
+//   Knowing the reverse also saves trying the way back from each new place.
+//
+// Places are told apart only by what the robot reports, such as a grid
+// position the robot keeps track of or a room's name.  A robot that can tell
+// where a move leads without making it saves trying moves into places
+// already mapped, or already found to be off limits.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::io;
+
+use crate::graph::{Graph, NodeId};
+use crate::point::{Point2D, SparseGrid};
+
+/// What a robot sees where it stands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observation<P, M, T> {
+    pub place: P,
+    pub tile: T,
+    /// The moves worth trying from here.
+    pub moves: Vec<M>,
+}
+
+/// The result of asking a robot to move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome<P, M, T> {
+    /// The robot moved and now stands at the place observed.
+    Moved(Observation<P, M, T>),
+    /// The robot refused to move, or was sent straight back, and stands
+    /// where it was.
+    Blocked,
+}
+
+/// The protocol for driving a robot through the world.
+pub trait Robot {
+    type Place: Clone + Eq + Hash;
+    type Move: Copy + Eq + Hash;
+    type Tile;
+
+    /// Describes where the robot stands before it has moved.  Called once,
+    /// when exploring starts.
+    fn observe(&mut self) -> Observation<Self::Place, Self::Move, Self::Tile>;
+
+    fn try_move(&mut self, mv: Self::Move) -> Outcome<Self::Place, Self::Move, Self::Tile>;
+
+    /// Where `mv` would take the robot, if it can tell without moving.  The
+    /// explorer then assumes a move into a place succeeds or fails the same
+    /// way wherever it is made from, and does not try it twice.
+    fn destination(&self, _mv: Self::Move) -> Option<Self::Place> {
+        None
+    }
+}
+
+/// A robot that can undo a move by making another.
+pub trait Backtrack: Robot {
+    /// The move that takes the robot back to where it was before `mv`.
+    fn reverse(&self, mv: Self::Move) -> Self::Move;
+}
+
+type RobotMap<R> = WorldMap<<R as Robot>::Place, <R as Robot>::Move, <R as Robot>::Tile>;
+
+/// Everything a robot has discovered.
+pub struct WorldMap<P, M, T> {
+    graph: Graph<(P, T), M>,
+    places: HashMap<P, NodeId>,
+    blocked: HashSet<(NodeId, M)>,
+    // Destinations of moves that were refused.
+    off_limits: HashSet<P>,
+    start: NodeId,
+}
+
+impl<P: Clone + Eq + Hash, M: Copy + Eq + Hash, T> WorldMap<P, M, T> {
+    fn new(observation: Observation<P, M, T>) -> (Self, Vec<M>) {
+        let mut graph = Graph::new();
+        let start = graph.add_node((observation.place.clone(), observation.tile));
+        let map = WorldMap {
+            graph,
+            places: HashMap::from([(observation.place, start)]),
+            blocked: HashSet::new(),
+            off_limits: HashSet::new(),
+            start,
+        };
+        (map, observation.moves)
+    }
+
+    /// Records the robot arriving somewhere, returning the place's node and,
+    /// if the place is new, the moves to try from it.
+    fn arrive(&mut self, observation: Observation<P, M, T>) -> (NodeId, Option<Vec<M>>) {
+        if let Some(&id) = self.places.get(&observation.place) {
+            return (id, None);
+        }
+        let id = self
+            .graph
+            .add_node((observation.place.clone(), observation.tile));
+        self.places.insert(observation.place, id);
+        (id, Some(observation.moves))
+    }
+
+    /// Whether `mv` has already been tried from `from`.
+    fn tried(&self, from: NodeId, mv: M) -> bool {
+        self.graph.successors(from).any(|(&m, _)| m == mv) || self.blocked.contains(&(from, mv))
+    }
+
+    /// Records the outcome of `mv` from `from` without trying it, if it is
+    /// already known from the move's `destination`.  Returns the place moved
+    /// to, if any, or `None` if the move still needs trying.
+    fn infer(&mut self, from: NodeId, mv: M, destination: Option<P>) -> Option<Option<NodeId>> {
+        let destination = destination?;
+        if let Some(&to) = self.places.get(&destination) {
+            self.graph.add_edge(from, to, mv);
+            Some(Some(to))
+        } else if self.off_limits.contains(&destination) {
+            self.blocked.insert((from, mv));
+            Some(None)
+        } else {
+            None
+        }
+    }
+
+    /// Records that `mv` from `from`, leading to `destination` if known, was
+    /// refused.
+    fn block(&mut self, from: NodeId, mv: M, destination: Option<P>) {
+        self.blocked.insert((from, mv));
+        self.off_limits.extend(destination);
+    }
+
+    /// The graph of places found, holding each place and what is there, with
+    /// an edge for each move found between them.
+    pub fn graph(&self) -> &Graph<(P, T), M> {
+        &self.graph
+    }
+
+    /// Where the robot started.
+    pub fn start(&self) -> NodeId {
+        self.start
+    }
+
+    pub fn len(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graph.node_count() == 0
+    }
+
+    pub fn node(&self, place: &P) -> Option<NodeId> {
+        self.places.get(place).copied()
+    }
+
+    pub fn place(&self, id: NodeId) -> &P {
+        &self.graph.get_node(id).unwrap().0
+    }
+
+    pub fn tile(&self, id: NodeId) -> &T {
+        &self.graph.get_node(id).unwrap().1
+    }
+
+    /// The places found and what is there, in the order they were found.
+    pub fn places(&self) -> impl Iterator<Item = (NodeId, &P, &T)> + '_ {
+        self.graph
+            .nodes()
+            .map(|(id, (place, tile))| (id, place, tile))
+    }
+
+    /// The moves the robot refused, or would have, and where it was, in no
+    /// particular order.
+    pub fn blocked(&self) -> impl Iterator<Item = (NodeId, M)> + '_ {
+        self.blocked.iter().copied()
+    }
+
+    /// The fewest moves that take the robot from `from` to `to`.
+    pub fn route(&self, from: NodeId, to: NodeId) -> Option<Vec<M>> {
+        let path = self.graph.bfs(from, |id| id == to)?;
+        Some(
+            path.edges
+                .iter()
+                .map(|&edge| *self.graph.get_edge(edge).unwrap())
+                .collect(),
+        )
+    }
+
+    /// Writes the map as a Graphviz graph.
+    pub fn write_dot(
+        &self,
+        w: &mut impl io::Write,
+        mut place_label: impl FnMut(&P, &T) -> String,
+        mut move_label: impl FnMut(&M) -> String,
+    ) -> io::Result<()> {
+        self.graph.write_dot(
+            w,
+            |_, (place, tile)| place_label(place, tile),
+            |_, mv| move_label(mv),
+        )
+    }
+}
+
+impl<M: Copy + Eq + Hash, T> WorldMap<Point2D<i32>, M, T> {
+    /// Lays the places found out on a grid, with `default` everywhere else.
+    pub fn to_sparse_grid<U: Clone>(
+        &self,
+        default: U,
+        mut cell: impl FnMut(&T) -> U,
+    ) -> SparseGrid<U> {
+        let mut grid = SparseGrid::new(default);
+        for (_, &place, tile) in self.places() {
+            grid.set(place, cell(tile));
+        }
+        grid
+    }
+}
+
+/// Explores breadth first, copying the robot to try each move.
+pub fn explore_by_cloning<R: Robot + Clone>(mut robot: R) -> RobotMap<R> {
+    let (mut map, moves) = WorldMap::new(robot.observe());
+    let mut queue = VecDeque::from([(map.start, robot, moves)]);
+    while let Some((from, robot, moves)) = queue.pop_front() {
+        for mv in moves {
+            let destination = robot.destination(mv);
+            if map.tried(from, mv) || map.infer(from, mv, destination.clone()).is_some() {
+                continue;
+            }
+            let mut next = robot.clone();
+            match next.try_move(mv) {
+                Outcome::Blocked => map.block(from, mv, destination),
+                Outcome::Moved(observation) => {
+                    let (to, moves) = map.arrive(observation);
+                    map.graph.add_edge(from, to, mv);
+                    if let Some(moves) = moves {
+                        queue.push_back((to, next, moves));
+                    }
+                }
+            }
+        }
+    }
+    map
+}
+
+/// Explores depth first with a single robot, which ends up back where it
+/// started.
+pub fn explore_by_backtracking<R: Backtrack>(robot: &mut R) -> RobotMap<R> {
+    let (mut map, moves) = WorldMap::new(robot.observe());
+    // Each place being explored, the moves left to try there, and the move
+    // that leads back to the place before it.
+    let mut stack = vec![(map.start, moves.into_iter(), None)];
+    while let Some((from, moves, _)) = stack.last_mut() {
+        let from = *from;
+        let Some(mv) = moves.next() else {
+            if let Some((_, _, Some(back))) = stack.pop() {
+                move_back(robot, back);
+            }
+            continue;
+        };
+        if map.tried(from, mv) {
+            continue;
+        }
+        let back = robot.reverse(mv);
+        let destination = robot.destination(mv);
+        if let Some(known) = map.infer(from, mv, destination.clone()) {
+            if let Some(to) = known
+                && !map.tried(to, back)
+            {
+                map.graph.add_edge(to, from, back);
+            }
+            continue;
+        }
+        match robot.try_move(mv) {
+            Outcome::Blocked => map.block(from, mv, destination),
+            Outcome::Moved(observation) => {
+                let (to, moves) = map.arrive(observation);
+                map.graph.add_edge(from, to, mv);
+                if !map.tried(to, back) {
+                    map.graph.add_edge(to, from, back);
+                }
+                match moves {
+                    Some(moves) => stack.push((to, moves.into_iter(), Some(back))),
+                    None => move_back(robot, back),
+                }
+            }
+        }
+    }
+    map
+}
+
+fn move_back<R: Robot>(robot: &mut R, back: R::Move) {
+    if let Outcome::Blocked = robot.try_move(back) {
+        panic!("robot could not move back the way it came");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::point::{CARDINAL_DIRECTIONS, CardinalDirection};
+
+    /// A robot walking a maze drawn as text.  Counts the moves tried by it
+    /// and all its copies.
+    #[derive(Clone)]
+    struct Walker {
+        rows: Vec<&'static [u8]>,
+        pos: Point2D<i32>,
+        tries: Rc<Cell<usize>>,
+        knows_destinations: bool,
+    }
+
+    impl Walker {
+        fn new(text: &'static str, knows_destinations: bool) -> Walker {
+            let rows: Vec<&[u8]> = text.lines().map(str::as_bytes).collect();
+            let y = rows.iter().position(|row| row.contains(&b'@')).unwrap();
+            let x = rows[y].iter().position(|&ch| ch == b'@').unwrap();
+            Walker {
+                rows,
+                pos: Point2D::new(x as i32, y as i32),
+                tries: Rc::new(Cell::new(0)),
+                knows_destinations,
+            }
+        }
+
+        fn look(&self) -> Observation<Point2D<i32>, CardinalDirection, char> {
+            Observation {
+                place: self.pos,
+                tile: self.rows[self.pos.y as usize][self.pos.x as usize] as char,
+                moves: CARDINAL_DIRECTIONS.to_vec(),
+            }
+        }
+    }
+
+    impl Robot for Walker {
+        type Place = Point2D<i32>;
+        type Move = CardinalDirection;
+        type Tile = char;
+
+        fn observe(&mut self) -> Observation<Point2D<i32>, CardinalDirection, char> {
+            self.look()
+        }
+
+        fn try_move(
+            &mut self,
+            dir: CardinalDirection,
+        ) -> Outcome<Point2D<i32>, CardinalDirection, char> {
+            self.tries.set(self.tries.get() + 1);
+            let dest = self.pos.cardinal_neighbor(dir);
+            if self.rows[dest.y as usize][dest.x as usize] == b'#' {
+                return Outcome::Blocked;
+            }
+            self.pos = dest;
+            Outcome::Moved(self.look())
+        }
+
+        fn destination(&self, dir: CardinalDirection) -> Option<Point2D<i32>> {
+            self.knows_destinations
+                .then(|| self.pos.cardinal_neighbor(dir))
+        }
+    }
+
+    impl Backtrack for Walker {
+        fn reverse(&self, dir: CardinalDirection) -> CardinalDirection {
+            dir.negate()
+        }
+    }
+
+    const MAZE: &str = "\
+#######
+#@..#x#
+#.#.#.#
+#.....#
+#######";
+
+    // Walls only show where the robot tried to walk into them.
+    const EXPLORED: &str = "\
+?###?#?
+#@..#x#
+#.#.#.#
+#.....#
+?#####?
+";
+
+    fn render(map: &WorldMap<Point2D<i32>, CardinalDirection, char>) -> String {
+        let mut grid = map.to_sparse_grid('?', |&tile| tile);
+        for (id, dir) in map.blocked() {
+            grid.set(map.place(id).cardinal_neighbor(dir), '#');
+        }
+        grid.render(|&ch| ch)
+    }
+
+    #[test]
+    fn test_explore_by_cloning() {
+        let mut tries = Vec::new();
+        for knows_destinations in [false, true] {
+            let walker = Walker::new(MAZE, knows_destinations);
+            let map = explore_by_cloning(walker.clone());
+            assert_eq!(map.len(), 12);
+            assert_eq!(render(&map), EXPLORED);
+            assert_eq!(map.place(map.start()), &Point2D::new(1, 1));
+            // Breadth first finds the farthest place last.
+            let (x, _, _) = map.places().last().unwrap();
+            assert_eq!(map.place(x), &Point2D::new(5, 1));
+            assert_eq!(map.route(map.start(), x).unwrap().len(), 8);
+            tries.push(walker.tries.get());
+        }
+        // Every move from every place, or just one into each new place and
+        // each wall.
+        assert_eq!(tries, vec![48, 11 + 18]);
+    }
+
+    #[test]
+    fn test_explore_by_backtracking() {
+        let mut knowing = Walker::new(MAZE, true);
+        let map = explore_by_backtracking(&mut knowing);
+        assert_eq!(map.len(), 12);
+        assert_eq!(render(&map), EXPLORED);
+        assert_eq!(knowing.pos, Point2D::new(1, 1));
+        assert_eq!(map.graph().edge_count(), 2 * 12);
+
+        let mut walker = Walker::new(MAZE, false);
+        let map = explore_by_backtracking(&mut walker);
+        assert_eq!(map.len(), 12);
+        assert_eq!(render(&map), EXPLORED);
+        assert_eq!(walker.pos, Point2D::new(1, 1));
+        assert!(knowing.tries.get() < walker.tries.get());
+
+        let x = map.node(&Point2D::new(5, 1)).unwrap();
+        assert_eq!(*map.tile(x), 'x');
+        let route = map.route(map.start(), x).unwrap();
+        assert_eq!(route.len(), 8);
+        let back = map.route(x, map.start()).unwrap();
+        assert_eq!(back.len(), 8);
+        // Every passage is known in both directions.
+        assert_eq!(map.graph().edge_count(), 2 * 12);
+
+        let mut dot = Vec::new();
+        map.write_dot(
+            &mut dot,
+            |_, tile| tile.to_string(),
+            |dir| format!("{:?}", dir),
+        )
+        .unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.contains("[label=\"x\"]"));
+    }
+}
@@ -0,0 +1,521 @@
+// Goal seeking for Intcode programs: find values for a handful of unknown
+// memory cells or inputs that make the program produce a target value.
+//
+// Many puzzle programs compute an affine function of their unknowns (day 2's
+// noun and verb, for example), so the solver first fits an affine model from a
+// few concrete runs and solves it directly.  If the model does not hold, it
+// falls back to searching every combination within the given ranges.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow, bail};
+use itertools::Itertools;
+
+use crate::intcode::{Computer, RunState};
+
+/// Where an unknown value is fed into the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    /// A memory cell, poked before the program starts.
+    Memory(i64),
+    /// The n-th value (counting from zero) read from the program's input.
+    Input(usize),
+}
+
+/// Which value of a finished run is compared against the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Observation {
+    /// A memory cell after the program finishes.
+    Memory(i64),
+    /// The n-th value (counting from zero) the program outputs.
+    Output(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unknown {
+    pub location: Location,
+    pub range: RangeInclusive<i64>,
+}
+
+impl Unknown {
+    pub fn new(location: Location, range: RangeInclusive<i64>) -> Unknown {
+        Unknown { location, range }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// The program was found to be affine in the unknowns and solved directly.
+    Affine,
+    /// Every combination of unknowns was tried until one hit the target.
+    Search,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// One value per unknown, in the order the unknowns were given.
+    pub values: Vec<i64>,
+    pub method: Method,
+}
+
+/// Finds values for `unknowns` that make `observation` equal `target` when
+/// `program` runs to completion.  Returns `Ok(None)` if no combination within
+/// the unknowns' ranges works; a run that fails counts as not working.  Fails
+/// only if the unknowns or the observation are invalid.
+pub fn solve(
+    program: &Computer,
+    unknowns: &[Unknown],
+    observation: Observation,
+    target: i64,
+) -> Result<Option<Solution>> {
+    validate(unknowns, observation)?;
+
+    // The model is only checked at a few points, so it can fit them without
+    // fitting everywhere.  Confirm its answer with a real run, and search if
+    // it has none.
+    if let Some(model) = AffineModel::fit(program, unknowns, observation)
+        && let Some(values) = model.solve(unknowns, target)
+        && evaluate(program, unknowns, &values, observation).is_ok_and(|value| value == target)
+    {
+        return Ok(Some(Solution {
+            values,
+            method: Method::Affine,
+        }));
+    }
+
+    Ok(search(program, unknowns, observation, target))
+}
+
+/// Runs a copy of `program` with `values` assigned to `unknowns` and returns
+/// the observed value.
+pub fn evaluate(
+    program: &Computer,
+    unknowns: &[Unknown],
+    values: &[i64],
+    observation: Observation,
+) -> Result<i64> {
+    assert_eq!(unknowns.len(), values.len());
+
+    let mut computer = program.clone();
+    let mut inputs = Vec::new();
+    for (unknown, &value) in unknowns.iter().zip(values) {
+        match unknown.location {
+            Location::Memory(address) => computer.poke(address, value),
+            Location::Input(n) => inputs.push((n, value)),
+        }
+    }
+    inputs.sort();
+    computer.append_input(&inputs.iter().map(|&(_, value)| value).collect_vec());
+
+    let mut outputs = Vec::new();
+    loop {
+        match computer.run() {
+            RunState::BlockedOnInput => {
+                bail!("program wanted more than {} input values", inputs.len())
+            }
+            RunState::BlockedOnOutput => {
+                outputs.push(computer.take_output().unwrap());
+                if let Observation::Output(n) = observation
+                    && n < outputs.len()
+                {
+                    return Ok(outputs[n]);
+                }
+            }
+            RunState::Finished => break,
+            RunState::Trapped => bail!("program stopped at an extension trap"),
+        }
+    }
+    match observation {
+        Observation::Memory(address) => Ok(computer.peek(address)),
+        Observation::Output(n) => bail!(
+            "program finished after {} outputs; wanted output {}",
+            outputs.len(),
+            n
+        ),
+    }
+}
+
+fn validate(unknowns: &[Unknown], observation: Observation) -> Result<()> {
+    if let Observation::Memory(address) = observation
+        && address < 0
+    {
+        bail!("{} is not a valid memory address", observation);
+    }
+    for (i, unknown) in unknowns.iter().enumerate() {
+        if unknown.range.is_empty() {
+            bail!("unknown {} has an empty range", unknown);
+        }
+        if let Location::Memory(address) = unknown.location
+            && address < 0
+        {
+            bail!("{} is not a valid memory address", unknown.location);
+        }
+        if unknowns[..i].iter().any(|u| u.location == unknown.location) {
+            bail!("{} is listed more than once", unknown.location);
+        }
+    }
+    let mut inputs = unknowns
+        .iter()
+        .filter_map(|u| match u.location {
+            Location::Input(n) => Some(n),
+            Location::Memory(_) => None,
+        })
+        .collect_vec();
+    inputs.sort();
+    if inputs.iter().enumerate().any(|(i, &n)| i != n) {
+        bail!("unknown inputs must be numbered 0, 1, 2, ... without gaps");
+    }
+    Ok(())
+}
+
+// Tries every combination in turn.  A run that fails, say by finishing
+// before the observed output, is no match, and the search moves on.
+fn search(
+    program: &Computer,
+    unknowns: &[Unknown],
+    observation: Observation,
+    target: i64,
+) -> Option<Solution> {
+    points(unknowns.iter().map(|u| u.range.clone()))
+        .find(|values| {
+            evaluate(program, unknowns, values, observation).is_ok_and(|value| value == target)
+        })
+        .map(|values| Solution {
+            values,
+            method: Method::Search,
+        })
+}
+
+/// Every combination of values from `ranges`, in lexicographic order.
+fn points(ranges: impl Iterator<Item = RangeInclusive<i64>>) -> Box<dyn Iterator<Item = Vec<i64>>> {
+    let ranges = ranges.collect_vec();
+    if ranges.is_empty() {
+        Box::new(std::iter::once(Vec::new()))
+    } else {
+        Box::new(ranges.into_iter().multi_cartesian_product())
+    }
+}
+
+/// The observed value modelled as `constant + sum(coefficients[i] * (x[i] -
+/// base[i]))`.
+struct AffineModel {
+    base: Vec<i64>,
+    constant: i64,
+    coefficients: Vec<i64>,
+}
+
+impl AffineModel {
+    /// Fits a model from runs at the low corner of the ranges and one step
+    /// along each axis, then checks it against the far corners and the middle.
+    /// Returns `None` if the program does not behave affinely, or if any of
+    /// these runs fails.
+    fn fit(
+        program: &Computer,
+        unknowns: &[Unknown],
+        observation: Observation,
+    ) -> Option<AffineModel> {
+        let evaluate = |point: &[i64]| evaluate(program, unknowns, point, observation).ok();
+        let base = unknowns.iter().map(|u| *u.range.start()).collect_vec();
+        let constant = evaluate(&base)?;
+        let mut coefficients = Vec::new();
+        for (i, unknown) in unknowns.iter().enumerate() {
+            if unknown.range.start() == unknown.range.end() {
+                coefficients.push(0);
+                continue;
+            }
+            let mut point = base.clone();
+            point[i] += 1;
+            coefficients.push(evaluate(&point)?.checked_sub(constant)?);
+        }
+        let model = AffineModel {
+            base,
+            constant,
+            coefficients,
+        };
+
+        let mut probes = Vec::new();
+        for (i, unknown) in unknowns.iter().enumerate() {
+            let mut point = model.base.clone();
+            point[i] = *unknown.range.end();
+            probes.push(point);
+        }
+        probes.push(unknowns.iter().map(|u| *u.range.end()).collect());
+        probes.push(
+            unknowns
+                .iter()
+                .map(|u| u.range.start().midpoint(*u.range.end()))
+                .collect(),
+        );
+        for point in probes {
+            let actual = evaluate(&point)?;
+            if i128::from(actual) != model.predict(&point) {
+                return None;
+            }
+        }
+        Some(model)
+    }
+
+    fn predict(&self, point: &[i64]) -> i128 {
+        let mut value = i128::from(self.constant);
+        for ((&c, &x), &b) in self.coefficients.iter().zip(point).zip(&self.base) {
+            value += i128::from(c) * (i128::from(x) - i128::from(b));
+        }
+        value
+    }
+
+    /// Solves the model for `target`.  Picks the unknown with the widest range
+    /// among those that matter as the pivot, enumerates the others, and solves
+    /// for the pivot by division, so no further program runs are needed.
+    fn solve(&self, unknowns: &[Unknown], target: i64) -> Option<Vec<i64>> {
+        let width = |i: usize| {
+            i128::from(*unknowns[i].range.end()) - i128::from(*unknowns[i].range.start())
+        };
+        let Some(pivot) = (0..unknowns.len())
+            .filter(|&i| self.coefficients[i] != 0)
+            .max_by_key(|&i| width(i))
+        else {
+            return (self.constant == target).then(|| self.base.clone());
+        };
+        let others = (0..unknowns.len())
+            .filter(|&i| i != pivot && self.coefficients[i] != 0)
+            .collect_vec();
+
+        let pivot_coefficient = i128::from(self.coefficients[pivot]);
+        for values in points(others.iter().map(|&i| unknowns[i].range.clone())) {
+            let mut point = self.base.clone();
+            for (&i, &value) in others.iter().zip(&values) {
+                point[i] = value;
+            }
+            let remainder = i128::from(target) - self.predict(&point);
+            if remainder % pivot_coefficient != 0 {
+                continue;
+            }
+            let pivot_value = i128::from(self.base[pivot]) + remainder / pivot_coefficient;
+            if let Ok(pivot_value) = i64::try_from(pivot_value)
+                && unknowns[pivot].range.contains(&pivot_value)
+            {
+                point[pivot] = pivot_value;
+                return Some(point);
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Memory(address) => write!(f, "mem:{}", address),
+            Location::Input(n) => write!(f, "in:{}", n),
+        }
+    }
+}
+
+impl fmt::Display for Observation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Observation::Memory(address) => write!(f, "mem:{}", address),
+            Observation::Output(n) => write!(f, "out:{}", n),
+        }
+    }
+}
+
+impl fmt::Display for Unknown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}={}..={}",
+            self.location,
+            self.range.start(),
+            self.range.end()
+        )
+    }
+}
+
+fn split_tagged(s: &str) -> Result<(&str, &str)> {
+    s.split_once(':')
+        .ok_or_else(|| anyhow!("expected \"<kind>:<number>\", got \"{}\"", s))
+}
+
+/// Parses `mem:<address>` or `in:<n>`.
+impl FromStr for Location {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, number) = split_tagged(s)?;
+        match kind {
+            "mem" => Ok(Location::Memory(number.parse()?)),
+            "in" => Ok(Location::Input(number.parse()?)),
+            _ => bail!("unknown location kind \"{}\" in \"{}\"", kind, s),
+        }
+    }
+}
+
+/// Parses `mem:<address>` or `out:<n>`.
+impl FromStr for Observation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, number) = split_tagged(s)?;
+        match kind {
+            "mem" => Ok(Observation::Memory(number.parse()?)),
+            "out" => Ok(Observation::Output(number.parse()?)),
+            _ => bail!("unknown observation kind \"{}\" in \"{}\"", kind, s),
+        }
+    }
+}
+
+/// Parses `<location>=<low>..=<high>`, e.g. `mem:1=0..=99`.
+impl FromStr for Unknown {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (location, range) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected \"<location>=<low>..=<high>\", got \"{}\"", s))?;
+        let (low, high) = range
+            .split_once("..=")
+            .ok_or_else(|| anyhow!("expected a range like \"0..=99\", got \"{}\"", range))?;
+        let low = low
+            .parse()
+            .with_context(|| format!("bad range in \"{}\"", s))?;
+        let high = high
+            .parse()
+            .with_context(|| format!("bad range in \"{}\"", s))?;
+        Ok(Unknown::new(location.parse()?, low..=high))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noun_verb() -> Vec<Unknown> {
+        vec![
+            Unknown::new(Location::Memory(1), 0..=99),
+            Unknown::new(Location::Memory(2), 0..=99),
+        ]
+    }
+
+    #[test]
+    fn test_affine_memory() {
+        // mem[0] = mem[1] * 1000 + mem[6], using immediate-mode operands.
+        let program = Computer::parse("1102,0,1000,13,1001,13,0,0,99,0,0,0,0,0");
+        let unknowns = vec![
+            Unknown::new(Location::Memory(1), 0..=99),
+            Unknown::new(Location::Memory(6), 0..=99),
+        ];
+        let solution = solve(&program, &unknowns, Observation::Memory(0), 42017)
+            .unwrap()
+            .unwrap();
+        assert_eq!(solution.values, vec![42, 17]);
+        assert_eq!(solution.method, Method::Affine);
+    }
+
+    #[test]
+    fn test_affine_no_solution() {
+        let program = Computer::parse("1102,0,1000,13,1001,13,0,0,99,0,0,0,0,0");
+        let unknowns = vec![
+            Unknown::new(Location::Memory(1), 0..=99),
+            Unknown::new(Location::Memory(6), 0..=99),
+        ];
+        assert_eq!(
+            solve(&program, &unknowns, Observation::Memory(0), 100_000).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_search_affine_at_probes_only() {
+        // Outputs in0, except 100 when in0 is 3, which no probe tries.
+        let program =
+            Computer::parse("3,20,1008,20,3,21,1005,21,13,4,20,99,0,104,100,99,0,0,0,0,0,0");
+        let unknowns = vec![Unknown::new(Location::Input(0), 0..=10)];
+        let solution = solve(&program, &unknowns, Observation::Output(0), 100)
+            .unwrap()
+            .unwrap();
+        assert_eq!(solution.values, vec![3]);
+        assert_eq!(solution.method, Method::Search);
+    }
+
+    #[test]
+    fn test_search_non_affine_input() {
+        // Outputs in0 * in1.
+        let program = Computer::parse("3,11,3,12,2,11,12,13,4,13,99,0,0,0");
+        let unknowns = vec![
+            Unknown::new(Location::Input(0), 2..=20),
+            Unknown::new(Location::Input(1), 2..=20),
+        ];
+        let solution = solve(&program, &unknowns, Observation::Output(0), 91)
+            .unwrap()
+            .unwrap();
+        assert_eq!(solution.values, vec![7, 13]);
+        assert_eq!(solution.method, Method::Search);
+    }
+
+    #[test]
+    fn test_day_two() {
+        let program = Computer::parse(include_str!("inputs/02.txt"));
+        let solution = solve(&program, &noun_verb(), Observation::Memory(0), 19690720)
+            .unwrap()
+            .unwrap();
+        assert_eq!(solution.values, vec![64, 17]);
+        assert_eq!(solution.method, Method::Affine);
+    }
+
+    #[test]
+    fn test_parse() {
+        let unknown: Unknown = "mem:1=0..=99".parse().unwrap();
+        assert_eq!(unknown, Unknown::new(Location::Memory(1), 0..=99));
+        assert_eq!(unknown.to_string(), "mem:1=0..=99");
+        let unknown: Unknown = "in:0=-5..=5".parse().unwrap();
+        assert_eq!(unknown, Unknown::new(Location::Input(0), -5..=5));
+        assert_eq!(
+            "out:3".parse::<Observation>().unwrap(),
+            Observation::Output(3)
+        );
+        assert!("mem:1".parse::<Unknown>().is_err());
+        assert!("reg:1=0..=2".parse::<Unknown>().is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let program = Computer::parse("99");
+        let duplicate = vec![
+            Unknown::new(Location::Memory(1), 0..=1),
+            Unknown::new(Location::Memory(1), 0..=1),
+        ];
+        assert!(solve(&program, &duplicate, Observation::Memory(0), 0).is_err());
+        let gap = vec![Unknown::new(Location::Input(1), 0..=1)];
+        assert!(solve(&program, &gap, Observation::Memory(0), 0).is_err());
+        let negative = vec![Unknown::new(Location::Memory(-1), 0..=1)];
+        assert!(solve(&program, &negative, Observation::Memory(0), 0).is_err());
+        assert!(solve(&program, &[], Observation::Memory(-1), 0).is_err());
+    }
+
+    #[test]
+    fn test_search_skips_failed_runs() {
+        // Outputs in0 * in0, but halts without output when in0 is zero.
+        let program = Computer::parse("3,20,1006,20,11,2,20,20,21,4,21,99,0,0,0,0,0,0,0,0,0,0");
+        let unknowns = vec![Unknown::new(Location::Input(0), 0..=10)];
+        let solution = solve(&program, &unknowns, Observation::Output(0), 49)
+            .unwrap()
+            .unwrap();
+        assert_eq!(solution.values, vec![7]);
+        assert_eq!(solution.method, Method::Search);
+    }
+
+    #[test]
+    fn test_full_range() {
+        // Outputs 7 whatever it reads.
+        let program = Computer::parse("3,5,104,7,99,0");
+        let unknowns = vec![Unknown::new(Location::Input(0), i64::MIN..=i64::MAX)];
+        let solution = solve(&program, &unknowns, Observation::Output(0), 7)
+            .unwrap()
+            .unwrap();
+        assert_eq!(solution.values, vec![i64::MIN]);
+        assert_eq!(solution.method, Method::Affine);
+    }
+}
@@ -1,16 +1,27 @@
 use std::num::NonZeroUsize;
 
+pub mod contract;
+pub mod format;
+pub mod layered;
+pub mod search;
+pub mod topology;
+
+/// A directed graph with data on its nodes and edges.
+///
+/// Nodes and edges are identified by `NodeId` and `EdgeId`.  Ids are never
+/// reused: removing a node or edge leaves a tombstone in its place, so an id
+/// taken before a removal either still refers to the same node or edge or
+/// refers to nothing, in which case lookups return `None`.
 pub struct Graph<N, E> {
-    nodes: Vec<NodeData<N>>,
-    edges: Vec<EdgeData<E>>,
+    nodes: Vec<Option<NodeData<N>>>,
+    edges: Vec<Option<EdgeData<E>>>,
+    node_count: usize,
+    edge_count: usize,
 }
 
 impl<N, E> Default for Graph<N, E> {
     fn default() -> Self {
-        Self {
-            nodes: Vec::new(),
-            edges: Vec::new(),
-        }
+        Self::new()
     }
 }
 
@@ -21,6 +32,10 @@ impl NodeId {
     fn index(&self) -> usize {
         self.0.get() - 1
     }
+
+    fn from_index(index: usize) -> NodeId {
+        NodeId(NonZeroUsize::new(index + 1).unwrap())
+    }
 }
 
 struct NodeData<T> {
@@ -28,17 +43,22 @@ struct NodeData<T> {
     first_outgoing_edge: Option<EdgeId>,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct EdgeId(NonZeroUsize);
 
 impl EdgeId {
     fn index(&self) -> usize {
         self.0.get() - 1
     }
+
+    fn from_index(index: usize) -> EdgeId {
+        EdgeId(NonZeroUsize::new(index + 1).unwrap())
+    }
 }
 
 struct EdgeData<T> {
     data: T,
+    source: NodeId,
     target: NodeId,
     next_outgoing_edge: Option<EdgeId>,
 }
@@ -48,35 +68,141 @@ impl<N, E> Graph<N, E> {
         Self {
             nodes: Vec::new(),
             edges: Vec::new(),
+            node_count: 0,
+            edge_count: 0,
         }
     }
 
     pub fn add_node(&mut self, data: N) -> NodeId {
-        self.nodes.push(NodeData {
+        self.nodes.push(Some(NodeData {
             data,
             first_outgoing_edge: None,
-        });
-        NodeId(NonZeroUsize::new(self.nodes.len()).unwrap())
+        }));
+        self.node_count += 1;
+        NodeId::from_index(self.nodes.len() - 1)
     }
 
+    /// Adds an edge from `source` to `target`.  Panics if either node has
+    /// been removed.
     pub fn add_edge(&mut self, source: NodeId, target: NodeId, data: E) -> EdgeId {
-        let node_data = &mut self.nodes[source.index()];
-        self.edges.push(EdgeData {
+        assert!(
+            self.contains_node(target),
+            "edge target {:?} was removed",
+            target
+        );
+        let edge_id = EdgeId::from_index(self.edges.len());
+        let next_outgoing_edge = self
+            .node_data_mut(source)
+            .first_outgoing_edge
+            .replace(edge_id);
+        self.edges.push(Some(EdgeData {
             data,
+            source,
             target,
-            next_outgoing_edge: node_data.first_outgoing_edge,
-        });
-        let edge_index = EdgeId(NonZeroUsize::new(self.edges.len()).unwrap());
-        node_data.first_outgoing_edge = Some(edge_index);
-        edge_index
+            next_outgoing_edge,
+        }));
+        self.edge_count += 1;
+        edge_id
+    }
+
+    fn node_data_mut(&mut self, id: NodeId) -> &mut NodeData<N> {
+        self.nodes[id.index()]
+            .as_mut()
+            .unwrap_or_else(|| panic!("node {:?} was removed", id))
+    }
+
+    fn edge_data(&self, id: EdgeId) -> Option<&EdgeData<E>> {
+        self.edges.get(id.index())?.as_ref()
+    }
+
+    pub fn contains_node(&self, id: NodeId) -> bool {
+        self.get_node(id).is_some()
     }
 
     pub fn get_node(&self, index: NodeId) -> Option<&N> {
-        self.nodes.get(index.index()).map(|node| &node.data)
+        self.nodes
+            .get(index.index())?
+            .as_ref()
+            .map(|node| &node.data)
+    }
+
+    pub fn get_node_mut(&mut self, index: NodeId) -> Option<&mut N> {
+        self.nodes
+            .get_mut(index.index())?
+            .as_mut()
+            .map(|node| &mut node.data)
     }
 
     pub fn get_edge(&self, index: EdgeId) -> Option<&E> {
-        self.edges.get(index.index()).map(|edge| &edge.data)
+        self.edge_data(index).map(|edge| &edge.data)
+    }
+
+    pub fn get_edge_mut(&mut self, index: EdgeId) -> Option<&mut E> {
+        self.edges
+            .get_mut(index.index())?
+            .as_mut()
+            .map(|edge| &mut edge.data)
+    }
+
+    /// The source and target of an edge.
+    pub fn edge_endpoints(&self, index: EdgeId) -> Option<(NodeId, NodeId)> {
+        self.edge_data(index).map(|edge| (edge.source, edge.target))
+    }
+
+    /// The number of nodes, not counting removed ones.
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    /// The number of edges, not counting removed ones.
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    /// Removes an edge, returning its data, or `None` if it was already
+    /// removed.
+    pub fn remove_edge(&mut self, id: EdgeId) -> Option<E> {
+        let edge = self.edges.get_mut(id.index())?.take()?;
+        // Unlink the edge from its source's list of outgoing edges.
+        let source = self.node_data_mut(edge.source);
+        if source.first_outgoing_edge == Some(id) {
+            source.first_outgoing_edge = edge.next_outgoing_edge;
+        } else {
+            let mut current = source.first_outgoing_edge;
+            while let Some(current_id) = current {
+                let current_edge = self.edges[current_id.index()].as_mut().unwrap();
+                if current_edge.next_outgoing_edge == Some(id) {
+                    current_edge.next_outgoing_edge = edge.next_outgoing_edge;
+                    break;
+                }
+                current = current_edge.next_outgoing_edge;
+            }
+        }
+        self.edge_count -= 1;
+        Some(edge.data)
+    }
+
+    /// Removes a node and every edge into or out of it, returning its data,
+    /// or `None` if it was already removed.  Takes time proportional to the
+    /// total number of edges.
+    pub fn remove_node(&mut self, id: NodeId) -> Option<N> {
+        self.get_node(id)?;
+        self.retain_edges(|source, target, _| source != id && target != id);
+        let node = self.nodes[id.index()].take().unwrap();
+        self.node_count -= 1;
+        Some(node.data)
+    }
+
+    /// Removes every edge for which `keep(source, target, data)` returns
+    /// false.
+    pub fn retain_edges(&mut self, mut keep: impl FnMut(NodeId, NodeId, &mut E) -> bool) {
+        for index in 0..self.edges.len() {
+            if let Some(edge) = self.edges[index].as_mut()
+                && !keep(edge.source, edge.target, &mut edge.data)
+            {
+                self.remove_edge(EdgeId::from_index(index));
+            }
+        }
     }
 
     pub fn nodes(&self) -> NodeIterator<'_, N, E> {
@@ -86,13 +212,33 @@ impl<N, E> Graph<N, E> {
         }
     }
 
-    pub fn successors(&self, source: NodeId) -> Successors<'_, N, E> {
-        let first_outgoing_edge = self.nodes[source.index()].first_outgoing_edge;
-        Successors {
+    /// Every edge as `(id, source, data, target)`, in the order the edges
+    /// were added.
+    pub fn edges(&self) -> EdgeIterator<'_, N, E> {
+        EdgeIterator {
+            graph: self,
+            current_edge_index: 0,
+        }
+    }
+
+    /// The edges out of `source`, most recently added first.
+    pub fn outgoing_edges(&self, source: NodeId) -> OutgoingEdges<'_, N, E> {
+        let first_outgoing_edge = self.nodes[source.index()]
+            .as_ref()
+            .and_then(|node| node.first_outgoing_edge);
+        OutgoingEdges {
             graph: self,
             current_edge_id: first_outgoing_edge,
         }
     }
+
+    /// The edge data and target of each edge out of `source`, most
+    /// recently added first.
+    pub fn successors(&self, source: NodeId) -> Successors<'_, N, E> {
+        Successors {
+            edges: self.outgoing_edges(source),
+        }
+    }
 }
 
 pub struct NodeIterator<'graph, N, E> {
@@ -104,34 +250,62 @@ impl<'graph, N, E> Iterator for NodeIterator<'graph, N, E> {
     type Item = (NodeId, &'graph N);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(data) = self.graph.nodes.get(self.current_node_index) {
+        while let Some(node) = self.graph.nodes.get(self.current_node_index) {
+            let id = NodeId::from_index(self.current_node_index);
             self.current_node_index += 1;
-            return Some((
-                NodeId(NonZeroUsize::new(self.current_node_index).unwrap()),
-                &data.data,
-            ));
+            if let Some(node) = node {
+                return Some((id, &node.data));
+            }
         }
         None
     }
 }
 
-pub struct Successors<'graph, N, E> {
+pub struct EdgeIterator<'graph, N, E> {
+    graph: &'graph Graph<N, E>,
+    current_edge_index: usize,
+}
+
+impl<'graph, N, E> Iterator for EdgeIterator<'graph, N, E> {
+    type Item = (EdgeId, NodeId, &'graph E, NodeId);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(edge) = self.graph.edges.get(self.current_edge_index) {
+            let id = EdgeId::from_index(self.current_edge_index);
+            self.current_edge_index += 1;
+            if let Some(edge) = edge {
+                return Some((id, edge.source, &edge.data, edge.target));
+            }
+        }
+        None
+    }
+}
+
+pub struct OutgoingEdges<'graph, N, E> {
     graph: &'graph Graph<N, E>,
     current_edge_id: Option<EdgeId>,
 }
 
+impl<'graph, N, E> Iterator for OutgoingEdges<'graph, N, E> {
+    type Item = (EdgeId, &'graph E, NodeId);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let edge_id = self.current_edge_id?;
+        let edge = self.graph.edges[edge_id.index()].as_ref().unwrap();
+        self.current_edge_id = edge.next_outgoing_edge;
+        Some((edge_id, &edge.data, edge.target))
+    }
+}
+
+pub struct Successors<'graph, N, E> {
+    edges: OutgoingEdges<'graph, N, E>,
+}
+
 impl<'graph, N, E> Iterator for Successors<'graph, N, E> {
     type Item = (&'graph E, NodeId);
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.current_edge_id {
-            None => None,
-            Some(edge_num) => {
-                let edge = &self.graph.edges[edge_num.index()];
-                self.current_edge_id = edge.next_outgoing_edge;
-                Some((&edge.data, edge.target))
-            }
-        }
+        self.edges.next().map(|(_, data, target)| (data, target))
     }
 }
 
@@ -163,4 +337,80 @@ mod test {
         let successors: Vec<_> = graph.successors(n0).collect();
         assert_eq!(&successors[..], &[(&12, n3), (&10, n1)]);
     }
+
+    fn diamond() -> (Graph<&'static str, i32>, Vec<NodeId>, Vec<EdgeId>) {
+        let mut graph = Graph::new();
+        let nodes: Vec<NodeId> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|name| graph.add_node(name))
+            .collect();
+        let edges = vec![
+            graph.add_edge(nodes[0], nodes[1], 1),
+            graph.add_edge(nodes[0], nodes[2], 2),
+            graph.add_edge(nodes[1], nodes[3], 3),
+            graph.add_edge(nodes[2], nodes[3], 4),
+            graph.add_edge(nodes[0], nodes[3], 5),
+        ];
+        (graph, nodes, edges)
+    }
+
+    fn successor_data<N, E: Copy>(graph: &Graph<N, E>, node: NodeId) -> Vec<E> {
+        graph.successors(node).map(|(&data, _)| data).collect()
+    }
+
+    #[test]
+    fn remove_edge() {
+        let (mut graph, nodes, edges) = diamond();
+        assert_eq!(successor_data(&graph, nodes[0]), vec![5, 2, 1]);
+
+        // Middle, last and first of a node's outgoing list.
+        assert_eq!(graph.remove_edge(edges[1]), Some(2));
+        assert_eq!(successor_data(&graph, nodes[0]), vec![5, 1]);
+        assert_eq!(graph.remove_edge(edges[0]), Some(1));
+        assert_eq!(graph.remove_edge(edges[4]), Some(5));
+        assert_eq!(successor_data(&graph, nodes[0]), Vec::<i32>::new());
+
+        assert_eq!(graph.remove_edge(edges[4]), None);
+        assert_eq!(graph.get_edge(edges[4]), None);
+        assert_eq!(graph.get_edge(edges[2]), Some(&3));
+        assert_eq!(graph.edge_count(), 2);
+        let new_edge = graph.add_edge(nodes[3], nodes[0], 6);
+        assert_ne!(new_edge, edges[4]);
+    }
+
+    #[test]
+    fn remove_node() {
+        let (mut graph, nodes, edges) = diamond();
+        assert_eq!(graph.remove_node(nodes[3]), Some("d"));
+        assert_eq!(graph.remove_node(nodes[3]), None);
+        assert_eq!(graph.get_node(nodes[3]), None);
+        assert!(!graph.contains_node(nodes[3]));
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.get_edge(edges[2]), None);
+        assert_eq!(graph.edge_endpoints(edges[0]), Some((nodes[0], nodes[1])));
+        assert_eq!(successor_data(&graph, nodes[0]), vec![2, 1]);
+        assert_eq!(successor_data(&graph, nodes[1]), Vec::<i32>::new());
+        let names: Vec<&str> = graph.nodes().map(|(_, &name)| name).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn mutate_and_retain() {
+        let (mut graph, nodes, edges) = diamond();
+        *graph.get_node_mut(nodes[1]).unwrap() = "B";
+        *graph.get_edge_mut(edges[3]).unwrap() += 10;
+        assert_eq!(graph.get_node(nodes[1]), Some(&"B"));
+        assert_eq!(graph.get_edge(edges[3]), Some(&14));
+
+        graph.retain_edges(|source, _, data| {
+            *data *= 2;
+            source != nodes[0]
+        });
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(successor_data(&graph, nodes[0]), Vec::<i32>::new());
+        assert_eq!(successor_data(&graph, nodes[2]), vec![28]);
+        let outgoing: Vec<_> = graph.outgoing_edges(nodes[1]).collect();
+        assert_eq!(outgoing, vec![(edges[2], &6, nodes[3])]);
+    }
 }
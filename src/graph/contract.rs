@@ -0,0 +1,240 @@
+// Corridor contraction: replacing runs of uninteresting nodes with single
+// edges.
+//
+// Mazes parsed cell by cell produce graphs where most nodes are floor with
+// one way in and one way out.  Searches over them are far faster once each
+// corridor between two interesting nodes (keys, doors, portals, or the
+// junctions where corridors meet) is a single edge carrying the combined
+// data of the edges it replaces.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{Graph, NodeId};
+
+impl<N: Clone, E: Clone> Graph<N, E> {
+    /// Builds a graph without the pass-through nodes: those that fail `keep`
+    /// and are joined, by edges either way, to exactly two other nodes.
+    /// Every corridor, a walk from one remaining node to another through
+    /// pass-through nodes only, becomes an edge whose data is built by
+    /// folding `combine` over the data of the edges along it.  Corridors
+    /// leading back to where they started are dropped.
+    ///
+    /// Each corridor is a single way through, so the new edges are exact
+    /// whatever the edge data holds.  Two nodes joined by several corridors,
+    /// or by a corridor and an edge, get an edge for each.
+    ///
+    /// Returns the new graph and a map from the old id of every remaining
+    /// node to its new id.
+    pub fn contract(
+        &self,
+        mut keep: impl FnMut(NodeId, &N) -> bool,
+        mut combine: impl FnMut(&E, &E) -> E,
+    ) -> (Graph<N, E>, HashMap<NodeId, NodeId>) {
+        let mut neighbors: HashMap<NodeId, HashSet<NodeId>> = HashMap::new();
+        for (_, source, _, target) in self.edges() {
+            if source != target {
+                neighbors.entry(source).or_default().insert(target);
+                neighbors.entry(target).or_default().insert(source);
+            }
+        }
+
+        let mut contracted = Graph::new();
+        let mut new_ids = HashMap::new();
+        for (id, data) in self.nodes() {
+            let pass_through = neighbors.get(&id).is_some_and(|others| others.len() == 2);
+            if keep(id, data) || !pass_through {
+                new_ids.insert(id, contracted.add_node(data.clone()));
+            }
+        }
+
+        for (source, _) in self.nodes() {
+            let Some(&new_source) = new_ids.get(&source) else {
+                continue;
+            };
+            // Follow each edge out of the source through pass-through nodes,
+            // never turning back, until it reaches a remaining node.
+            let mut walks: Vec<(NodeId, NodeId, E)> = self
+                .successors(source)
+                .map(|(data, target)| (source, target, data.clone()))
+                .collect();
+            while let Some((previous, node, data)) = walks.pop() {
+                if node == source {
+                    continue;
+                }
+                if let Some(&new_target) = new_ids.get(&node) {
+                    contracted.add_edge(new_source, new_target, data);
+                    continue;
+                }
+                for (next_data, next) in self.successors(node) {
+                    if next != previous && next != node {
+                        walks.push((node, next, combine(&data, next_data)));
+                    }
+                }
+            }
+        }
+        (contracted, new_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Step {
+        distance: u32,
+        height_change: i32,
+    }
+
+    fn step(distance: u32, height_change: i32) -> Step {
+        Step {
+            distance,
+            height_change,
+        }
+    }
+
+    fn join(a: &Step, b: &Step) -> Step {
+        step(a.distance + b.distance, a.height_change + b.height_change)
+    }
+
+    // Adds edges both ways between consecutive nodes of `chain`.
+    fn corridor(graph: &mut Graph<char, Step>, chain: &[NodeId], data: Step) {
+        for pair in chain.windows(2) {
+            graph.add_edge(pair[0], pair[1], data);
+            graph.add_edge(pair[1], pair[0], data);
+        }
+    }
+
+    // Contracted edges as (source, target, data), sorted.
+    fn edges(graph: &Graph<char, Step>) -> Vec<(char, char, u32, i32)> {
+        let name = |id| *graph.get_node(id).unwrap();
+        let mut edges: Vec<_> = graph
+            .edges()
+            .map(|(_, source, data, target)| {
+                (
+                    name(source),
+                    name(target),
+                    data.distance,
+                    data.height_change,
+                )
+            })
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    #[test]
+    fn test_contract_corridors() {
+        //   A . . B . C
+        //         .
+        //         D
+        // with a one-way, height changing edge from C to A.
+        let mut graph = Graph::new();
+        let ids: Vec<NodeId> = "A12B3C4D".chars().map(|ch| graph.add_node(ch)).collect();
+        let [a, one, two, b, three, c, four, d] = ids[..] else {
+            unreachable!()
+        };
+        corridor(&mut graph, &[a, one, two, b, three, c], step(1, 0));
+        corridor(&mut graph, &[b, four, d], step(1, 0));
+        graph.add_edge(c, a, step(1, 1));
+
+        let (contracted, new_ids) = graph.contract(|_, ch| ch.is_ascii_uppercase(), join);
+        assert_eq!(contracted.node_count(), 4);
+        assert_eq!(new_ids.len(), 4);
+        assert!(!new_ids.contains_key(&one));
+        assert_eq!(contracted.get_node(new_ids[&d]), Some(&'D'));
+        assert_eq!(
+            edges(&contracted),
+            vec![
+                ('A', 'B', 3, 0),
+                ('B', 'A', 3, 0),
+                ('B', 'C', 2, 0),
+                ('B', 'D', 2, 0),
+                ('C', 'A', 1, 1),
+                ('C', 'B', 2, 0),
+                ('D', 'B', 2, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contract_parallel_corridors() {
+        // Two corridors of different lengths join A and B, and a loop from
+        // A back to itself is dropped.
+        let mut graph = Graph::new();
+        let ids: Vec<NodeId> = "AB12345".chars().map(|ch| graph.add_node(ch)).collect();
+        let [a, b, one, two, three, four, five] = ids[..] else {
+            unreachable!()
+        };
+        corridor(&mut graph, &[a, one, b], step(1, 0));
+        corridor(&mut graph, &[a, two, three, b], step(1, 0));
+        corridor(&mut graph, &[a, four, five, a], step(1, 0));
+
+        let (contracted, _) = graph.contract(|_, ch| ch.is_ascii_uppercase(), join);
+        assert_eq!(
+            edges(&contracted),
+            vec![
+                ('A', 'B', 2, 0),
+                ('A', 'B', 3, 0),
+                ('B', 'A', 2, 0),
+                ('B', 'A', 3, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contract_keeps_costlier_corridors() {
+        // A portal joins A and B directly, changing height, alongside a
+        // longer corridor that stays level.  Both survive.
+        let mut graph = Graph::new();
+        let ids: Vec<NodeId> = "AB12".chars().map(|ch| graph.add_node(ch)).collect();
+        let [a, b, one, two] = ids[..] else {
+            unreachable!()
+        };
+        graph.add_edge(a, b, step(1, -1));
+        graph.add_edge(b, a, step(1, 1));
+        corridor(&mut graph, &[a, one, two, b], step(1, 0));
+
+        let (contracted, _) = graph.contract(|_, ch| ch.is_ascii_uppercase(), join);
+        assert_eq!(
+            edges(&contracted),
+            vec![
+                ('A', 'B', 1, -1),
+                ('A', 'B', 3, 0),
+                ('B', 'A', 1, 1),
+                ('B', 'A', 3, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contract_junctions() {
+        // Floor where corridors meet, and at dead ends, is not a pass-through
+        // node, so it stays.
+        //   A 1 2 B
+        //     3
+        //     4
+        let mut graph = Graph::new();
+        let ids: Vec<NodeId> = "A12B34".chars().map(|ch| graph.add_node(ch)).collect();
+        let [a, one, two, b, three, four] = ids[..] else {
+            unreachable!()
+        };
+        corridor(&mut graph, &[a, one, two, b], step(1, 0));
+        corridor(&mut graph, &[one, three, four], step(1, 0));
+
+        let (contracted, new_ids) = graph.contract(|_, ch| ch.is_ascii_uppercase(), join);
+        assert_eq!(contracted.node_count(), 4);
+        assert!(new_ids.contains_key(&one) && new_ids.contains_key(&four));
+        assert_eq!(
+            edges(&contracted),
+            vec![
+                ('1', '4', 2, 0),
+                ('1', 'A', 1, 0),
+                ('1', 'B', 2, 0),
+                ('4', '1', 2, 0),
+                ('A', '1', 1, 0),
+                ('B', '1', 2, 0),
+            ]
+        );
+    }
+}
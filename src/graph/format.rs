@@ -0,0 +1,258 @@
+// Reading and writing a `Graph` in formats other tools understand.
+//
+// Graphviz DOT is write-only and meant for looking at a graph:
+//
+//     cargo run --bin 20 -- --dot | dot -Tsvg > maze.svg
+//
+// The JSON format can also be read back.  It is a single object holding the
+// nodes and the edges, each with a string label produced by the caller:
+//
+//     {
+//       "nodes": [{"id": 0, "label": "AA"}, {"id": 1, "label": "ZZ"}],
+//       "edges": [{"id": 0, "source": 0, "target": 1, "label": "26"}]
+//     }
+//
+// Node ids are the indices of the graph's `NodeId`s and edge ids those of its
+// `EdgeId`s, so they have gaps where nodes or edges were removed.  Edges are
+// listed in the order they were added, which keeps the order of each node's
+// outgoing edges across a round trip.
+
+use std::collections::HashMap;
+use std::io;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::{Value, json};
+
+use super::{EdgeId, Graph, NodeId};
+
+impl<N, E> Graph<N, E> {
+    /// Writes the graph as a Graphviz `digraph`, labelling nodes and edges
+    /// with the given closures.
+    pub fn write_dot(
+        &self,
+        w: &mut impl io::Write,
+        mut node_label: impl FnMut(NodeId, &N) -> String,
+        mut edge_label: impl FnMut(EdgeId, &E) -> String,
+    ) -> io::Result<()> {
+        writeln!(w, "digraph {{")?;
+        for (id, data) in self.nodes() {
+            writeln!(
+                w,
+                "    {} [label={}];",
+                id.index(),
+                dot_string(&node_label(id, data))
+            )?;
+        }
+        for (id, source, data, target) in self.edges() {
+            writeln!(
+                w,
+                "    {} -> {} [label={}];",
+                source.index(),
+                target.index(),
+                dot_string(&edge_label(id, data))
+            )?;
+        }
+        writeln!(w, "}}")
+    }
+
+    /// Writes the graph in the JSON format described at the top of this
+    /// module, labelling nodes and edges with the given closures.
+    pub fn write_json(
+        &self,
+        w: &mut impl io::Write,
+        mut node_label: impl FnMut(NodeId, &N) -> String,
+        mut edge_label: impl FnMut(EdgeId, &E) -> String,
+    ) -> io::Result<()> {
+        let nodes: Vec<Value> = self
+            .nodes()
+            .map(|(id, data)| json!({"id": id.index(), "label": node_label(id, data)}))
+            .collect();
+        let edges: Vec<Value> = self
+            .edges()
+            .map(|(id, source, data, target)| {
+                json!({
+                    "id": id.index(),
+                    "source": source.index(),
+                    "target": target.index(),
+                    "label": edge_label(id, data),
+                })
+            })
+            .collect();
+        serde_json::to_writer_pretty(&mut *w, &json!({"nodes": nodes, "edges": edges}))?;
+        writeln!(w)
+    }
+
+    /// Reads a graph written by [`Graph::write_json`], turning labels back
+    /// into node and edge data with the given closures.
+    ///
+    /// Nodes are added in the order they are listed, so the new graph's
+    /// ids have no gaps and generally differ from the ids in the file.
+    pub fn read_json(
+        text: &str,
+        mut node_data: impl FnMut(&str) -> Result<N>,
+        mut edge_data: impl FnMut(&str) -> Result<E>,
+    ) -> Result<Self> {
+        let value: Value = serde_json::from_str(text)?;
+        let mut graph = Graph::new();
+        let mut ids = HashMap::new();
+        for node in array_field(&value, "nodes")? {
+            let id = index_field(node, "id")?;
+            let data = node_data(string_field(node, "label")?)
+                .with_context(|| format!("bad label on node {}", id))?;
+            if ids.insert(id, graph.add_node(data)).is_some() {
+                bail!("duplicate node id {}", id);
+            }
+        }
+        for edge in array_field(&value, "edges")? {
+            let endpoint = |field| {
+                let id = index_field(edge, field)?;
+                ids.get(&id)
+                    .copied()
+                    .ok_or_else(|| anyhow!("edge {} refers to unknown node {}", field, id))
+            };
+            let (source, target) = (endpoint("source")?, endpoint("target")?);
+            let data = edge_data(string_field(edge, "label")?)
+                .with_context(|| format!("bad label on edge {}", edge))?;
+            graph.add_edge(source, target, data);
+        }
+        Ok(graph)
+    }
+}
+
+// Quotes `text` as a DOT string.
+pub(crate) fn dot_string(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for ch in text.chars() {
+        match ch {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(ch);
+            }
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn array_field<'a>(value: &'a Value, field: &str) -> Result<&'a Vec<Value>> {
+    value[field]
+        .as_array()
+        .ok_or_else(|| anyhow!("expected array \"{}\" in {}", field, value))
+}
+
+fn string_field<'a>(value: &'a Value, field: &str) -> Result<&'a str> {
+    value[field]
+        .as_str()
+        .ok_or_else(|| anyhow!("expected string \"{}\" in {}", field, value))
+}
+
+fn index_field(value: &Value, field: &str) -> Result<usize> {
+    value[field]
+        .as_u64()
+        .and_then(|n| usize::try_from(n).ok())
+        .ok_or_else(|| anyhow!("expected index \"{}\" in {}", field, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Graph<String, u32> {
+        let mut graph = Graph::new();
+        let a = graph.add_node("AA".to_string());
+        let b = graph.add_node("say \"hi\"".to_string());
+        let c = graph.add_node("ZZ".to_string());
+        graph.add_edge(a, b, 4);
+        graph.add_edge(a, c, 7);
+        graph.add_edge(b, c, 2);
+        graph
+    }
+
+    #[test]
+    fn test_write_dot() {
+        let graph = example();
+        let mut dot = Vec::new();
+        graph
+            .write_dot(
+                &mut dot,
+                |_, name| name.clone(),
+                |_, distance| distance.to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(dot).unwrap(),
+            "digraph {
+    0 [label=\"AA\"];
+    1 [label=\"say \\\"hi\\\"\"];
+    2 [label=\"ZZ\"];
+    0 -> 1 [label=\"4\"];
+    0 -> 2 [label=\"7\"];
+    1 -> 2 [label=\"2\"];
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut graph = example();
+        let removed = graph.nodes().next().unwrap().0;
+        graph.remove_node(removed);
+        graph.add_node("new".to_string());
+
+        let mut json = Vec::new();
+        graph
+            .write_json(
+                &mut json,
+                |_, name| name.clone(),
+                |_, distance| distance.to_string(),
+            )
+            .unwrap();
+        let json = String::from_utf8(json).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["nodes"][0], json!({"id": 1, "label": "say \"hi\""}));
+        assert_eq!(
+            value["edges"],
+            json!([{"id": 2, "source": 1, "target": 2, "label": "2"}])
+        );
+
+        let read: Graph<String, u32> = Graph::read_json(
+            &json,
+            |label| Ok(label.to_string()),
+            |label| Ok(label.parse()?),
+        )
+        .unwrap();
+        let names: Vec<&String> = read.nodes().map(|(_, name)| name).collect();
+        assert_eq!(names, vec!["say \"hi\"", "ZZ", "new"]);
+        let edges: Vec<(&String, u32, &String)> = read
+            .edges()
+            .map(|(_, source, &distance, target)| {
+                (
+                    read.get_node(source).unwrap(),
+                    distance,
+                    read.get_node(target).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            edges,
+            vec![(&"say \"hi\"".to_string(), 2, &"ZZ".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_read_json_errors() {
+        let read = |text| Graph::<(), u32>::read_json(text, |_| Ok(()), |label| Ok(label.parse()?));
+        assert!(read("[]").is_err());
+        assert!(read(r#"{"nodes": [{"id": 0, "label": ""}], "edges": [{"source": 0, "target": 1, "label": "1"}]}"#).is_err());
+        assert!(read(r#"{"nodes": [{"id": 0, "label": ""}], "edges": [{"source": 0, "target": 0, "label": "x"}]}"#).is_err());
+        assert!(
+            read(r#"{"nodes": [{"id": 0, "label": ""}, {"id": 0, "label": ""}], "edges": []}"#)
+                .is_err()
+        );
+        let graph = read(r#"{"nodes": [{"id": 5, "label": ""}], "edges": [{"source": 5, "target": 5, "label": "3"}]}"#).unwrap();
+        assert_eq!((graph.node_count(), graph.edge_count()), (1, 1));
+    }
+}
@@ -0,0 +1,334 @@
+// Searching a graph replicated across integer levels.
+//
+// Recursive mazes are a base graph copied onto every level, where some
+// edges lead from one level to another: a portal that goes one level
+// deeper, or a grid cell whose neighbor is a whole row of the grid nested
+// inside it.  A `Layered` graph describes that product without building it,
+// so the number of levels need not be known up front.
+
+use std::fmt;
+
+use num::Zero;
+
+use super::search::{Path, best_first, breadth_first};
+use super::{EdgeId, Graph, NodeId};
+
+/// A node of a [`Layered`] graph: a node of the base graph on some level.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LevelNode {
+    pub level: i32,
+    pub node: NodeId,
+}
+
+impl LevelNode {
+    pub fn new(level: i32, node: NodeId) -> LevelNode {
+        LevelNode { level, node }
+    }
+}
+
+impl fmt::Debug for LevelNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{}", self.node, self.level)
+    }
+}
+
+/// A base graph with a copy on every level, where each edge leads from its
+/// source on some level to its target on that level plus `level_change` of
+/// the edge's data.
+///
+/// Levels can be limited to a range with [`Layered::min_level`] and
+/// [`Layered::max_level`]; edges that would leave the range are ignored.
+pub struct Layered<'graph, N, E, F> {
+    graph: &'graph Graph<N, E>,
+    level_change: F,
+    min_level: Option<i32>,
+    max_level: Option<i32>,
+    // Whether any edge leads up a level, and whether any leads down.
+    up: bool,
+    down: bool,
+}
+
+impl<'graph, N, E, F> Layered<'graph, N, E, F>
+where
+    F: Fn(&E) -> i32,
+{
+    pub fn new(graph: &'graph Graph<N, E>, level_change: F) -> Self {
+        let changes = || graph.edges().map(|(_, _, data, _)| level_change(data));
+        let up = changes().any(|change| change > 0);
+        let down = changes().any(|change| change < 0);
+        Layered {
+            graph,
+            level_change,
+            min_level: None,
+            max_level: None,
+            up,
+            down,
+        }
+    }
+
+    /// Removes every level below `level`.
+    pub fn min_level(mut self, level: i32) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+
+    /// Removes every level above `level`, capping how deep a search may
+    /// go.  A search misses any path that needs a higher level, so a cap
+    /// that is only a guess at how deep paths go can hide the answer.
+    pub fn max_level(mut self, level: i32) -> Self {
+        self.max_level = Some(level);
+        self
+    }
+
+    pub fn graph(&self) -> &'graph Graph<N, E> {
+        self.graph
+    }
+
+    pub fn contains_level(&self, level: i32) -> bool {
+        self.min_level.is_none_or(|min| level >= min)
+            && self.max_level.is_none_or(|max| level <= max)
+    }
+
+    /// The edges out of `from`, with their data and targets.
+    pub fn outgoing_edges(
+        &self,
+        from: LevelNode,
+    ) -> impl Iterator<Item = (EdgeId, &'graph E, LevelNode)> + '_ {
+        self.graph
+            .outgoing_edges(from.node)
+            .filter_map(move |(edge, data, target)| {
+                let level = from.level.checked_add((self.level_change)(data))?;
+                self.contains_level(level)
+                    .then_some((edge, data, LevelNode::new(level, target)))
+            })
+    }
+
+    /// The nodes an edge out of `from` leads to.
+    pub fn neighbors(&self, from: LevelNode) -> impl Iterator<Item = LevelNode> + '_ {
+        self.outgoing_edges(from).map(|(_, _, target)| target)
+    }
+
+    /// Finds a path from `start` to `goal` with the fewest edges.
+    ///
+    /// Levels from which `goal` cannot be reached are skipped, but when edges
+    /// lead both up and down a level the search relies on
+    /// [`Layered::min_level`] and [`Layered::max_level`] to end if there is
+    /// no path.  Panics if a limit it needs has not been set.
+    pub fn bfs(&self, start: LevelNode, goal: LevelNode) -> Option<Path<usize, LevelNode>> {
+        let (lowest, highest) = self.reachable_levels(start, goal);
+        breadth_first(
+            start,
+            |from| {
+                self.outgoing_edges(from)
+                    .filter(move |(_, _, to)| (lowest..=highest).contains(&to.level))
+                    .map(|(edge, _, to)| (edge, to))
+            },
+            |node| node == goal,
+        )
+    }
+
+    /// Finds a cheapest path from `start` to `goal`.  `weight` gives the
+    /// cost of each edge, or `None` for edges that must not be used.
+    ///
+    /// Like [`Layered::bfs`], panics if the levels searched are unbounded.
+    pub fn dijkstra<C>(
+        &self,
+        start: LevelNode,
+        mut weight: impl FnMut(&E) -> Option<C>,
+        goal: LevelNode,
+    ) -> Option<Path<C, LevelNode>>
+    where
+        C: Zero + Ord + Copy,
+    {
+        let (lowest, highest) = self.reachable_levels(start, goal);
+        let successors = |from| {
+            self.outgoing_edges(from)
+                .filter(|(_, _, to)| (lowest..=highest).contains(&to.level))
+                .filter_map(|(edge, data, to)| Some((edge, weight(data)?, to)))
+                .collect()
+        };
+        best_first(start, successors, |_| C::zero(), |node| node == goal).0
+    }
+
+    // The levels a path from `start` to `goal` can pass through.  When
+    // edges only lead up a level, those are the levels from the start up to
+    // the goal, and likewise down; when they lead both ways the search needs
+    // limits set, or it would wander off forever when there is no path.
+    fn reachable_levels(&self, start: LevelNode, goal: LevelNode) -> (i32, i32) {
+        match (self.up, self.down) {
+            (_, false) => (start.level, goal.level),
+            (false, true) => (goal.level, start.level),
+            (true, true) => (
+                self.min_level
+                    .expect("edges lead both up and down a level, so a search needs a min_level"),
+                self.max_level
+                    .expect("edges lead both up and down a level, so a search needs a max_level"),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy)]
+    struct Edge {
+        distance: u32,
+        height_change: i32,
+    }
+
+    fn edge(distance: u32, height_change: i32) -> Edge {
+        Edge {
+            distance,
+            height_change,
+        }
+    }
+
+    // A donut: the start and end on the outer edge are 10 apart, and a
+    // portal on the inside, 1 from the start, leads one level deeper to the
+    // outer edge, 1 from the end.
+    fn donut() -> (Graph<&'static str, Edge>, [NodeId; 4]) {
+        let mut graph = Graph::new();
+        let start = graph.add_node("AA");
+        let end = graph.add_node("ZZ");
+        let outer = graph.add_node("BC outer");
+        let inner = graph.add_node("BC inner");
+        for (a, b, distance) in [
+            (start, end, 10),
+            (start, inner, 1),
+            (outer, end, 1),
+            (inner, end, 12),
+        ] {
+            graph.add_edge(a, b, edge(distance, 0));
+            graph.add_edge(b, a, edge(distance, 0));
+        }
+        graph.add_edge(inner, outer, edge(1, 1));
+        graph.add_edge(outer, inner, edge(1, -1));
+        (graph, [start, end, outer, inner])
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let (graph, [start, _, outer, inner]) = donut();
+        let layered = Layered::new(&graph, |edge: &Edge| edge.height_change).min_level(0);
+        let mut neighbors: Vec<LevelNode> = layered.neighbors(LevelNode::new(0, inner)).collect();
+        neighbors.sort();
+        assert_eq!(neighbors.len(), 3);
+        assert!(neighbors.contains(&LevelNode::new(1, outer)));
+        assert!(neighbors.contains(&LevelNode::new(0, start)));
+
+        // The outer portal leads nowhere on the top level.
+        assert!(
+            !layered
+                .neighbors(LevelNode::new(0, outer))
+                .any(|n| n.node == inner)
+        );
+        assert!(
+            layered
+                .neighbors(LevelNode::new(1, outer))
+                .any(|n| n == LevelNode::new(0, inner))
+        );
+
+        let capped = Layered::new(&graph, |edge: &Edge| edge.height_change).max_level(0);
+        assert!(
+            !capped
+                .neighbors(LevelNode::new(0, inner))
+                .any(|n| n.node == outer)
+        );
+        assert!(capped.contains_level(-5) && !capped.contains_level(1));
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let (graph, [start, end, outer, inner]) = donut();
+        let layered = Layered::new(&graph, |edge: &Edge| edge.height_change)
+            .min_level(0)
+            .max_level(10);
+        let path = layered
+            .dijkstra(
+                LevelNode::new(0, start),
+                |edge| Some(edge.distance),
+                LevelNode::new(0, end),
+            )
+            .unwrap();
+        assert_eq!(path.cost, 10);
+        assert_eq!(
+            path.nodes,
+            vec![LevelNode::new(0, start), LevelNode::new(0, end)]
+        );
+
+        // Ending a level down, the portal is the way.
+        let path = layered
+            .dijkstra(
+                LevelNode::new(0, start),
+                |edge| Some(edge.distance),
+                LevelNode::new(1, end),
+            )
+            .unwrap();
+        assert_eq!(path.cost, 3);
+        assert_eq!(
+            path.nodes,
+            vec![
+                LevelNode::new(0, start),
+                LevelNode::new(0, inner),
+                LevelNode::new(1, outer),
+                LevelNode::new(1, end),
+            ]
+        );
+        assert_eq!(path.edges.len(), 3);
+        assert_eq!(graph.get_edge(path.edges[1]).unwrap().height_change, 1);
+
+        let path = layered
+            .bfs(LevelNode::new(0, start), LevelNode::new(2, end))
+            .unwrap();
+        assert_eq!(path.cost, 6);
+        let capped = Layered::new(&graph, |edge: &Edge| edge.height_change)
+            .min_level(0)
+            .max_level(1);
+        assert!(
+            capped
+                .bfs(LevelNode::new(0, start), LevelNode::new(2, end))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_prune_unreachable_levels() {
+        // Edges only ever lead deeper, so a search for a goal that isn't
+        // there must still end even with no depth cap.
+        let mut graph = Graph::new();
+        let a = graph.add_node(());
+        let b = graph.add_node(());
+        graph.add_edge(a, b, 0);
+        graph.add_edge(b, a, 1);
+        let layered = Layered::new(&graph, |&change: &i32| change);
+        let goal = LevelNode::new(3, a);
+        assert_eq!(
+            layered
+                .bfs(LevelNode::new(0, a), goal)
+                .map(|path| path.cost),
+            Some(6)
+        );
+        assert!(
+            layered
+                .bfs(LevelNode::new(0, a), LevelNode::new(-1, a))
+                .is_none()
+        );
+        assert!(
+            layered
+                .dijkstra(LevelNode::new(4, a), |_| Some(1), goal)
+                .is_none()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "needs a max_level")]
+    fn test_unbounded_search() {
+        // Portals lead both ways and nothing caps the depth, so a search for
+        // an unreachable goal could go on forever.
+        let (graph, [start, _, _, _]) = donut();
+        let layered = Layered::new(&graph, |edge: &Edge| edge.height_change).min_level(0);
+        layered.bfs(LevelNode::new(0, start), LevelNode::new(0, start));
+    }
+}
@@ -0,0 +1,346 @@
+// Shortest-path searches over a `Graph`.
+//
+// Every search returns a `Path` holding the nodes visited and the edges
+// taken, so callers can follow or print the route and not just its length.
+// Edge weights come from a closure over the edge data; returning `None`
+// makes an edge unusable for that search.
+//
+// The searches themselves are written over any node type, so graphs built
+// on top of a `Graph`, such as a `Layered` one, can share them.
+
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+
+use num::Zero;
+
+use super::{EdgeId, Graph, NodeId};
+
+// The cost of reaching each node, and the node and edge it was reached
+// through (none for the start node).
+pub(super) type Reached<C, Node> = HashMap<Node, (C, Option<(Node, EdgeId)>)>;
+
+/// A route through a graph.  `nodes` runs from the start to the end
+/// inclusive, and `edges[i]` leads from `nodes[i]` to `nodes[i + 1]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path<C, Node = NodeId> {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<EdgeId>,
+    pub cost: C,
+}
+
+impl<C, Node: Copy> Path<C, Node> {
+    pub fn start(&self) -> Node {
+        self.nodes[0]
+    }
+
+    pub fn end(&self) -> Node {
+        *self.nodes.last().unwrap()
+    }
+}
+
+/// The cheapest way to reach every node reachable from a start node, as
+/// found by [`Graph::dijkstra_all`].
+#[derive(Debug, Clone)]
+pub struct ShortestPaths<C> {
+    start: NodeId,
+    reached: Reached<C, NodeId>,
+}
+
+impl<C: Copy> ShortestPaths<C> {
+    pub fn start(&self) -> NodeId {
+        self.start
+    }
+
+    /// The cost of the cheapest path to `node`, or `None` if it is
+    /// unreachable.
+    pub fn cost(&self, node: NodeId) -> Option<C> {
+        self.reached.get(&node).map(|&(cost, _)| cost)
+    }
+
+    /// The cheapest path to `node`, or `None` if it is unreachable.
+    pub fn path_to(&self, node: NodeId) -> Option<Path<C>> {
+        self.reached
+            .contains_key(&node)
+            .then(|| build_path(&self.reached, node))
+    }
+
+    /// Every reachable node, including the start, with the cost of
+    /// reaching it, in no particular order.
+    pub fn costs(&self) -> impl Iterator<Item = (NodeId, C)> + '_ {
+        self.reached.iter().map(|(&node, &(cost, _))| (node, cost))
+    }
+}
+
+pub(super) fn build_path<C: Copy, Node: Copy + Eq + Hash>(
+    reached: &Reached<C, Node>,
+    end: Node,
+) -> Path<C, Node> {
+    let cost = reached[&end].0;
+    let mut nodes = vec![end];
+    let mut edges = Vec::new();
+    let mut current = end;
+    while let Some((previous, edge)) = reached[&current].1 {
+        nodes.push(previous);
+        edges.push(edge);
+        current = previous;
+    }
+    nodes.reverse();
+    edges.reverse();
+    Path { nodes, edges, cost }
+}
+
+// Breadth-first search from `start` for the nearest node satisfying
+// `is_goal`, where `successors` lists the edges out of a node and their
+// targets.
+pub(super) fn breadth_first<Node, I>(
+    start: Node,
+    mut successors: impl FnMut(Node) -> I,
+    mut is_goal: impl FnMut(Node) -> bool,
+) -> Option<Path<usize, Node>>
+where
+    Node: Copy + Eq + Hash,
+    I: IntoIterator<Item = (EdgeId, Node)>,
+{
+    let mut reached = HashMap::from([(start, (0, None))]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        if is_goal(node) {
+            return Some(build_path(&reached, node));
+        }
+        let cost = reached[&node].0;
+        for (edge, target) in successors(node) {
+            if let Entry::Vacant(entry) = reached.entry(target) {
+                entry.insert((cost + 1, Some((node, edge))));
+                queue.push_back(target);
+            }
+        }
+    }
+    None
+}
+
+// Best-first search from `start`, expanding nodes in order of their cost
+// plus `heuristic` until one satisfies `is_goal`.  `successors` lists the
+// edges out of a node with their costs and targets.  Returns the goal found,
+// if any, and the cheapest known way to every node reached.
+pub(super) fn best_first<Node, C>(
+    start: Node,
+    mut successors: impl FnMut(Node) -> Vec<(EdgeId, C, Node)>,
+    mut heuristic: impl FnMut(Node) -> C,
+    mut is_goal: impl FnMut(Node) -> bool,
+) -> (Option<Path<C, Node>>, Reached<C, Node>)
+where
+    Node: Copy + Eq + Hash + Ord,
+    C: Zero + Ord + Copy,
+{
+    let mut reached = HashMap::from([(start, (C::zero(), None))]);
+    let mut queue = BinaryHeap::from([Reverse((heuristic(start), C::zero(), start))]);
+    while let Some(Reverse((_, cost, node))) = queue.pop() {
+        if cost > reached[&node].0 {
+            continue; // already reached more cheaply
+        }
+        if is_goal(node) {
+            return (Some(build_path(&reached, node)), reached);
+        }
+        for (edge, edge_cost, target) in successors(node) {
+            let target_cost = cost + edge_cost;
+            if reached
+                .get(&target)
+                .is_none_or(|&(known, _)| target_cost < known)
+            {
+                reached.insert(target, (target_cost, Some((node, edge))));
+                queue.push(Reverse((
+                    target_cost + heuristic(target),
+                    target_cost,
+                    target,
+                )));
+            }
+        }
+    }
+    (None, reached)
+}
+
+impl<N, E> Graph<N, E> {
+    // The edges out of `node` that `weight` allows, with their costs.
+    fn weighted_edges<C>(
+        &self,
+        node: NodeId,
+        weight: &mut impl FnMut(&E) -> Option<C>,
+    ) -> Vec<(EdgeId, C, NodeId)> {
+        self.outgoing_edges(node)
+            .filter_map(|(edge, data, target)| Some((edge, weight(data)?, target)))
+            .collect()
+    }
+
+    /// Finds a path from `start` to the nearest node satisfying `is_goal`
+    /// with the fewest edges.  The cost of the path is its edge count.
+    pub fn bfs(&self, start: NodeId, is_goal: impl FnMut(NodeId) -> bool) -> Option<Path<usize>> {
+        breadth_first(
+            start,
+            |node| {
+                self.outgoing_edges(node)
+                    .map(|(edge, _, target)| (edge, target))
+            },
+            is_goal,
+        )
+    }
+
+    /// Finds a cheapest path from `start` to a node satisfying `is_goal`.
+    /// `weight` gives the cost of each edge, or `None` for edges that must
+    /// not be used.
+    pub fn dijkstra<C>(
+        &self,
+        start: NodeId,
+        weight: impl FnMut(&E) -> Option<C>,
+        is_goal: impl FnMut(NodeId) -> bool,
+    ) -> Option<Path<C>>
+    where
+        C: Zero + Ord + Copy,
+    {
+        self.astar(start, weight, |_| C::zero(), is_goal)
+    }
+
+    /// Finds a cheapest path like [`Graph::dijkstra`], guided by
+    /// `heuristic`, an estimate of the remaining cost from a node to the
+    /// nearest goal.  The path is cheapest provided the heuristic never
+    /// overestimates.
+    pub fn astar<C>(
+        &self,
+        start: NodeId,
+        mut weight: impl FnMut(&E) -> Option<C>,
+        heuristic: impl FnMut(NodeId) -> C,
+        is_goal: impl FnMut(NodeId) -> bool,
+    ) -> Option<Path<C>>
+    where
+        C: Zero + Ord + Copy,
+    {
+        let successors = |node| self.weighted_edges(node, &mut weight);
+        best_first(start, successors, heuristic, is_goal).0
+    }
+
+    /// Finds the cheapest path from `start` to every node reachable from
+    /// it, with edge costs given by `weight` as for [`Graph::dijkstra`].
+    pub fn dijkstra_all<C>(
+        &self,
+        start: NodeId,
+        mut weight: impl FnMut(&E) -> Option<C>,
+    ) -> ShortestPaths<C>
+    where
+        C: Zero + Ord + Copy,
+    {
+        let successors = |node| self.weighted_edges(node, &mut weight);
+        let (_, reached) = best_first(start, successors, |_| C::zero(), |_| false);
+        ShortestPaths { start, reached }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //   a --1--> b --1--> d
+    //   |                 ^
+    //   +--5--> c ---1----+
+    //   a --9--> d
+    //   e (unreachable)
+    fn example() -> (Graph<char, u32>, HashMap<char, NodeId>) {
+        let mut graph = Graph::new();
+        let ids: HashMap<char, NodeId> =
+            "abcde".chars().map(|ch| (ch, graph.add_node(ch))).collect();
+        for (from, to, cost) in [
+            ('a', 'b', 1),
+            ('b', 'd', 1),
+            ('a', 'c', 5),
+            ('c', 'd', 1),
+            ('a', 'd', 9),
+        ] {
+            graph.add_edge(ids[&from], ids[&to], cost);
+        }
+        (graph, ids)
+    }
+
+    fn names(graph: &Graph<char, u32>, nodes: &[NodeId]) -> String {
+        nodes
+            .iter()
+            .map(|&id| *graph.get_node(id).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_bfs() {
+        let (graph, ids) = example();
+        let path = graph.bfs(ids[&'a'], |id| id == ids[&'d']).unwrap();
+        assert_eq!(names(&graph, &path.nodes), "ad");
+        assert_eq!(path.cost, 1);
+        assert_eq!(path.edges.len(), 1);
+        assert_eq!(graph.get_edge(path.edges[0]), Some(&9));
+        assert_eq!((path.start(), path.end()), (ids[&'a'], ids[&'d']));
+
+        let path = graph.bfs(ids[&'a'], |id| id == ids[&'a']).unwrap();
+        assert_eq!((path.nodes.len(), path.edges.len(), path.cost), (1, 0, 0));
+        assert!(graph.bfs(ids[&'a'], |id| id == ids[&'e']).is_none());
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let (graph, ids) = example();
+        let path = graph
+            .dijkstra(ids[&'a'], |&cost| Some(cost), |id| id == ids[&'d'])
+            .unwrap();
+        assert_eq!(names(&graph, &path.nodes), "abd");
+        assert_eq!(path.cost, 2);
+        let costs: Vec<u32> = path
+            .edges
+            .iter()
+            .map(|&e| *graph.get_edge(e).unwrap())
+            .collect();
+        assert_eq!(costs, vec![1, 1]);
+
+        // Skipping the cheap edges leaves the direct one as the cheapest.
+        let path = graph
+            .dijkstra(
+                ids[&'a'],
+                |&cost| (cost > 1).then_some(cost),
+                |id| id == ids[&'d'],
+            )
+            .unwrap();
+        assert_eq!(
+            (names(&graph, &path.nodes), path.cost),
+            ("ad".to_string(), 9)
+        );
+    }
+
+    #[test]
+    fn test_astar() {
+        let (graph, ids) = example();
+        let remaining: HashMap<NodeId, u32> = [('a', 2), ('b', 1), ('c', 1), ('d', 0), ('e', 0)]
+            .map(|(ch, h)| (ids[&ch], h))
+            .into();
+        let path = graph
+            .astar(
+                ids[&'a'],
+                |&cost| Some(cost),
+                |id| remaining[&id],
+                |id| id == ids[&'d'],
+            )
+            .unwrap();
+        assert_eq!(names(&graph, &path.nodes), "abd");
+        assert_eq!(path.cost, 2);
+    }
+
+    #[test]
+    fn test_dijkstra_all() {
+        let (graph, ids) = example();
+        let paths = graph.dijkstra_all(ids[&'a'], |&cost| Some(cost));
+        assert_eq!(paths.start(), ids[&'a']);
+        assert_eq!(paths.cost(ids[&'a']), Some(0));
+        assert_eq!(paths.cost(ids[&'c']), Some(5));
+        assert_eq!(paths.cost(ids[&'d']), Some(2));
+        assert_eq!(paths.cost(ids[&'e']), None);
+        assert_eq!(paths.costs().count(), 4);
+        let path = paths.path_to(ids[&'d']).unwrap();
+        assert_eq!(names(&graph, &path.nodes), "abd");
+        assert!(paths.path_to(ids[&'e']).is_none());
+    }
+}
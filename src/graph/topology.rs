@@ -0,0 +1,287 @@
+// The shape of a directed graph: the order its edges impose on the nodes,
+// its cycles, and what can be reached from where.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+use super::{Graph, NodeId};
+
+/// The error from [`Graph::topological_sort`] on a graph with a cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError {
+    /// The nodes of one cycle, in order; the last has an edge back to the
+    /// first.
+    pub cycle: Vec<NodeId>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "graph has a cycle: ")?;
+        for node in &self.cycle {
+            write!(f, "{:?} -> ", node)?;
+        }
+        write!(f, "{:?}", self.cycle[0])
+    }
+}
+
+impl Error for CycleError {}
+
+impl<N, E> Graph<N, E> {
+    /// Orders the nodes so that every edge leads from an earlier node to a
+    /// later one.  Whenever several nodes could come next, the one added
+    /// first does.
+    pub fn topological_sort(&self) -> Result<Vec<NodeId>, CycleError> {
+        let mut in_degrees: HashMap<NodeId, usize> = self.nodes().map(|(id, _)| (id, 0)).collect();
+        for (_, _, _, target) in self.edges() {
+            *in_degrees.get_mut(&target).unwrap() += 1;
+        }
+        // Ids grow as nodes are added, so the lowest ready id was added first.
+        let mut ready: BinaryHeap<Reverse<NodeId>> = self
+            .nodes()
+            .map(|(id, _)| id)
+            .filter(|id| in_degrees[id] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(self.node_count());
+        while let Some(Reverse(node)) = ready.pop() {
+            order.push(node);
+            for (_, target) in self.successors(node) {
+                let in_degree = in_degrees.get_mut(&target).unwrap();
+                *in_degree -= 1;
+                if *in_degree == 0 {
+                    ready.push(Reverse(target));
+                }
+            }
+        }
+        if order.len() == self.node_count() {
+            Ok(order)
+        } else {
+            Err(CycleError {
+                cycle: self.find_cycle(|node| in_degrees[&node] > 0),
+            })
+        }
+    }
+
+    // Finds a cycle among the nodes satisfying `remaining`, every one of
+    // which must have an edge in from another such node.  Walking those
+    // edges backwards must then come round to a node already seen.
+    fn find_cycle(&self, remaining: impl Fn(NodeId) -> bool) -> Vec<NodeId> {
+        let mut predecessors = HashMap::new();
+        for (_, source, _, target) in self.edges() {
+            if remaining(source) && remaining(target) {
+                predecessors.insert(target, source);
+            }
+        }
+        let mut node = *predecessors.keys().min().unwrap();
+        let mut seen = Vec::new();
+        while !seen.contains(&node) {
+            seen.push(node);
+            node = predecessors[&node];
+        }
+        let start = seen.iter().position(|&seen| seen == node).unwrap();
+        let mut cycle = seen.split_off(start);
+        cycle.reverse();
+        // Start from the lowest id so the same cycle is always reported the
+        // same way.
+        let lowest = cycle
+            .iter()
+            .position(|node| node == cycle.iter().min().unwrap())
+            .unwrap();
+        cycle.rotate_left(lowest);
+        cycle
+    }
+
+    /// Splits the nodes into strongly connected components: maximal sets of
+    /// nodes that can each reach all the others.  Components come in
+    /// topological order, so edges between components only lead to later
+    /// ones, and the nodes of each are sorted by id.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        // Tarjan's algorithm, with an explicit stack in place of recursion.
+        let mut tarjan = Tarjan::default();
+        let mut components = Vec::new();
+        for (root, _) in self.nodes() {
+            if tarjan.indices.contains_key(&root) {
+                continue;
+            }
+            tarjan.visit(root);
+            let mut calls = vec![(root, self.successors(root))];
+            while let Some((node, successors)) = calls.last_mut() {
+                let node = *node;
+                if let Some((_, target)) = successors.next() {
+                    if !tarjan.indices.contains_key(&target) {
+                        tarjan.visit(target);
+                        calls.push((target, self.successors(target)));
+                    } else if tarjan.on_stack.contains(&target) {
+                        tarjan.lower(node, tarjan.indices[&target]);
+                    }
+                    continue;
+                }
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    tarjan.lower(parent, tarjan.low_links[&node]);
+                }
+                if tarjan.low_links[&node] == tarjan.indices[&node] {
+                    components.push(tarjan.pop_component(node));
+                }
+            }
+        }
+        // Tarjan's algorithm finishes components that nothing leaves first.
+        components.reverse();
+        components
+    }
+
+    /// Every node reachable from any of `starts` by following edges,
+    /// including the starts themselves.
+    pub fn reachable_from(&self, starts: impl IntoIterator<Item = NodeId>) -> HashSet<NodeId> {
+        let mut reached: HashSet<NodeId> = HashSet::new();
+        let mut queue: VecDeque<NodeId> = starts
+            .into_iter()
+            .filter(|&start| reached.insert(start))
+            .collect();
+        while let Some(node) = queue.pop_front() {
+            for (_, target) in self.successors(node) {
+                if reached.insert(target) {
+                    queue.push_back(target);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Whether a path leads from `from` to `to`.  Every node can reach
+    /// itself.
+    pub fn is_reachable(&self, from: NodeId, to: NodeId) -> bool {
+        self.bfs(from, |node| node == to).is_some()
+    }
+}
+
+#[derive(Default)]
+struct Tarjan {
+    // The order in which nodes were first visited.
+    indices: HashMap<NodeId, usize>,
+    // The lowest index known to be reachable from each node through nodes
+    // still on the stack.
+    low_links: HashMap<NodeId, usize>,
+    stack: Vec<NodeId>,
+    on_stack: HashSet<NodeId>,
+}
+
+impl Tarjan {
+    fn visit(&mut self, node: NodeId) {
+        let index = self.indices.len();
+        self.indices.insert(node, index);
+        self.low_links.insert(node, index);
+        self.stack.push(node);
+        self.on_stack.insert(node);
+    }
+
+    fn lower(&mut self, node: NodeId, low_link: usize) {
+        let current = self.low_links.get_mut(&node).unwrap();
+        *current = (*current).min(low_link);
+    }
+
+    // Pops the component rooted at `root` off the stack.
+    fn pop_component(&mut self, root: NodeId) -> Vec<NodeId> {
+        let mut component = Vec::new();
+        while let Some(member) = self.stack.pop() {
+            self.on_stack.remove(&member);
+            component.push(member);
+            if member == root {
+                break;
+            }
+        }
+        component.sort();
+        component
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(nodes: &str, edges: &[(char, char)]) -> (Graph<char, ()>, HashMap<char, NodeId>) {
+        let mut graph = Graph::new();
+        let ids: HashMap<char, NodeId> = nodes.chars().map(|ch| (ch, graph.add_node(ch))).collect();
+        for &(source, target) in edges {
+            graph.add_edge(ids[&source], ids[&target], ());
+        }
+        (graph, ids)
+    }
+
+    fn names(graph: &Graph<char, ()>, nodes: &[NodeId]) -> String {
+        nodes
+            .iter()
+            .map(|&id| *graph.get_node(id).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_topological_sort() {
+        let (g, _) = graph(
+            "abcde",
+            &[('d', 'b'), ('b', 'a'), ('c', 'a'), ('e', 'c'), ('d', 'e')],
+        );
+        // b and e could come in either order, and b was added first.
+        assert_eq!(names(&g, &g.topological_sort().unwrap()), "dbeca");
+
+        let (g, _) = graph("", &[]);
+        assert_eq!(g.topological_sort(), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_topological_sort_cycle() {
+        let (g, ids) = graph(
+            "abcde",
+            &[('a', 'b'), ('b', 'c'), ('c', 'd'), ('d', 'b'), ('d', 'e')],
+        );
+        let error = g.topological_sort().unwrap_err();
+        assert_eq!(names(&g, &error.cycle), "bcd");
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "graph has a cycle: {:?} -> {:?} -> {:?} -> {:?}",
+                ids[&'b'], ids[&'c'], ids[&'d'], ids[&'b']
+            )
+        );
+
+        let (g, _) = graph("ab", &[('a', 'b'), ('b', 'b')]);
+        assert_eq!(names(&g, &g.topological_sort().unwrap_err().cycle), "b");
+    }
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let (g, _) = graph(
+            "abcdefg",
+            &[
+                ('a', 'b'),
+                ('b', 'c'),
+                ('c', 'a'),
+                ('c', 'd'),
+                ('d', 'e'),
+                ('e', 'd'),
+                ('f', 'a'),
+                ('e', 'g'),
+            ],
+        );
+        let components: Vec<String> = g
+            .strongly_connected_components()
+            .iter()
+            .map(|component| names(&g, component))
+            .collect();
+        assert_eq!(components, vec!["f", "abc", "de", "g"]);
+    }
+
+    #[test]
+    fn test_reachability() {
+        let (g, ids) = graph("abcde", &[('a', 'b'), ('b', 'c'), ('d', 'c'), ('e', 'e')]);
+        let mut reached: Vec<NodeId> = g.reachable_from([ids[&'a']]).into_iter().collect();
+        reached.sort();
+        assert_eq!(names(&g, &reached), "abc");
+        assert_eq!(g.reachable_from([ids[&'d'], ids[&'e']]).len(), 3);
+        assert!(g.is_reachable(ids[&'a'], ids[&'c']));
+        assert!(g.is_reachable(ids[&'c'], ids[&'c']));
+        assert!(!g.is_reachable(ids[&'c'], ids[&'a']));
+    }
+}
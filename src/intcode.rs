@@ -1,10 +1,21 @@
 // Example import from this file: `use aoc2019::intcode::Foo;`.
 
+use std::collections::HashMap;
 use std::collections::VecDeque;
 
 use anyhow::bail;
 use anyhow::Result;
 
+pub mod extension;
+pub mod image;
+
+use extension::{Extension, ExtensionAction, ExtensionCall, ExtensionOpcode, ParameterKind};
+use image::ProgramImage;
+
+// The standard instruction set uses at most three parameters; extension
+// opcodes may use more.
+const MAX_PARAMETERS: usize = 8;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum Opcode {
     Add,
@@ -20,6 +31,10 @@ enum Opcode {
 }
 
 impl Opcode {
+    fn is_standard(i: i64) -> bool {
+        Opcode::from(i).is_ok()
+    }
+
     fn from(i: i64) -> anyhow::Result<Opcode> {
         match i {
             1 => anyhow::Ok(Opcode::Add),
@@ -74,6 +89,7 @@ enum Instruction {
     Equals(Parameter, Parameter, Parameter),
     Finished,
     AdjustRelativeBase(Parameter),
+    Extension(i64, Vec<Parameter>),
 }
 
 #[derive(Debug, Clone)]
@@ -129,7 +145,7 @@ fn parse_parameter(
     pc: i64,
     memory: &Memory,
 ) -> anyhow::Result<Parameter> {
-    if !(0..=2).contains(&param) {
+    if !(0..MAX_PARAMETERS as i64).contains(&param) {
         panic!("Invalid parameter index: {}", param);
     }
 
@@ -146,8 +162,23 @@ fn parse_parameter(
     Ok(parameter)
 }
 
-fn parse_instruction(pc: i64, memory: &Memory) -> anyhow::Result<Instruction> {
+fn parse_instruction(
+    pc: i64,
+    memory: &Memory,
+    extension_opcodes: &HashMap<i64, ExtensionOpcode>,
+) -> anyhow::Result<Instruction> {
     let instruction = memory.get(pc);
+    if let Some(extension_opcode) = extension_opcodes.get(&(instruction % 100)) {
+        let mut params = Vec::new();
+        for (i, kind) in extension_opcode.parameters.iter().enumerate() {
+            let param = parse_parameter(i as i64, instruction, pc, memory)?;
+            if *kind == ParameterKind::Write && matches!(param.mode, ParameterMode::Immediate) {
+                bail!("Immediate write parameter {} in {}", i, instruction);
+            }
+            params.push(param);
+        }
+        return Ok(Instruction::Extension(instruction % 100, params));
+    }
     let op = Opcode::from(instruction % 100)?;
     let instr = match op {
         Opcode::Add => {
@@ -223,6 +254,8 @@ pub struct Computer {
     finished: bool,
     trace: bool,
     step: i32,
+    extensions: Vec<Box<dyn Extension>>,
+    extension_opcodes: HashMap<i64, ExtensionOpcode>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -230,6 +263,8 @@ pub enum RunState {
     BlockedOnInput,
     BlockedOnOutput,
     Finished,
+    /// An extension opcode trapped; see [`ExtensionAction::Trap`].
+    Trapped,
 }
 
 #[derive(Debug, PartialEq)]
@@ -238,6 +273,7 @@ pub enum StepState {
     BlockedOnInput,
     BlockedOnOutput,
     Finished,
+    Trapped,
 }
 
 impl Computer {
@@ -251,19 +287,60 @@ impl Computer {
             finished: false,
             trace: false,
             step: 0,
+            extensions: Vec::new(),
+            extension_opcodes: HashMap::new(),
         }
     }
 
+    /// Creates a [`Computer`] from a program in the comma-separated puzzle
+    /// input format.  Panics if the text is malformed.
     pub fn parse(text: &str) -> Computer {
+        let image = ProgramImage::parse_comma(text)
+            .unwrap_or_else(|e| panic!("Invalid Intcode program: {}", e));
+        Computer::from_image(&image)
+    }
+
+    pub fn from_image(image: &ProgramImage) -> Computer {
         let mut computer = Computer::new();
-        for number in text.trim_end().split(',') {
-            computer.memory.push(number.parse().unwrap_or_else(|e| {
-                panic!("Invalid numerical string: \"{}\" error: {}", number, e);
-            }));
+        for &word in image.words() {
+            computer.memory.push(word);
         }
         computer
     }
 
+    /// Registers an extension for the opcodes it claims.  Fails if it claims
+    /// a standard opcode, one already claimed by another extension, or
+    /// declares too many parameters.
+    pub fn register_extension(&mut self, extension: impl Extension + 'static) -> Result<()> {
+        let index = self.extensions.len();
+        let mut claimed = HashMap::new();
+        for opcode in extension.opcodes() {
+            if !(0..100).contains(&opcode) {
+                bail!("Extension opcode {} is not in 0..100", opcode);
+            }
+            if Opcode::is_standard(opcode) {
+                bail!("Extension claims standard opcode {}", opcode);
+            }
+            if self.extension_opcodes.contains_key(&opcode) || claimed.contains_key(&opcode) {
+                bail!("Extension opcode {} is already claimed", opcode);
+            }
+            let parameters = extension.parameters(opcode);
+            if parameters.len() > MAX_PARAMETERS {
+                bail!("Extension opcode {} has too many parameters", opcode);
+            }
+            claimed.insert(
+                opcode,
+                ExtensionOpcode {
+                    extension: index,
+                    parameters,
+                },
+            );
+        }
+        self.extension_opcodes.extend(claimed);
+        self.extensions.push(Box::new(extension));
+        Ok(())
+    }
+
     fn store(&mut self, param: Parameter, value: i64) {
         let address = match param.mode {
             ParameterMode::Position => param.value,
@@ -300,6 +377,10 @@ impl Computer {
         self.memory.set(index, value);
     }
 
+    pub fn peek(&self, index: i64) -> i64 {
+        self.memory.get(index)
+    }
+
     pub fn run(&mut self) -> RunState {
         loop {
             match self.step() {
@@ -307,6 +388,7 @@ impl Computer {
                 StepState::BlockedOnInput => return RunState::BlockedOnInput,
                 StepState::BlockedOnOutput => return RunState::BlockedOnOutput,
                 StepState::Finished => return RunState::Finished,
+                StepState::Trapped => return RunState::Trapped,
             }
         }
     }
@@ -329,7 +411,7 @@ impl Computer {
         let mut out = Vec::new();
         loop {
             match self.run() {
-                RunState::BlockedOnInput | RunState::Finished => break,
+                RunState::BlockedOnInput | RunState::Finished | RunState::Trapped => break,
                 RunState::BlockedOnOutput => {
                     out.push(self.output.take().unwrap());
                 }
@@ -346,7 +428,7 @@ impl Computer {
         loop {
             match self.step() {
                 StepState::Running => {}
-                StepState::BlockedOnInput | StepState::Finished => break,
+                StepState::BlockedOnInput | StepState::Finished | StepState::Trapped => break,
                 StepState::BlockedOnOutput => {
                     let output = self.output.unwrap();
                     if (0..128).contains(&output) {
@@ -377,8 +459,8 @@ impl Computer {
             println!("step {}: pc={}", self.step, self.pc);
         }
 
-        let instruction =
-            parse_instruction(self.pc, &self.memory).expect("parse instruction failed");
+        let instruction = parse_instruction(self.pc, &self.memory, &self.extension_opcodes)
+            .expect("parse instruction failed");
         if self.trace {
             println!("  instruction: {:?}", instruction);
         }
@@ -457,9 +539,48 @@ impl Computer {
                 self.finished = true;
                 return StepState::Finished;
             }
+            Instruction::Extension(opcode, params) => {
+                return self.execute_extension(opcode, params);
+            }
         }
         StepState::Running
     }
+
+    fn execute_extension(&mut self, opcode: i64, params: Vec<Parameter>) -> StepState {
+        let index = self.extension_opcodes[&opcode].extension;
+        let length = 1 + params.len() as i64;
+        // Move the extensions out so the call can borrow the computer.
+        let mut extensions = std::mem::take(&mut self.extensions);
+        let action = extensions[index].execute(&mut ExtensionCall {
+            computer: self,
+            opcode,
+            params,
+        });
+        self.extensions = extensions;
+        let action = action.unwrap_or_else(|e| panic!("extension opcode {} failed: {}", opcode, e));
+        if self.trace {
+            println!("  extension action: {:?}", action);
+        }
+
+        match action {
+            ExtensionAction::Continue | ExtensionAction::Trap => self.pc += length,
+            ExtensionAction::Jump(address) => self.pc = address,
+            ExtensionAction::BlockOnInput => return StepState::BlockedOnInput,
+            ExtensionAction::Finish => {
+                self.finished = true;
+                return StepState::Finished;
+            }
+        }
+        // A trap takes precedence over a pending output, which is still
+        // returned by the next step.
+        if action == ExtensionAction::Trap {
+            StepState::Trapped
+        } else if self.output.is_some() {
+            StepState::BlockedOnOutput
+        } else {
+            StepState::Running
+        }
+    }
 }
 
 #[cfg(test)]
@@ -497,6 +618,7 @@ mod tests {
                 RunState::BlockedOnInput => panic!("Input exhausted!"),
                 RunState::BlockedOnOutput => output.push(computer.take_output().unwrap()),
                 RunState::Finished => break,
+                RunState::Trapped => panic!("Unexpected trap!"),
             }
         }
         output
@@ -534,4 +656,153 @@ mod tests {
     fn test_output_input() {
         assert_eq!(run_program("3,0,4,0,99", 42, true), vec![42]);
     }
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Opcode 42 records its one parameter.
+    #[derive(Clone, Default)]
+    struct DebugPrint {
+        printed: Rc<RefCell<Vec<i64>>>,
+    }
+
+    impl Extension for DebugPrint {
+        fn opcodes(&self) -> Vec<i64> {
+            vec![42]
+        }
+
+        fn parameters(&self, _opcode: i64) -> Vec<ParameterKind> {
+            vec![ParameterKind::Read]
+        }
+
+        fn execute(&mut self, call: &mut ExtensionCall) -> Result<ExtensionAction> {
+            self.printed.borrow_mut().push(call.arg(0));
+            Ok(ExtensionAction::Continue)
+        }
+    }
+
+    /// Opcode 50 is a breakpoint.
+    #[derive(Clone)]
+    struct Breakpoint;
+
+    impl Extension for Breakpoint {
+        fn opcodes(&self) -> Vec<i64> {
+            vec![50]
+        }
+
+        fn parameters(&self, _opcode: i64) -> Vec<ParameterKind> {
+            Vec::new()
+        }
+
+        fn execute(&mut self, _call: &mut ExtensionCall) -> Result<ExtensionAction> {
+            Ok(ExtensionAction::Trap)
+        }
+    }
+
+    /// Opcode 60 stores `service(a)` through its second parameter; opcode 61
+    /// reads an input and outputs it doubled.
+    #[derive(Clone)]
+    struct HostCall<F> {
+        service: F,
+    }
+
+    impl<F: Fn(i64) -> i64 + Clone + 'static> Extension for HostCall<F> {
+        fn opcodes(&self) -> Vec<i64> {
+            vec![60, 61]
+        }
+
+        fn parameters(&self, opcode: i64) -> Vec<ParameterKind> {
+            match opcode {
+                60 => vec![ParameterKind::Read, ParameterKind::Write],
+                _ => Vec::new(),
+            }
+        }
+
+        fn execute(&mut self, call: &mut ExtensionCall) -> Result<ExtensionAction> {
+            if call.opcode() == 61 {
+                let Some(value) = call.input() else {
+                    return Ok(ExtensionAction::BlockOnInput);
+                };
+                call.output(2 * value);
+            } else {
+                let result = (self.service)(call.arg(0));
+                call.store(1, result);
+            }
+            Ok(ExtensionAction::Continue)
+        }
+    }
+
+    #[test]
+    fn test_extension_debug_print() {
+        let mut computer = Computer::parse("142,1234,42,5,99,77");
+        let debug = DebugPrint::default();
+        computer.register_extension(debug.clone()).unwrap();
+        assert_eq!(computer.run(), RunState::Finished);
+        assert_eq!(*debug.printed.borrow(), vec![1234, 77]);
+    }
+
+    #[test]
+    fn test_extension_breakpoint() {
+        let mut computer = Computer::parse("1101,1,2,10,50,4,10,99");
+        computer.register_extension(Breakpoint).unwrap();
+        assert_eq!(computer.run(), RunState::Trapped);
+        assert_eq!(computer.pc, 5);
+        assert_eq!(computer.run(), RunState::BlockedOnOutput);
+        assert_eq!(computer.take_output(), Some(3));
+        assert_eq!(computer.run(), RunState::Finished);
+    }
+
+    #[test]
+    fn test_extension_host_call() {
+        let mut computer = Computer::parse("160,7,11,4,11,61,61,99");
+        computer
+            .register_extension(HostCall { service: |x| x * x })
+            .unwrap();
+        // Cloned computers carry their extensions with them.
+        let mut computer = computer.clone();
+        assert_eq!(computer.read_output(), vec![49]);
+        computer.append_input(&[5, 6]);
+        assert_eq!(computer.read_output(), vec![10, 12]);
+        assert_eq!(computer.run(), RunState::Finished);
+    }
+
+    #[test]
+    fn test_extension_registration_errors() {
+        let mut computer = Computer::parse("99");
+        computer.register_extension(Breakpoint).unwrap();
+        assert!(computer.register_extension(Breakpoint).is_err());
+
+        #[derive(Clone)]
+        struct ClaimsAdd;
+        impl Extension for ClaimsAdd {
+            fn opcodes(&self) -> Vec<i64> {
+                vec![1]
+            }
+            fn parameters(&self, _opcode: i64) -> Vec<ParameterKind> {
+                Vec::new()
+            }
+            fn execute(&mut self, _call: &mut ExtensionCall) -> Result<ExtensionAction> {
+                unreachable!()
+            }
+        }
+        assert!(computer.register_extension(ClaimsAdd).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "parse instruction failed")]
+    fn test_unclaimed_opcode_fails() {
+        let mut computer = Computer::parse("42,0,99");
+        computer.register_extension(Breakpoint).unwrap();
+        computer.run();
+    }
+
+    #[test]
+    #[should_panic(expected = "parse instruction failed")]
+    fn test_extension_immediate_write_fails() {
+        let mut computer = Computer::parse("1160,7,9,99");
+        computer
+            .register_extension(HostCall { service: |x| x })
+            .unwrap();
+        computer.run();
+    }
 }
@@ -0,0 +1,138 @@
+// Extension opcodes for experimenting with Intcode dialects.
+//
+// An `Extension` claims opcode numbers outside the standard set and is
+// registered on a `Computer` with `Computer::register_extension`.  When the
+// computer decodes a claimed opcode it parses the declared parameters with the
+// usual position/immediate/relative modes and hands control to the extension
+// through an `ExtensionCall`, which gives access to the parameters, memory and
+// I/O.  Opcodes nobody claims still fail to decode.
+
+use super::{Computer, Parameter, ParameterMode};
+
+/// How an extension opcode uses one of its parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterKind {
+    /// The parameter is read; any mode is allowed.
+    Read,
+    /// The parameter is an address that is written; immediate mode is
+    /// rejected when the instruction is decoded.
+    Write,
+}
+
+/// What the computer does after an extension opcode executes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionAction {
+    /// Continue with the next instruction.
+    Continue,
+    /// Continue at the given address.
+    Jump(i64),
+    /// Re-execute this instruction once more input has been appended.  The
+    /// extension should not have consumed any input.
+    BlockOnInput,
+    /// Stop with `StepState::Trapped`, leaving the program counter after this
+    /// instruction so the next step resumes past it.
+    Trap,
+    /// Stop the program as if it executed opcode 99.
+    Finish,
+}
+
+pub trait Extension: ExtensionClone {
+    /// The opcodes (instruction value modulo 100) this extension handles.
+    fn opcodes(&self) -> Vec<i64>;
+
+    /// The parameters of `opcode`, in order.
+    fn parameters(&self, opcode: i64) -> Vec<ParameterKind>;
+
+    fn execute(&mut self, call: &mut ExtensionCall) -> anyhow::Result<ExtensionAction>;
+}
+
+/// Lets a [`Computer`] with extensions be cloned; implemented for every
+/// `Extension` that is `Clone`.
+pub trait ExtensionClone {
+    fn clone_box(&self) -> Box<dyn Extension>;
+}
+
+impl<T: Extension + Clone + 'static> ExtensionClone for T {
+    fn clone_box(&self) -> Box<dyn Extension> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Extension> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// The opcode table entry for an opcode claimed by an extension.
+#[derive(Clone, Debug)]
+pub(super) struct ExtensionOpcode {
+    pub(super) extension: usize,
+    pub(super) parameters: Vec<ParameterKind>,
+}
+
+/// The state an extension sees while one of its opcodes executes.
+pub struct ExtensionCall<'a> {
+    pub(super) computer: &'a mut Computer,
+    pub(super) opcode: i64,
+    pub(super) params: Vec<Parameter>,
+}
+
+impl ExtensionCall<'_> {
+    pub fn opcode(&self) -> i64 {
+        self.opcode
+    }
+
+    /// The address of the instruction being executed.
+    pub fn pc(&self) -> i64 {
+        self.computer.pc
+    }
+
+    /// The value of parameter `i`, loaded according to its mode.
+    pub fn arg(&self, i: usize) -> i64 {
+        self.computer.load(self.params[i].clone())
+    }
+
+    /// The address parameter `i` refers to.  Panics for immediate-mode
+    /// parameters.
+    pub fn address(&self, i: usize) -> i64 {
+        let param = &self.params[i];
+        match param.mode {
+            ParameterMode::Position => param.value,
+            ParameterMode::Immediate => panic!("parameter {} is immediate", i),
+            ParameterMode::RelativePosition => self.computer.relative_base + param.value,
+        }
+    }
+
+    /// Stores `value` through parameter `i`.
+    pub fn store(&mut self, i: usize, value: i64) {
+        self.computer.store(self.params[i].clone(), value);
+    }
+
+    pub fn peek(&self, address: i64) -> i64 {
+        self.computer.peek(address)
+    }
+
+    pub fn poke(&mut self, address: i64, value: i64) {
+        self.computer.poke(address, value);
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.computer.relative_base
+    }
+
+    /// Takes the next input value, if there is one.
+    pub fn input(&mut self) -> Option<i64> {
+        self.computer.input_buffer.pop_front()
+    }
+
+    /// Produces an output value.  The computer blocks on output after the
+    /// instruction completes, as it does for opcode 4.
+    pub fn output(&mut self, value: i64) {
+        assert!(
+            self.computer.output.is_none(),
+            "an instruction may output only one value"
+        );
+        self.computer.output = Some(value);
+    }
+}
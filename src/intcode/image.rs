@@ -0,0 +1,320 @@
+// Intcode program images and the file formats they are stored in.
+//
+// Three formats are supported:
+//
+// - Comma: the puzzle input format, `1,0,0,3,99`.  Whitespace, including
+//   newlines, may appear around any number, and a trailing comma is allowed.
+// - Commented: for hand-written programs.  Numbers are separated by commas
+//   and/or whitespace, and `#` starts a comment that runs to the end of the
+//   line.
+// - Binary: the magic bytes `ICB\x01`, a little-endian `u32` word count, then
+//   each word as a zigzag-encoded little-endian base-128 varint.  Small
+//   numbers, which make up most of any program, take a single byte.
+
+use std::fmt;
+use std::io;
+
+const BINARY_MAGIC: &[u8; 4] = b"ICB\x01";
+
+/// The initial memory of an Intcode program.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProgramImage {
+    words: Vec<i64>,
+}
+
+/// A format error, with the byte offset into the input where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(offset: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            offset,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_word(token: &str, offset: usize) -> Result<i64, ParseError> {
+    token
+        .parse()
+        .map_err(|e| ParseError::new(offset, format!("invalid number \"{}\": {}", token, e)))
+}
+
+impl ProgramImage {
+    pub fn new(words: Vec<i64>) -> ProgramImage {
+        ProgramImage { words }
+    }
+
+    pub fn words(&self) -> &[i64] {
+        &self.words
+    }
+
+    pub fn into_words(self) -> Vec<i64> {
+        self.words
+    }
+
+    /// Loads an image in any supported format: binary if `bytes` starts with
+    /// the binary magic, otherwise the commented text format (which also
+    /// accepts everything the comma format does).
+    pub fn load(bytes: &[u8]) -> Result<ProgramImage, ParseError> {
+        if bytes.starts_with(BINARY_MAGIC) {
+            return Self::parse_binary(bytes);
+        }
+        let text = std::str::from_utf8(bytes)
+            .map_err(|e| ParseError::new(e.valid_up_to(), "text is not valid UTF-8"))?;
+        Self::parse_commented(text)
+    }
+
+    /// Parses the comma-separated puzzle input format.
+    pub fn parse_comma(text: &str) -> Result<ProgramImage, ParseError> {
+        let mut words = Vec::new();
+        let mut field_start = 0;
+        let fields: Vec<&str> = text.split(',').collect();
+        for (i, field) in fields.iter().enumerate() {
+            let token = field.trim();
+            let offset = field_start + (field.len() - field.trim_start().len());
+            field_start += field.len() + 1;
+            if token.is_empty() {
+                if i > 0 && i == fields.len() - 1 {
+                    break;
+                }
+                return Err(ParseError::new(offset, "expected a number"));
+            }
+            words.push(parse_word(token, offset)?);
+        }
+        Ok(ProgramImage { words })
+    }
+
+    /// Parses the commented text format.
+    pub fn parse_commented(text: &str) -> Result<ProgramImage, ParseError> {
+        let bytes = text.as_bytes();
+        let mut words = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            match bytes[pos] {
+                b',' => pos += 1,
+                b if b.is_ascii_whitespace() => pos += 1,
+                b'#' => {
+                    while pos < bytes.len() && bytes[pos] != b'\n' {
+                        pos += 1;
+                    }
+                }
+                _ => {
+                    let start = pos;
+                    while pos < bytes.len()
+                        && !matches!(bytes[pos], b',' | b'#')
+                        && !bytes[pos].is_ascii_whitespace()
+                    {
+                        pos += 1;
+                    }
+                    words.push(parse_word(&text[start..pos], start)?);
+                }
+            }
+        }
+        Ok(ProgramImage { words })
+    }
+
+    /// Parses the binary format.
+    pub fn parse_binary(bytes: &[u8]) -> Result<ProgramImage, ParseError> {
+        if !bytes.starts_with(BINARY_MAGIC) {
+            return Err(ParseError::new(0, "missing binary image magic"));
+        }
+        let mut pos = BINARY_MAGIC.len();
+        let count = bytes
+            .get(pos..pos + 4)
+            .ok_or_else(|| ParseError::new(pos, "truncated word count"))?;
+        let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+        pos += 4;
+
+        let mut words = Vec::with_capacity(count.min(bytes.len()));
+        for _ in 0..count {
+            let start = pos;
+            let mut value: u64 = 0;
+            let mut shift = 0;
+            loop {
+                let byte = *bytes
+                    .get(pos)
+                    .ok_or_else(|| ParseError::new(pos, "truncated word"))?;
+                pos += 1;
+                if shift == 63 && byte > 1 {
+                    return Err(ParseError::new(start, "word does not fit in 64 bits"));
+                }
+                value |= u64::from(byte & 0x7f) << shift;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+                shift += 7;
+            }
+            words.push(zigzag_decode(value));
+        }
+        if pos != bytes.len() {
+            return Err(ParseError::new(pos, "trailing bytes after the last word"));
+        }
+        Ok(ProgramImage { words })
+    }
+
+    /// Writes the comma-separated puzzle input format, with a final newline.
+    pub fn write_comma<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        for (i, word) in self.words.iter().enumerate() {
+            if i > 0 {
+                write!(w, ",")?;
+            }
+            write!(w, "{}", word)?;
+        }
+        writeln!(w)
+    }
+
+    /// Writes the commented text format, `per_line` words to a line, each
+    /// line commented with the address of its first word.
+    pub fn write_commented<W: io::Write>(&self, w: &mut W, per_line: usize) -> io::Result<()> {
+        assert!(per_line > 0);
+        for (line, chunk) in self.words.chunks(per_line).enumerate() {
+            let text: Vec<String> = chunk.iter().map(|word| word.to_string()).collect();
+            writeln!(w, "{:<48} # @{}", text.join(", "), line * per_line)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the binary format.
+    pub fn write_binary<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let count = u32::try_from(self.words.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "image too large"))?;
+        w.write_all(BINARY_MAGIC)?;
+        w.write_all(&count.to_le_bytes())?;
+        for &word in &self.words {
+            let mut value = zigzag_encode(word);
+            loop {
+                let byte = (value & 0x7f) as u8;
+                value >>= 7;
+                if value == 0 {
+                    w.write_all(&[byte])?;
+                    break;
+                }
+                w.write_all(&[byte | 0x80])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<i64>> for ProgramImage {
+    fn from(words: Vec<i64>) -> Self {
+        ProgramImage::new(words)
+    }
+}
+
+fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn zigzag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written(write: impl Fn(&mut Vec<u8>) -> io::Result<()>) -> Vec<u8> {
+        let mut out = Vec::new();
+        write(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_parse_comma() {
+        let image = ProgramImage::parse_comma("1, 0,0,3,\n99\n").unwrap();
+        assert_eq!(image.words(), &[1, 0, 0, 3, 99]);
+        let image = ProgramImage::parse_comma("104,-7,99,\n").unwrap();
+        assert_eq!(image.words(), &[104, -7, 99]);
+    }
+
+    #[test]
+    fn test_parse_comma_errors() {
+        assert_eq!(
+            ProgramImage::parse_comma("1,2,x3,4"),
+            Err(ParseError::new(
+                4,
+                "invalid number \"x3\": invalid digit found in string"
+            ))
+        );
+        assert_eq!(ProgramImage::parse_comma("1,,2").unwrap_err().offset, 2);
+        assert_eq!(ProgramImage::parse_comma("").unwrap_err().offset, 0);
+    }
+
+    #[test]
+    fn test_parse_commented() {
+        let text = "\
+# Echo one input.
+3, 0    # in -> mem[0]
+4 0     # out mem[0]
+99
+";
+        let image = ProgramImage::parse_commented(text).unwrap();
+        assert_eq!(image.words(), &[3, 0, 4, 0, 99]);
+        let error = ProgramImage::parse_commented("1 2\n3 four # x\n").unwrap_err();
+        assert_eq!(error.offset, 6);
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let image = ProgramImage::new(vec![0, 1, -1, 63, -64, 64, 99, i64::MAX, i64::MIN]);
+        let bytes = written(|w| image.write_binary(w));
+        assert_eq!(&bytes[..8], b"ICB\x01\x09\x00\x00\x00");
+        assert_eq!(bytes[8..15], [0, 2, 1, 126, 127, 128, 1]);
+        assert_eq!(ProgramImage::parse_binary(&bytes).unwrap(), image);
+        assert_eq!(ProgramImage::load(&bytes).unwrap(), image);
+    }
+
+    #[test]
+    fn test_binary_errors() {
+        assert_eq!(ProgramImage::parse_binary(b"1,2").unwrap_err().offset, 0);
+        assert_eq!(
+            ProgramImage::parse_binary(b"ICB\x01\x02")
+                .unwrap_err()
+                .offset,
+            4
+        );
+        let truncated = b"ICB\x01\x02\x00\x00\x00\x05\x80";
+        assert_eq!(
+            ProgramImage::parse_binary(truncated).unwrap_err().offset,
+            10
+        );
+        let trailing = b"ICB\x01\x01\x00\x00\x00\x05\x06";
+        assert_eq!(ProgramImage::parse_binary(trailing).unwrap_err().offset, 9);
+        let mut too_long = b"ICB\x01\x01\x00\x00\x00".to_vec();
+        too_long.extend([0xff; 9]);
+        too_long.push(0x02);
+        assert_eq!(ProgramImage::parse_binary(&too_long).unwrap_err().offset, 8);
+    }
+
+    #[test]
+    fn test_text_round_trips() {
+        let image = ProgramImage::parse_comma(include_str!("../inputs/09.txt")).unwrap();
+        let comma = written(|w| image.write_comma(w));
+        assert_eq!(
+            ProgramImage::parse_comma(std::str::from_utf8(&comma).unwrap()).unwrap(),
+            image
+        );
+        let commented = written(|w| image.write_commented(w, 8));
+        let commented = String::from_utf8(commented).unwrap();
+        assert!(
+            commented.starts_with("1102, 34463338, 34463338, 63, 1007, 63, 34463338, 63 # @0\n")
+        );
+        assert_eq!(ProgramImage::load(commented.as_bytes()).unwrap(), image);
+        let binary = written(|w| image.write_binary(w));
+        assert!(binary.len() < comma.len() / 2);
+    }
+}
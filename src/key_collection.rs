@@ -0,0 +1,562 @@
+// Collecting every key in a maze with one or more agents, as in day 18.
+//
+// The maze is a `Graph` whose nodes may hold keys and whose edges may need
+// keys to pass.  The solver searches over states made of the keys held and
+// where each agent stands, where a move sends one agent to a node with keys
+// it lacks.
+//
+// The moves out of a state follow routes found once per node.  A route
+// needs the keys of the doors it passes and of the nodes with keys it passes
+// through, since an agent reaching one of those with a key missing would
+// stop there first.  On a maze with loops a longer detour may need fewer
+// keys, so the search from a node keeps every route that no other route to
+// the same node beats on both length and keys needed.  A state then takes,
+// for each node with keys it lacks, the shortest route whose keys it holds,
+// which is exact without searching the maze again for every set of keys.
+//
+// Neither search uses the graph's own `best_first`.  The route search keeps
+// several routes per node rather than one, and in the state search states
+// are not nodes of the graph, so there is no `EdgeId` for a move and the
+// state, holding a position per agent, is not `Copy`; it uses
+// `pathfinding`'s A* instead.
+
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+use pathfinding::prelude::astar;
+
+use crate::graph::{Graph, NodeId};
+
+/// A set of keys, numbered from 0 to `KeySet::CAPACITY - 1`.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeySet {
+    mask: u128,
+}
+
+impl KeySet {
+    pub const CAPACITY: usize = 128;
+
+    pub fn new() -> KeySet {
+        KeySet::default()
+    }
+
+    /// The set holding just `key`.  Panics if `key` is out of range.
+    pub fn single(key: usize) -> KeySet {
+        assert!(key < Self::CAPACITY, "key {} out of range", key);
+        KeySet { mask: 1 << key }
+    }
+
+    pub fn len(&self) -> usize {
+        self.mask.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
+    pub fn contains(&self, key: usize) -> bool {
+        key < Self::CAPACITY && self.mask & (1 << key) != 0
+    }
+
+    pub fn contains_all(&self, other: KeySet) -> bool {
+        self.intersection(other) == other
+    }
+
+    pub fn insert(&mut self, key: usize) {
+        *self = self.union(KeySet::single(key));
+    }
+
+    pub fn union(&self, other: KeySet) -> KeySet {
+        KeySet {
+            mask: self.mask | other.mask,
+        }
+    }
+
+    pub fn intersection(&self, other: KeySet) -> KeySet {
+        KeySet {
+            mask: self.mask & other.mask,
+        }
+    }
+
+    pub fn difference(&self, other: KeySet) -> KeySet {
+        KeySet {
+            mask: self.mask & !other.mask,
+        }
+    }
+
+    /// The keys in the set, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..Self::CAPACITY).filter(|&key| self.contains(key))
+    }
+}
+
+impl FromIterator<usize> for KeySet {
+    fn from_iter<I: IntoIterator<Item = usize>>(keys: I) -> KeySet {
+        keys.into_iter()
+            .fold(KeySet::new(), |set, key| set.union(KeySet::single(key)))
+    }
+}
+
+impl fmt::Debug for KeySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Node data in a key collection maze.
+pub trait KeyNode {
+    /// The keys an agent picks up on reaching this node.
+    fn keys(&self) -> KeySet;
+}
+
+/// Edge data in a key collection maze.
+pub trait KeyEdge {
+    fn distance(&self) -> u32;
+
+    /// The keys an agent must hold to pass along this edge.
+    fn required_keys(&self) -> KeySet;
+}
+
+/// One agent's trip from one stop to the next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Move {
+    /// The index of the agent in the list of start nodes.
+    pub agent: usize,
+    pub distance: u32,
+    /// The keys picked up at the end of the move.
+    pub keys: KeySet,
+    /// Every node passed through, from the agent's stop before the move to
+    /// its stop after.
+    pub path: Vec<NodeId>,
+}
+
+impl Move {
+    pub fn from(&self) -> NodeId {
+        self.path[0]
+    }
+
+    pub fn to(&self) -> NodeId {
+        *self.path.last().unwrap()
+    }
+}
+
+/// A shortest way for the agents to collect every key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// The total distance walked by all the agents.
+    pub distance: u32,
+    pub moves: Vec<Move>,
+    /// Every node each agent passes through, in order, starting at its start
+    /// node.
+    pub routes: Vec<Vec<NodeId>>,
+}
+
+impl Solution {
+    /// The nodes where keys were picked up, in the order they were reached.
+    pub fn collection_order(&self) -> Vec<NodeId> {
+        self.moves.iter().map(Move::to).collect()
+    }
+}
+
+// A way from one stop to another, and the keys an agent needs to take it.
+#[derive(Debug, Clone)]
+struct Route {
+    distance: u32,
+    needs: KeySet,
+    path: Vec<NodeId>,
+}
+
+impl Route {
+    fn to(&self) -> NodeId {
+        *self.path.last().unwrap()
+    }
+}
+
+// The routes out of a node to nodes with keys, shortest first.
+type Routes = Rc<Vec<Route>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct State {
+    keys: KeySet,
+    positions: Vec<NodeId>,
+}
+
+/// Finds the shortest way to collect every key in a maze, with one agent
+/// starting at each of a list of nodes.
+pub struct Solver<'graph, N, E> {
+    graph: &'graph Graph<N, E>,
+    starts: Vec<NodeId>,
+    all_keys: KeySet,
+    // The routes out of each node, found as needed.
+    routes: RefCell<HashMap<NodeId, Routes>>,
+    // For each key node, the length of the shortest path to it from any
+    // start or other key node, ignoring doors, when every key is at exactly
+    // one node.  Used for the heuristic.
+    closest_approach: Option<HashMap<NodeId, u32>>,
+    use_heuristic: bool,
+}
+
+impl<'graph, N: KeyNode, E: KeyEdge> Solver<'graph, N, E> {
+    /// Creates a solver for `graph` with an agent at each of `starts`.  The
+    /// graph may have any shape, loops included: every route between stops
+    /// that needs fewer keys than the shorter ones is kept.
+    pub fn new(graph: &'graph Graph<N, E>, starts: impl IntoIterator<Item = NodeId>) -> Self {
+        let starts: Vec<NodeId> = starts.into_iter().collect();
+        let key_nodes: Vec<NodeId> = graph
+            .nodes()
+            .filter(|(_, node)| !node.keys().is_empty())
+            .map(|(id, _)| id)
+            .collect();
+        let all_keys = key_nodes.iter().fold(KeySet::new(), |keys, &id| {
+            keys.union(graph.get_node(id).unwrap().keys())
+        });
+
+        let keys_unique = key_nodes
+            .iter()
+            .map(|&id| graph.get_node(id).unwrap().keys().len())
+            .sum::<usize>()
+            == all_keys.len();
+        let closest_approach = keys_unique.then(|| {
+            let mut closest = HashMap::new();
+            for &from in starts.iter().chain(&key_nodes) {
+                let paths = graph.dijkstra_all(from, |edge| Some(edge.distance()));
+                for &to in key_nodes.iter().filter(|&&to| to != from) {
+                    if let Some(distance) = paths.cost(to) {
+                        let closest = closest.entry(to).or_insert(distance);
+                        *closest = distance.min(*closest);
+                    }
+                }
+            }
+            closest
+        });
+
+        Solver {
+            graph,
+            starts,
+            all_keys,
+            routes: RefCell::new(HashMap::new()),
+            closest_approach,
+            use_heuristic: false,
+        }
+    }
+
+    /// Guides the search with a lower bound on the distance still to walk:
+    /// for each key not yet held, the shortest path to it from anywhere.
+    /// The bound only holds when every key is at exactly one node, so
+    /// otherwise it is not used.
+    pub fn with_heuristic(mut self, use_heuristic: bool) -> Self {
+        self.use_heuristic = use_heuristic;
+        self
+    }
+
+    /// All the keys in the maze.
+    pub fn all_keys(&self) -> KeySet {
+        self.all_keys
+    }
+
+    /// Finds a shortest way to collect every key, or `None` if some key
+    /// cannot be reached.
+    pub fn solve(&self) -> Option<Solution> {
+        let start_keys = self.starts.iter().fold(KeySet::new(), |keys, &id| {
+            keys.union(self.graph.get_node(id).unwrap().keys())
+        });
+        let start = State {
+            keys: start_keys,
+            positions: self.starts.clone(),
+        };
+        let (states, distance) = astar(
+            &start,
+            |state| self.successors(state),
+            |state| self.heuristic(state),
+            |state| state.keys.contains_all(self.all_keys),
+        )?;
+
+        let mut routes: Vec<Vec<NodeId>> = self.starts.iter().map(|&id| vec![id]).collect();
+        let moves: Vec<Move> = states
+            .windows(2)
+            .map(|pair| {
+                let (before, after) = (&pair[0], &pair[1]);
+                let agent = (0..self.starts.len())
+                    .find(|&agent| before.positions[agent] != after.positions[agent])
+                    .unwrap();
+                let from_routes = self.routes(before.positions[agent]);
+                let route = self
+                    .usable(&from_routes, before.keys)
+                    .into_iter()
+                    .find(|route| route.to() == after.positions[agent])
+                    .unwrap();
+                routes[agent].extend(&route.path[1..]);
+                Move {
+                    agent,
+                    distance: route.distance,
+                    keys: after.keys.difference(before.keys),
+                    path: route.path.clone(),
+                }
+            })
+            .collect();
+        Some(Solution {
+            distance,
+            moves,
+            routes,
+        })
+    }
+
+    // The routes from `from` to every node with keys.  The search visits a
+    // node once for each route to it that no earlier, so no longer, route
+    // beats on keys needed.
+    fn routes(&self, from: NodeId) -> Routes {
+        if let Some(routes) = self.routes.borrow().get(&from) {
+            return Rc::clone(routes);
+        }
+        let keys_at = |id: NodeId| self.graph.get_node(id).unwrap().keys();
+
+        // Each route found so far, as its end, length, keys needed and the
+        // route it extends.
+        let mut found: Vec<(NodeId, u32, KeySet, Option<usize>)> =
+            vec![(from, 0, KeySet::new(), None)];
+        let mut queue = BinaryHeap::from([Reverse((0, 0))]);
+        // The keys needed by each route kept to each node.
+        let mut kept: HashMap<NodeId, Vec<KeySet>> = HashMap::new();
+        let mut routes = Vec::new();
+        while let Some(Reverse((distance, index))) = queue.pop() {
+            let (node, _, needs, _) = found[index];
+            let kept_needs = kept.entry(node).or_default();
+            if kept_needs.iter().any(|&other| needs.contains_all(other)) {
+                continue;
+            }
+            kept_needs.push(needs);
+            let mut passing = needs;
+            if node != from && !keys_at(node).is_empty() {
+                let mut path = Vec::new();
+                let mut step = Some(index);
+                while let Some(i) = step {
+                    path.push(found[i].0);
+                    step = found[i].3;
+                }
+                path.reverse();
+                routes.push(Route {
+                    distance,
+                    needs,
+                    path,
+                });
+                passing = passing.union(keys_at(node));
+            }
+            for (_, edge, target) in self.graph.outgoing_edges(node) {
+                let needs = passing.union(edge.required_keys());
+                let beaten = kept
+                    .get(&target)
+                    .is_some_and(|kept| kept.iter().any(|&other| needs.contains_all(other)));
+                if target != from && !beaten {
+                    let distance = distance + edge.distance();
+                    found.push((target, distance, needs, Some(index)));
+                    queue.push(Reverse((distance, found.len() - 1)));
+                }
+            }
+        }
+
+        let routes = Rc::new(routes);
+        self.routes.borrow_mut().insert(from, Rc::clone(&routes));
+        routes
+    }
+
+    // The shortest route in `routes` to each node with keys missing from
+    // `keys`, among those an agent holding `keys` can take.
+    fn usable<'r>(&self, routes: &'r [Route], keys: KeySet) -> Vec<&'r Route> {
+        let mut usable: Vec<&Route> = Vec::new();
+        for route in routes {
+            let missing = !keys.contains_all(self.graph.get_node(route.to()).unwrap().keys());
+            if missing
+                && keys.contains_all(route.needs)
+                && usable.iter().all(|other| other.to() != route.to())
+            {
+                usable.push(route);
+            }
+        }
+        usable
+    }
+
+    fn successors(&self, state: &State) -> Vec<(State, u32)> {
+        let mut successors = Vec::new();
+        for (agent, &position) in state.positions.iter().enumerate() {
+            let routes = self.routes(position);
+            for route in self.usable(&routes, state.keys) {
+                let to = route.to();
+                let mut next = state.clone();
+                next.keys = state.keys.union(self.graph.get_node(to).unwrap().keys());
+                next.positions[agent] = to;
+                successors.push((next, route.distance));
+            }
+        }
+        successors
+    }
+
+    fn heuristic(&self, state: &State) -> u32 {
+        match (&self.closest_approach, self.use_heuristic) {
+            (Some(closest), true) => closest
+                .iter()
+                .filter(|(to, _)| {
+                    !state
+                        .keys
+                        .contains_all(self.graph.get_node(**to).unwrap().keys())
+                })
+                .map(|(_, distance)| distance)
+                .sum(),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy)]
+    struct Room(KeySet);
+
+    impl KeyNode for Room {
+        fn keys(&self) -> KeySet {
+            self.0
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    struct Door {
+        distance: u32,
+        needs: KeySet,
+    }
+
+    impl KeyEdge for Door {
+        fn distance(&self) -> u32 {
+            self.distance
+        }
+
+        fn required_keys(&self) -> KeySet {
+            self.needs
+        }
+    }
+
+    fn keys(keys: &[usize]) -> KeySet {
+        keys.iter().copied().collect()
+    }
+
+    fn connect(
+        graph: &mut Graph<Room, Door>,
+        a: NodeId,
+        b: NodeId,
+        distance: u32,
+        needs: &[usize],
+    ) {
+        let door = Door {
+            distance,
+            needs: keys(needs),
+        };
+        graph.add_edge(a, b, door);
+        graph.add_edge(b, a, door);
+    }
+
+    #[test]
+    fn test_key_set() {
+        let mut set = KeySet::new();
+        assert!(set.is_empty());
+        set.insert(3);
+        set.insert(100);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(100) && !set.contains(4) && !set.contains(500));
+        assert_eq!(format!("{:?}", set), "{3, 100}");
+        assert_eq!(format!("{:?}", KeySet::default()), "{}");
+        assert!(set.contains_all(keys(&[3])));
+        assert!(!set.contains_all(keys(&[3, 4])));
+        assert_eq!(set.difference(keys(&[3])), keys(&[100]));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 100]);
+    }
+
+    // The first example from day 18:
+    //
+    //     #########
+    //     #b.A.@.a#
+    //     #########
+    #[test]
+    fn test_single_agent() {
+        let mut graph = Graph::new();
+        let b = graph.add_node(Room(keys(&[1])));
+        let start = graph.add_node(Room(KeySet::new()));
+        let a = graph.add_node(Room(keys(&[0])));
+        connect(&mut graph, b, start, 4, &[0]);
+        connect(&mut graph, start, a, 2, &[]);
+
+        for heuristic in [false, true] {
+            let solver = Solver::new(&graph, [start]).with_heuristic(heuristic);
+            assert_eq!(solver.all_keys(), keys(&[0, 1]));
+            let solution = solver.solve().unwrap();
+            assert_eq!(solution.distance, 8);
+            assert_eq!(solution.collection_order(), vec![a, b]);
+            assert_eq!(solution.moves[1].keys, keys(&[1]));
+            assert_eq!((solution.moves[1].from(), solution.moves[1].to()), (a, b));
+            assert_eq!(solution.routes, vec![vec![start, a, start, b]]);
+        }
+    }
+
+    #[test]
+    fn test_agents_and_many_keys() {
+        // Two agents at the hub of a star, each arm holding a key numbered
+        // past 100; every arm after the first two needs the key on the arm
+        // two before it.
+        let mut graph = Graph::new();
+        let hub = graph.add_node(Room(KeySet::new()));
+        let arms: Vec<NodeId> = (0..12)
+            .map(|key| graph.add_node(Room(keys(&[key + 100]))))
+            .collect();
+        for (i, &arm) in arms.iter().enumerate() {
+            let needs = if i < 2 { vec![] } else { vec![i + 100 - 2] };
+            connect(&mut graph, hub, arm, 1, &needs);
+        }
+        let solution = Solver::new(&graph, [hub, hub])
+            .with_heuristic(true)
+            .solve()
+            .unwrap();
+        // Each agent walks out and back along every arm but its last.
+        assert_eq!(solution.distance, 2 * 12 - 2);
+        assert_eq!(solution.moves.len(), 12);
+        assert_eq!(solution.routes.len(), 2);
+        // Every arm comes after the arm holding its key.
+        let order = solution.collection_order();
+        let position = |arm| order.iter().position(|&id| id == arm).unwrap();
+        assert!((2..12).all(|i| position(arms[i - 2]) < position(arms[i])));
+    }
+
+    #[test]
+    fn test_detour_around_door() {
+        // Key 0 lies past a door needing key 1 on the short way round and
+        // along a long open corridor on the other.  Key 1 is behind a door
+        // needing key 0, so the long way must be taken there and back.
+        let mut graph = Graph::new();
+        let start = graph.add_node(Room(KeySet::new()));
+        let a = graph.add_node(Room(keys(&[0])));
+        let b = graph.add_node(Room(keys(&[1])));
+        connect(&mut graph, start, a, 2, &[1]);
+        connect(&mut graph, start, a, 10, &[]);
+        connect(&mut graph, start, b, 3, &[0]);
+        for heuristic in [false, true] {
+            let solution = Solver::new(&graph, [start])
+                .with_heuristic(heuristic)
+                .solve()
+                .unwrap();
+            assert_eq!(solution.distance, 23);
+            assert_eq!(solution.collection_order(), vec![a, b]);
+        }
+    }
+
+    #[test]
+    fn test_unreachable_key() {
+        let mut graph = Graph::new();
+        let start = graph.add_node(Room(KeySet::new()));
+        let a = graph.add_node(Room(keys(&[0])));
+        let b = graph.add_node(Room(keys(&[1])));
+        connect(&mut graph, start, a, 1, &[1]);
+        connect(&mut graph, start, b, 1, &[0]);
+        assert!(Solver::new(&graph, [start]).solve().is_none());
+    }
+}
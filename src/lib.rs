@@ -1,3 +1,8 @@
+pub mod explore;
+pub mod goal_seek;
 pub mod graph;
 pub mod intcode;
+pub mod key_collection;
+pub mod math;
 pub mod point;
+pub mod tree;
@@ -0,0 +1,127 @@
+// Number theory for puzzles that wrap around a modulus, such as day 22's
+// card shuffles, or wait for several periods to line up, such as day 12's
+// moons.
+//
+// The functions are generic over the `num` integer traits.  Apart from
+// `mul_mod`, they compute products in the integer type itself, so pick one
+// wide enough for the square of the modulus: `i128` for moduli beyond 2^31
+// in `i64`, for example.
+
+use num::{Integer, Signed};
+
+/// The greatest common divisor of `a` and `b`, which is never negative.
+pub fn gcd<T: Integer>(a: T, b: T) -> T {
+    a.gcd(&b)
+}
+
+/// Returns `(g, x, y)` where `g` is the greatest common divisor of `a` and
+/// `b`, never negative, and `a * x + b * y == g`.
+pub fn extended_gcd<T: Integer + Signed + Copy>(a: T, b: T) -> (T, T, T) {
+    let (mut r0, mut r1) = (a, b);
+    let (mut x0, mut x1) = (T::one(), T::zero());
+    let (mut y0, mut y1) = (T::zero(), T::one());
+    while !r1.is_zero() {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (x0, x1) = (x1, x0 - q * x1);
+        (y0, y1) = (y1, y0 - q * y1);
+    }
+    if r0.is_negative() {
+        (-r0, -x0, -y0)
+    } else {
+        (r0, x0, y0)
+    }
+}
+
+/// The least common multiple of `values`, or one if there are none.
+pub fn lcm<T: Integer + Copy>(values: &[T]) -> T {
+    values.iter().fold(T::one(), |acc, value| acc.lcm(value))
+}
+
+/// `a * b` modulo `m`, without overflowing however large the operands.
+pub fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+/// `base` raised to `exp` modulo `m`, in the range `0..m`.
+pub fn mod_pow<T: Integer + Copy>(base: T, mut exp: u64, m: T) -> T {
+    let mut result = T::one().mod_floor(&m);
+    let mut square = base.mod_floor(&m);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = (result * square).mod_floor(&m);
+        }
+        square = (square * square).mod_floor(&m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// The `x` in `0..m` with `a * x` congruent to one modulo `m`, if `a` and
+/// `m` are coprime.
+pub fn mod_inv<T: Integer + Signed + Copy>(a: T, m: T) -> Option<T> {
+    let (g, x, _) = extended_gcd(a.mod_floor(&m), m);
+    g.is_one().then(|| x.mod_floor(&m))
+}
+
+/// Solves a system of congruences `x ≡ residue (mod modulus)` with the
+/// Chinese remainder theorem, returning `(x, lcm of the moduli)` with `x` in
+/// range of the latter.  The moduli need not be coprime; returns `None` if
+/// the congruences contradict each other.
+pub fn crt<T: Integer + Signed + Copy>(congruences: &[(T, T)]) -> Option<(T, T)> {
+    congruences
+        .iter()
+        .try_fold((T::zero(), T::one()), |(r1, m1), &(r2, m2)| {
+            let (g, p, _) = extended_gcd(m1, m2);
+            let (q, rem) = (r2 - r1).div_rem(&g);
+            if !rem.is_zero() {
+                return None;
+            }
+            // x = r1 + m1 * k, where m1 * k ≡ r2 - r1 (mod m2).
+            let step = m2 / g;
+            let k = (q * p).mod_floor(&step);
+            let modulus = m1 * step;
+            Some(((r1 + m1 * k).mod_floor(&modulus), modulus))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(-12, 18), 6);
+        assert_eq!(gcd(0, 5), 5);
+        assert_eq!(lcm(&[4, 6, 10]), 60);
+        assert_eq!(lcm::<i64>(&[]), 1);
+        assert_eq!(lcm(&[18_u64, 28, 44]), 2772);
+
+        for (a, b) in [(240, 46), (-240, 46), (46, -240), (0, 7), (7, 0)] {
+            let (g, x, y) = extended_gcd(a, b);
+            assert_eq!(g, gcd(a, b));
+            assert_eq!(a * x + b * y, g);
+        }
+    }
+
+    #[test]
+    fn test_modular() {
+        assert_eq!(mul_mod(u64::MAX, u64::MAX, 1_000_000_007), 114_944_269);
+        assert_eq!(mod_pow(2, 10, 1000), 24);
+        assert_eq!(mod_pow(-2, 3, 7), 6);
+        assert_eq!(mod_pow(5, 0, 1), 0);
+        assert_eq!(mod_pow(3_i128, 119_315_717_514_046, 119_315_717_514_047), 1);
+        assert_eq!(mod_inv(3, 10), Some(7));
+        assert_eq!(mod_inv(-3, 10), Some(3));
+        assert_eq!(mod_inv(4, 10), None);
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(-1, 4), (1, 6)]), Some((7, 12)));
+        assert_eq!(crt(&[(0, 4), (1, 6)]), None);
+        assert_eq!(crt::<i64>(&[]), Some((0, 1)));
+    }
+}
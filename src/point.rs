@@ -1,9 +1,18 @@
-use std::cmp::Ord;
+use std::borrow::Borrow;
+use std::cmp::{Ord, Ordering};
+use std::collections::HashMap;
 use std::fmt;
+use std::io;
 use std::ops::Add;
+use std::ops::Div;
+use std::ops::Index;
+use std::ops::IndexMut;
+use std::ops::Mul;
+use std::ops::Neg;
 use std::ops::Sub;
+use std::str::FromStr;
 
-use num::One;
+use num::{CheckedAdd, CheckedSub, Integer, One, Signed, ToPrimitive, Zero};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum CardinalDirection {
@@ -22,6 +31,116 @@ impl CardinalDirection {
             CardinalDirection::West => CardinalDirection::East,
         }
     }
+
+    /// The direction 90 degrees counterclockwise from this one.
+    pub fn turn_left(&self) -> Self {
+        match *self {
+            CardinalDirection::North => CardinalDirection::West,
+            CardinalDirection::West => CardinalDirection::South,
+            CardinalDirection::South => CardinalDirection::East,
+            CardinalDirection::East => CardinalDirection::North,
+        }
+    }
+
+    /// The direction 90 degrees clockwise from this one.
+    pub fn turn_right(&self) -> Self {
+        match *self {
+            CardinalDirection::North => CardinalDirection::East,
+            CardinalDirection::East => CardinalDirection::South,
+            CardinalDirection::South => CardinalDirection::West,
+            CardinalDirection::West => CardinalDirection::North,
+        }
+    }
+
+    pub fn rotate(&self, turn: Turn) -> Self {
+        match turn {
+            Turn::Left => self.turn_left(),
+            Turn::Right => self.turn_right(),
+            Turn::Around => self.negate(),
+        }
+    }
+
+    /// The unit step in this direction.  With [`YAxis::Down`], the screen
+    /// convention used by the puzzle maps, north is `(0, -1)`; with
+    /// [`YAxis::Up`] it is `(0, 1)`.
+    pub fn delta<T: Zero + One + Neg<Output = T>>(&self, y_axis: YAxis) -> Point2D<T> {
+        let (x, y) = match *self {
+            CardinalDirection::North => (T::zero(), T::one()),
+            CardinalDirection::South => (T::zero(), -T::one()),
+            CardinalDirection::East => (T::one(), T::zero()),
+            CardinalDirection::West => (-T::one(), T::zero()),
+        };
+        match y_axis {
+            YAxis::Up => Point2D { x, y },
+            YAxis::Down => Point2D { x, y: -y },
+        }
+    }
+
+    /// The arrow that points this way on a map drawn with y increasing
+    /// downward: one of `^v><`.
+    pub fn arrow(&self) -> char {
+        match *self {
+            CardinalDirection::North => '^',
+            CardinalDirection::South => 'v',
+            CardinalDirection::East => '>',
+            CardinalDirection::West => '<',
+        }
+    }
+}
+
+/// Parses a direction from `N/S/E/W`, `U/D/L/R` (either case) or an arrow
+/// `^v<>`.
+impl TryFrom<char> for CardinalDirection {
+    type Error = anyhow::Error;
+
+    fn try_from(ch: char) -> anyhow::Result<Self> {
+        match ch {
+            'N' | 'n' | 'U' | 'u' | '^' => Ok(CardinalDirection::North),
+            'S' | 's' | 'D' | 'd' | 'v' => Ok(CardinalDirection::South),
+            'E' | 'e' | 'R' | 'r' | '>' => Ok(CardinalDirection::East),
+            'W' | 'w' | 'L' | 'l' | '<' => Ok(CardinalDirection::West),
+            _ => anyhow::bail!("invalid direction {:?}", ch),
+        }
+    }
+}
+
+/// Parses a direction from a single character accepted by
+/// `TryFrom<char>`, or from one of the words `north`, `south`, `east` and
+/// `west` (any case).
+impl FromStr for CardinalDirection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut chars = s.chars();
+        if let (Some(ch), None) = (chars.next(), chars.next()) {
+            return CardinalDirection::try_from(ch);
+        }
+        match s.to_ascii_lowercase().as_str() {
+            "north" => Ok(CardinalDirection::North),
+            "south" => Ok(CardinalDirection::South),
+            "east" => Ok(CardinalDirection::East),
+            "west" => Ok(CardinalDirection::West),
+            _ => anyhow::bail!("invalid direction {:?}", s),
+        }
+    }
+}
+
+/// A change of heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Turn {
+    Left,
+    Right,
+    Around,
+}
+
+/// Which way y increases when a [`CardinalDirection`] becomes a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum YAxis {
+    /// y increases southward, as in the puzzle maps.
+    #[default]
+    Down,
+    /// y increases northward.
+    Up,
 }
 
 pub const CARDINAL_DIRECTIONS: [CardinalDirection; 4] = [
@@ -87,6 +206,173 @@ impl<T: Add<Output = T> + Sub<Output = T> + Ord + Copy + TryFrom<usize> + TryInt
     }
 }
 
+impl<T: Integer + Signed + Copy> Point2D<T> {
+    /// The cross product `self.x * other.y - self.y * other.x`.
+    pub fn cross(&self, other: Self) -> T {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn dot(&self, other: Self) -> T {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// The shortest lattice vector pointing the same way as this one: each
+    /// component divided by their greatest common divisor.  The zero vector
+    /// stays zero.
+    pub fn primitive_direction(&self) -> Self {
+        let divisor = self.x.gcd(&self.y);
+        if divisor.is_zero() {
+            *self
+        } else {
+            Point2D {
+                x: self.x / divisor,
+                y: self.y / divisor,
+            }
+        }
+    }
+
+    /// The lattice points on the segment from this point to `end`,
+    /// including both ends, in order.
+    pub fn lattice_points(&self, end: Self) -> impl Iterator<Item = Self> + use<T> {
+        let step = (end - *self).primitive_direction();
+        std::iter::successors(Some(*self), move |&p| (p != end).then(|| p + step))
+    }
+
+    /// The points `self + step`, `self + 2 * step`, ... without end.
+    pub fn ray(&self, step: Self) -> impl Iterator<Item = Self> + use<T> {
+        let start = *self;
+        std::iter::successors(Some(start + step), move |&p| Some(p + step))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.x.is_zero() && self.y.is_zero()
+    }
+
+    /// Compares the directions of this vector and `other` by the clockwise
+    /// angle from `heading`, in `[0, 360)`.  Vectors pointing the same way
+    /// are equal whatever their lengths, and the zero vector comes before
+    /// everything else.  Uses only exact integer arithmetic.
+    ///
+    /// Clockwise depends on which way y grows: with [`YAxis::Down`] it is
+    /// clockwise as the puzzle maps are drawn, so `(1, 0)` comes a quarter
+    /// turn after `(0, -1)`.
+    ///
+    /// Panics if `heading` is the zero vector, which points no way at all.
+    pub fn clockwise_cmp(&self, other: &Self, heading: Self, y_axis: YAxis) -> Ordering {
+        assert!(!heading.is_zero(), "clockwise_cmp needs a nonzero heading");
+        // Express everything in y-up terms, where clockwise turns have a
+        // negative cross product.
+        let orient = |v: Self| match y_axis {
+            YAxis::Up => v,
+            YAxis::Down => Point2D { x: v.x, y: -v.y },
+        };
+        let heading = orient(heading);
+        let half = |v: Self| {
+            if v.is_zero() {
+                return 0;
+            }
+            let cross = heading.cross(v);
+            if cross < T::zero() || (cross.is_zero() && heading.dot(v) > T::zero()) {
+                1
+            } else {
+                2
+            }
+        };
+        let (a, b) = (orient(*self), orient(*other));
+        half(a)
+            .cmp(&half(b))
+            .then_with(|| a.cross(b).cmp(&T::zero()))
+    }
+}
+
+impl<T: Copy> Point2D<T> {
+    /// The distance to `other` under metric `M`, for example
+    /// `a.distance::<Chebyshev>(b)`.
+    pub fn distance<M: Metric<T>>(&self, other: Self) -> M::Output {
+        M::distance(*self, other)
+    }
+}
+
+impl<T: CheckedAdd + CheckedSub> Point2D<T> {
+    /// Adds `other`, returning `None` if either coordinate overflows.
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(Point2D {
+            x: self.x.checked_add(&other.x)?,
+            y: self.y.checked_add(&other.y)?,
+        })
+    }
+
+    /// Subtracts `other`, returning `None` if either coordinate overflows.
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        Some(Point2D {
+            x: self.x.checked_sub(&other.x)?,
+            y: self.y.checked_sub(&other.y)?,
+        })
+    }
+}
+
+/// A way of measuring the distance between two points.
+pub trait Metric<T> {
+    type Output;
+
+    fn distance(a: Point2D<T>, b: Point2D<T>) -> Self::Output;
+}
+
+/// The number of orthogonal steps between two points: `|dx| + |dy|`.
+pub struct Manhattan;
+
+/// The number of orthogonal or diagonal steps between two points, as a
+/// chess king moves: `max(|dx|, |dy|)`.
+pub struct Chebyshev;
+
+/// `dx * dx + dy * dy`, which orders points by straight-line distance
+/// using exact integer arithmetic.
+pub struct SquaredEuclidean;
+
+/// The straight-line distance, as an `f64`.
+pub struct Euclidean;
+
+impl<T: Add<Output = T> + Sub<Output = T> + Ord + Copy> Metric<T> for Manhattan {
+    type Output = T;
+
+    fn distance(a: Point2D<T>, b: Point2D<T>) -> T {
+        abs_difference(a.x, b.x) + abs_difference(a.y, b.y)
+    }
+}
+
+impl<T: Sub<Output = T> + Ord + Copy> Metric<T> for Chebyshev {
+    type Output = T;
+
+    fn distance(a: Point2D<T>, b: Point2D<T>) -> T {
+        abs_difference(a.x, b.x).max(abs_difference(a.y, b.y))
+    }
+}
+
+impl<T: Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Ord + Copy> Metric<T>
+    for SquaredEuclidean
+{
+    type Output = T;
+
+    fn distance(a: Point2D<T>, b: Point2D<T>) -> T {
+        let dx = abs_difference(a.x, b.x);
+        let dy = abs_difference(a.y, b.y);
+        dx * dx + dy * dy
+    }
+}
+
+impl<T: Sub<Output = T> + Ord + Copy + ToPrimitive> Metric<T> for Euclidean {
+    type Output = f64;
+
+    fn distance(a: Point2D<T>, b: Point2D<T>) -> f64 {
+        let component = |a: T, b: T| {
+            abs_difference(a, b)
+                .to_f64()
+                .expect("coordinate difference is not representable as f64")
+        };
+        component(a.x, b.x).hypot(component(a.y, b.y))
+    }
+}
+
 // Notice that the implementation uses the associated type `Output`.
 impl<T: Add<Output = T>> Add for Point2D<T> {
     type Output = Self;
@@ -110,6 +396,30 @@ impl<T: Sub<Output = T>> Sub for Point2D<T> {
     }
 }
 
+/// Scales both coordinates by `scalar`.
+impl<T: Mul<Output = T> + Copy> Mul<T> for Point2D<T> {
+    type Output = Self;
+
+    fn mul(self, scalar: T) -> Self::Output {
+        Self {
+            x: self.x * scalar,
+            y: self.y * scalar,
+        }
+    }
+}
+
+/// Divides both coordinates by `scalar`, rounding as `T`'s division does.
+impl<T: Div<Output = T> + Copy> Div<T> for Point2D<T> {
+    type Output = Self;
+
+    fn div(self, scalar: T) -> Self::Output {
+        Self {
+            x: self.x / scalar,
+            y: self.y / scalar,
+        }
+    }
+}
+
 impl<T: fmt::Display> fmt::Display for Point2D<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "({}, {})", self.x, self.y)
@@ -122,6 +432,476 @@ impl<T: fmt::Display> fmt::Debug for Point2D<T> {
     }
 }
 
+/// A point or vector in three dimensions.
+#[derive(Default, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point3D<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+/// One of the axes of a [`Point3D`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+pub const AXES: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+impl<T> Point3D<T> {
+    pub fn new(x: T, y: T, z: T) -> Point3D<T> {
+        Point3D { x, y, z }
+    }
+}
+
+impl<T: Copy> Point3D<T> {
+    /// The component along `axis`.
+    pub fn axis(&self, axis: Axis) -> T {
+        match axis {
+            Axis::X => self.x,
+            Axis::Y => self.y,
+            Axis::Z => self.z,
+        }
+    }
+
+    /// The projection onto the xy plane.
+    pub fn xy(&self) -> Point2D<T> {
+        Point2D {
+            x: self.x,
+            y: self.y,
+        }
+    }
+
+    /// Applies `f` to each component.
+    pub fn map<U>(&self, mut f: impl FnMut(T) -> U) -> Point3D<U> {
+        Point3D {
+            x: f(self.x),
+            y: f(self.y),
+            z: f(self.z),
+        }
+    }
+}
+
+impl<T: Add<Output = T> + Sub<Output = T> + Ord + Copy> Point3D<T> {
+    pub fn manhattan_distance(&self, other: Self) -> T {
+        abs_difference(self.x, other.x)
+            + abs_difference(self.y, other.y)
+            + abs_difference(self.z, other.z)
+    }
+}
+
+impl<T: Signed + Copy> Point3D<T> {
+    /// The sign of each component: -1, 0 or 1.
+    pub fn signum(&self) -> Self {
+        self.map(|n| n.signum())
+    }
+}
+
+impl<T: Zero + One + Neg<Output = T> + Add<Output = T> + Copy> Point3D<T> {
+    /// The six points that differ from this one by one along a single axis.
+    pub fn cardinal_neighbors(&self) -> impl Iterator<Item = Self> + use<T> {
+        let center = *self;
+        Self::offsets()
+            .filter(|&(dx, dy, dz)| dx.abs() + dy.abs() + dz.abs() == 1)
+            .map(move |offset| center + Self::offset(offset))
+    }
+
+    /// The 26 points that differ from this one by at most one along each
+    /// axis.
+    pub fn neighbors(&self) -> impl Iterator<Item = Self> + use<T> {
+        let center = *self;
+        Self::offsets()
+            .filter(|&offset| offset != (0, 0, 0))
+            .map(move |offset| center + Self::offset(offset))
+    }
+
+    fn offsets() -> impl Iterator<Item = (i8, i8, i8)> {
+        (-1..=1).flat_map(|dz| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (dx, dy, dz))))
+    }
+
+    fn offset((dx, dy, dz): (i8, i8, i8)) -> Self {
+        let unit = |d: i8| match d {
+            -1 => -T::one(),
+            0 => T::zero(),
+            _ => T::one(),
+        };
+        Point3D::new(unit(dx), unit(dy), unit(dz))
+    }
+}
+
+impl<T: Add<Output = T>> Add for Point3D<T> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Point3D<T> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Self {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Point3D<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+/// Formats as `<x=1, y=2, z=3>`, the notation used by the puzzles.
+impl<T: fmt::Display> fmt::Display for Point3D<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<x={}, y={}, z={}>", self.x, self.y, self.z)
+    }
+}
+
+impl<T: fmt::Display> fmt::Debug for Point3D<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// Parses the `<x=1, y=2, z=3>` notation.  Whitespace around the
+/// components is ignored.
+impl<T: FromStr> FromStr for Point3D<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let inner = s
+            .trim()
+            .strip_prefix('<')
+            .and_then(|s| s.strip_suffix('>'))
+            .ok_or_else(|| anyhow::anyhow!("expected <x=.., y=.., z=..>: {:?}", s))?;
+        let mut components = inner.split(',').map(str::trim);
+        let mut component = |name: &str| -> anyhow::Result<T> {
+            let field = components.next().unwrap_or("");
+            let value = field
+                .strip_prefix(name)
+                .and_then(|field| field.strip_prefix('='))
+                .ok_or_else(|| anyhow::anyhow!("expected {}=.. in {:?}", name, s))?;
+            Ok(value.trim().parse()?)
+        };
+        let point = Point3D::new(component("x")?, component("y")?, component("z")?);
+        if components.next().is_some() {
+            anyhow::bail!("too many components in {:?}", s);
+        }
+        Ok(point)
+    }
+}
+
+/// A position and heading that moves by turning and stepping forward,
+/// recording every point it visits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turtle {
+    position: Point2D<i32>,
+    heading: CardinalDirection,
+    y_axis: YAxis,
+    path: Vec<Point2D<i32>>,
+}
+
+/// A command for [`Turtle::execute`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurtleCommand {
+    Forward(u32),
+    Turn(Turn),
+}
+
+impl Turtle {
+    /// Creates a turtle using the [`YAxis::Down`] convention.
+    pub fn new(position: Point2D<i32>, heading: CardinalDirection) -> Turtle {
+        Self::with_y_axis(position, heading, YAxis::Down)
+    }
+
+    pub fn with_y_axis(
+        position: Point2D<i32>,
+        heading: CardinalDirection,
+        y_axis: YAxis,
+    ) -> Turtle {
+        Turtle {
+            position,
+            heading,
+            y_axis,
+            path: vec![position],
+        }
+    }
+
+    pub fn position(&self) -> Point2D<i32> {
+        self.position
+    }
+
+    pub fn heading(&self) -> CardinalDirection {
+        self.heading
+    }
+
+    /// The point ahead of the turtle.
+    pub fn ahead(&self) -> Point2D<i32> {
+        self.position + self.heading.delta(self.y_axis)
+    }
+
+    /// Every point visited so far, starting with the initial position.
+    pub fn path(&self) -> &[Point2D<i32>] {
+        &self.path
+    }
+
+    pub fn turn(&mut self, turn: Turn) {
+        self.heading = self.heading.rotate(turn);
+    }
+
+    pub fn face(&mut self, heading: CardinalDirection) {
+        self.heading = heading;
+    }
+
+    /// Moves `steps` points forward, recording each one.
+    pub fn forward(&mut self, steps: u32) {
+        for _ in 0..steps {
+            self.position = self.ahead();
+            self.path.push(self.position);
+        }
+    }
+
+    pub fn execute(&mut self, command: TurtleCommand) {
+        match command {
+            TurtleCommand::Forward(steps) => self.forward(steps),
+            TurtleCommand::Turn(turn) => self.turn(turn),
+        }
+    }
+}
+
+/// An axis-aligned rectangle of lattice points, from `min` to `max`
+/// inclusive.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rect<T> {
+    pub min: Point2D<T>,
+    pub max: Point2D<T>,
+}
+
+impl<T: Add<Output = T> + Sub<Output = T> + Ord + Copy + Zero + One> Rect<T> {
+    /// Creates the rectangle with corners `a` and `b`, in any order.
+    pub fn new(a: Point2D<T>, b: Point2D<T>) -> Rect<T> {
+        Rect {
+            min: Point2D {
+                x: a.x.min(b.x),
+                y: a.y.min(b.y),
+            },
+            max: Point2D {
+                x: a.x.max(b.x),
+                y: a.y.max(b.y),
+            },
+        }
+    }
+
+    /// The smallest rectangle containing every point, or `None` if there
+    /// are none.
+    pub fn from_points<P: Borrow<Point2D<T>>>(
+        points: impl IntoIterator<Item = P>,
+    ) -> Option<Rect<T>> {
+        let mut points = points.into_iter();
+        let first = *points.next()?.borrow();
+        Some(points.fold(Rect::new(first, first), |rect, p| rect.include(*p.borrow())))
+    }
+
+    /// Whether the rectangle holds no points, which happens when `min` lies
+    /// beyond `max` on either axis.
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y
+    }
+
+    pub fn width(&self) -> T {
+        if self.is_empty() {
+            T::zero()
+        } else {
+            self.max.x - self.min.x + T::one()
+        }
+    }
+
+    pub fn height(&self) -> T {
+        if self.is_empty() {
+            T::zero()
+        } else {
+            self.max.y - self.min.y + T::one()
+        }
+    }
+
+    /// The number of points in the rectangle.
+    pub fn area(&self) -> T
+    where
+        T: Mul<Output = T>,
+    {
+        self.width() * self.height()
+    }
+
+    pub fn contains(&self, p: &Point2D<T>) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x) && (self.min.y..=self.max.y).contains(&p.y)
+    }
+
+    /// The points in both rectangles, or `None` if they do not overlap.
+    pub fn intersect(&self, other: &Rect<T>) -> Option<Rect<T>> {
+        let min = Point2D {
+            x: self.min.x.max(other.min.x),
+            y: self.min.y.max(other.min.y),
+        };
+        let max = Point2D {
+            x: self.max.x.min(other.max.x),
+            y: self.max.y.min(other.max.y),
+        };
+        (min.x <= max.x && min.y <= max.y).then_some(Rect { min, max })
+    }
+
+    /// The smallest rectangle containing both rectangles.  An empty
+    /// rectangle adds nothing to the other.
+    pub fn union(&self, other: &Rect<T>) -> Rect<T> {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        self.include(other.min).include(other.max)
+    }
+
+    /// The smallest rectangle containing this one and `p`, which is just `p`
+    /// if this one is empty.
+    pub fn include(&self, p: Point2D<T>) -> Rect<T> {
+        if self.is_empty() {
+            return Rect { min: p, max: p };
+        }
+        Rect::new(
+            Point2D {
+                x: self.min.x.min(p.x),
+                y: self.min.y.min(p.y),
+            },
+            Point2D {
+                x: self.max.x.max(p.x),
+                y: self.max.y.max(p.y),
+            },
+        )
+    }
+
+    /// Grows the rectangle by `dx` on the left and right and `dy` on the
+    /// top and bottom, or shrinks it for negative amounts.  Returns `None`
+    /// if it shrinks away to nothing.
+    pub fn expand(&self, dx: T, dy: T) -> Option<Rect<T>> {
+        let rect = Rect {
+            min: Point2D {
+                x: self.min.x - dx,
+                y: self.min.y - dy,
+            },
+            max: Point2D {
+                x: self.max.x + dx,
+                y: self.max.y + dy,
+            },
+        };
+        (!rect.is_empty()).then_some(rect)
+    }
+
+    /// Iterates over the points row by row, top to bottom.
+    pub fn points(&self) -> RectPoints<T> {
+        RectPoints {
+            rect: *self,
+            next: (!self.is_empty()).then_some(self.min),
+            column_major: false,
+        }
+    }
+
+    /// Iterates over the points column by column, left to right.
+    pub fn points_column_major(&self) -> RectPoints<T> {
+        RectPoints {
+            rect: *self,
+            next: (!self.is_empty()).then_some(self.min),
+            column_major: true,
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Debug for Rect<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..={}", self.min, self.max)
+    }
+}
+
+/// The iterator returned by [`Rect::points`] and
+/// [`Rect::points_column_major`].
+pub struct RectPoints<T> {
+    rect: Rect<T>,
+    next: Option<Point2D<T>>,
+    column_major: bool,
+}
+
+impl<T: Add<Output = T> + Ord + Copy + One> Iterator for RectPoints<T> {
+    type Item = Point2D<T>;
+
+    fn next(&mut self) -> Option<Point2D<T>> {
+        let p = self.next?;
+        let (min, max) = (self.rect.min, self.rect.max);
+        self.next = if self.column_major {
+            if p.y < max.y {
+                Some(Point2D {
+                    x: p.x,
+                    y: p.y + T::one(),
+                })
+            } else if p.x < max.x {
+                Some(Point2D {
+                    x: p.x + T::one(),
+                    y: min.y,
+                })
+            } else {
+                None
+            }
+        } else if p.x < max.x {
+            Some(Point2D {
+                x: p.x + T::one(),
+                y: p.y,
+            })
+        } else if p.y < max.y {
+            Some(Point2D {
+                x: min.x,
+                y: p.y + T::one(),
+            })
+        } else {
+            None
+        };
+        Some(p)
+    }
+}
+
+/// Reports the bounding rectangle of a collection of points.
+pub trait BoundingRect<T> {
+    fn bounding_rect(self) -> Option<Rect<T>>;
+}
+
+impl<T, P, I> BoundingRect<T> for I
+where
+    T: Add<Output = T> + Sub<Output = T> + Ord + Copy + Zero + One,
+    P: Borrow<Point2D<T>>,
+    I: IntoIterator<Item = P>,
+{
+    fn bounding_rect(self) -> Option<Rect<T>> {
+        Rect::from_points(self)
+    }
+}
+
 pub struct NeighborsIterator<T> {
     point: Point2D<T>,
     current_direction: i32,
@@ -232,3 +1012,1079 @@ impl<T: Add<Output = T> + Sub<Output = T> + From<i32> + Copy> Iterator
         Some(neighbor_point)
     }
 }
+
+/// Integer types usable as [`Grid`] coordinates.
+pub trait Coordinate: Copy + TryFrom<usize> + TryInto<usize> {}
+
+impl<T: Copy + TryFrom<usize> + TryInto<usize>> Coordinate for T {}
+
+/// A dense, rectangular grid of cells indexed by [`Point2D`], with (0, 0) at
+/// the top left and y increasing downward.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Creates a grid with every cell set to `fill`.
+    pub fn new(width: usize, height: usize, fill: T) -> Grid<T>
+    where
+        T: Clone,
+    {
+        Grid {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    /// Creates a grid from rows of equal length.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> anyhow::Result<Grid<T>> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.len());
+        if let Some(y) = rows.iter().position(|row| row.len() != width) {
+            anyhow::bail!("row {} has length {}; expected {}", y, rows[y].len(), width);
+        }
+        Ok(Grid {
+            width,
+            height,
+            cells: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    /// Parses a character map, one row per line, converting each character
+    /// with `cell`.  Empty lines at the start and end of `text` are ignored,
+    /// but every other line must have the same length.  Fails on a character
+    /// for which `cell` returns `None`.
+    pub fn parse(text: &str, mut cell: impl FnMut(char) -> Option<T>) -> anyhow::Result<Grid<T>> {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .collect();
+        let start = lines.iter().position(|line| !line.is_empty());
+        let end = lines.iter().rposition(|line| !line.is_empty());
+        let lines = match (start, end) {
+            (Some(start), Some(end)) => &lines[start..=end],
+            _ => &lines[..0],
+        };
+        let mut rows = Vec::new();
+        for (y, line) in lines.iter().enumerate() {
+            let mut row = Vec::new();
+            for (x, ch) in line.chars().enumerate() {
+                match cell(ch) {
+                    Some(value) => row.push(value),
+                    None => anyhow::bail!("invalid character {:?} at ({}, {})", ch, x, y),
+                }
+            }
+            rows.push(row);
+        }
+        Self::from_rows(rows)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index_of<C: Coordinate>(&self, p: Point2D<C>) -> Option<usize> {
+        let x: usize = p.x.try_into().ok()?;
+        let y: usize = p.y.try_into().ok()?;
+        (x < self.width && y < self.height).then_some(y * self.width + x)
+    }
+
+    fn point_at<C: Coordinate>(&self, index: usize) -> Point2D<C> {
+        let convert = |n: usize| {
+            C::try_from(n)
+                .ok()
+                .expect("grid size exceeds the coordinate type")
+        };
+        Point2D {
+            x: convert(index % self.width),
+            y: convert(index / self.width),
+        }
+    }
+
+    pub fn contains<C: Coordinate>(&self, p: Point2D<C>) -> bool {
+        self.index_of(p).is_some()
+    }
+
+    /// Returns the cell at `p`, or `None` if `p` is out of bounds.
+    pub fn get<C: Coordinate>(&self, p: Point2D<C>) -> Option<&T> {
+        self.index_of(p).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut<C: Coordinate>(&mut self, p: Point2D<C>) -> Option<&mut T> {
+        self.index_of(p).map(|i| &mut self.cells[i])
+    }
+
+    /// Sets the cell at `p`.  Panics if `p` is out of bounds.
+    pub fn set<C: Coordinate>(&mut self, p: Point2D<C>, value: T) {
+        let i = self.index_of(p).expect("point out of bounds");
+        self.cells[i] = value;
+    }
+
+    pub fn rows(&self) -> std::slice::ChunksExact<'_, T> {
+        self.cells.chunks_exact(self.width.max(1))
+    }
+
+    /// Iterates over every cell with its coordinates, in row-major order.
+    pub fn cells<C: Coordinate>(&self) -> impl Iterator<Item = (Point2D<C>, &T)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| (self.point_at(i), cell))
+    }
+
+    /// Returns the first cell, in row-major order, matching `predicate`.
+    pub fn find<C: Coordinate>(&self, predicate: impl FnMut(&T) -> bool) -> Option<Point2D<C>> {
+        self.cells
+            .iter()
+            .position(predicate)
+            .map(|i| self.point_at(i))
+    }
+
+    /// Returns every cell, in row-major order, matching `predicate`.
+    pub fn find_all<C: Coordinate>(
+        &self,
+        mut predicate: impl FnMut(&T) -> bool,
+    ) -> Vec<Point2D<C>> {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| predicate(cell))
+            .map(|(i, _)| self.point_at(i))
+            .collect()
+    }
+
+    /// The in-bounds points among the eight neighbors of `p`.
+    pub fn neighbors<C: Coordinate>(&self, p: Point2D<C>) -> impl Iterator<Item = Point2D<C>> + '_ {
+        const OFFSETS: [(isize, isize); 8] = [
+            (0, 1),
+            (0, -1),
+            (1, 0),
+            (-1, 0),
+            (1, 1),
+            (-1, -1),
+            (1, -1),
+            (-1, 1),
+        ];
+        self.offset_points(p, &OFFSETS)
+    }
+
+    /// The in-bounds points among the four cardinal neighbors of `p`.
+    pub fn cardinal_neighbors<C: Coordinate>(
+        &self,
+        p: Point2D<C>,
+    ) -> impl Iterator<Item = Point2D<C>> + '_ {
+        const OFFSETS: [(isize, isize); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+        self.offset_points(p, &OFFSETS)
+    }
+
+    fn offset_points<C: Coordinate>(
+        &self,
+        p: Point2D<C>,
+        offsets: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = Point2D<C>> + '_ {
+        let center = self.index_of(p).map(|i| (i % self.width, i / self.width));
+        offsets.iter().filter_map(move |&(dx, dy)| {
+            let (x, y) = center?;
+            let x = x.checked_add_signed(dx).filter(|&x| x < self.width)?;
+            let y = y.checked_add_signed(dy).filter(|&y| y < self.height)?;
+            Some(self.point_at(y * self.width + x))
+        })
+    }
+
+    /// Converts every cell with `f`.
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+
+    /// Renders the grid one row per line, converting each cell with `glyph`.
+    pub fn render(&self, mut glyph: impl FnMut(&T) -> char) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for row in self.rows() {
+            out.extend(row.iter().map(&mut glyph));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// The bounding rectangle of the cells matching `predicate`, or `None`
+    /// if no cell matches.
+    pub fn bounding_box<C: Coordinate + Add<Output = C> + Sub<Output = C> + Ord + Zero + One>(
+        &self,
+        mut predicate: impl FnMut(&T) -> bool,
+    ) -> Option<Rect<C>> {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| predicate(cell))
+            .map(|(i, _)| self.point_at::<C>(i))
+            .bounding_rect()
+    }
+
+    fn from_fn(width: usize, height: usize, mut f: impl FnMut(usize, usize) -> T) -> Grid<T> {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+}
+
+/// Transformations that build a new grid.
+impl<T: Clone> Grid<T> {
+    fn cell(&self, x: usize, y: usize) -> T {
+        self.cells[y * self.width + x].clone()
+    }
+
+    /// Rotates the grid a quarter turn clockwise (`Turn::Right`),
+    /// counterclockwise (`Turn::Left`) or half a turn (`Turn::Around`).
+    pub fn rotate(&self, turn: Turn) -> Grid<T> {
+        let (w, h) = (self.width, self.height);
+        match turn {
+            Turn::Right => Grid::from_fn(h, w, |x, y| self.cell(y, h - 1 - x)),
+            Turn::Left => Grid::from_fn(h, w, |x, y| self.cell(w - 1 - y, x)),
+            Turn::Around => Grid::from_fn(w, h, |x, y| self.cell(w - 1 - x, h - 1 - y)),
+        }
+    }
+
+    /// Mirrors the grid left to right.
+    pub fn flip_horizontal(&self) -> Grid<T> {
+        Grid::from_fn(self.width, self.height, |x, y| {
+            self.cell(self.width - 1 - x, y)
+        })
+    }
+
+    /// Mirrors the grid top to bottom.
+    pub fn flip_vertical(&self) -> Grid<T> {
+        Grid::from_fn(self.width, self.height, |x, y| {
+            self.cell(x, self.height - 1 - y)
+        })
+    }
+
+    /// Swaps rows and columns.
+    pub fn transpose(&self) -> Grid<T> {
+        Grid::from_fn(self.height, self.width, |x, y| self.cell(y, x))
+    }
+
+    /// Returns the part of the grid within `rect`.  Panics if `rect` is
+    /// not entirely within the grid.
+    pub fn crop<C: Coordinate>(&self, rect: Rect<C>) -> Grid<T> {
+        let corner = |p| {
+            self.index_of(p)
+                .map(|i| (i % self.width, i / self.width))
+                .expect("point out of bounds")
+        };
+        let (x0, y0) = corner(rect.min);
+        let (x1, y1) = corner(rect.max);
+        assert!(x0 <= x1 && y0 <= y1, "crop corners are out of order");
+        Grid::from_fn(x1 - x0 + 1, y1 - y0 + 1, |x, y| self.cell(x0 + x, y0 + y))
+    }
+}
+
+impl<T: PartialEq> Grid<T> {
+    /// Finds every placement of `pattern` that lies entirely within the
+    /// grid, in row-major order.  With `rotations`, the pattern is also
+    /// tried rotated by one, two and three quarter turns clockwise; rotations
+    /// that give the same pattern are only tried once.
+    pub fn find_pattern<C: Coordinate + Add<Output = C>>(
+        &self,
+        pattern: &Pattern<T>,
+        rotations: bool,
+    ) -> Vec<Placement<C>>
+    where
+        T: Clone,
+    {
+        let mut variants = vec![(0, pattern.clone())];
+        if rotations {
+            let mut rotated = pattern.clone();
+            for turns in 1..4 {
+                rotated = rotated.rotate(Turn::Right);
+                if variants.iter().all(|(_, variant)| *variant != rotated) {
+                    variants.push((turns, rotated.clone()));
+                }
+            }
+        }
+        let mut placements = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let origin = self.point_at::<C>(y * self.width + x);
+                for (turns, variant) in &variants {
+                    let fits =
+                        x + variant.width() <= self.width && y + variant.height() <= self.height;
+                    if fits && variant.matches_at(origin, |p| self.get(p)) {
+                        placements.push(Placement {
+                            position: origin,
+                            turns: *turns,
+                        });
+                    }
+                }
+            }
+        }
+        placements
+    }
+}
+
+/// A rectangular arrangement of cells to look for in a grid.  `None` cells
+/// match anything.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern<T> {
+    cells: Grid<Option<T>>,
+    // The indices of the cells that are not wildcards, so that a sparse
+    // pattern is matched without visiting the rest.
+    fixed: Vec<usize>,
+}
+
+/// Where [`Grid::find_pattern`] found a pattern: the grid point under the
+/// pattern's top-left cell, after rotating the pattern `turns` quarter turns
+/// clockwise.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Placement<C> {
+    pub position: Point2D<C>,
+    pub turns: u8,
+}
+
+impl<C: fmt::Display> fmt::Debug for Placement<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} turned {}", self.position, self.turns)
+    }
+}
+
+impl<T> Pattern<T> {
+    pub fn new(cells: Grid<Option<T>>) -> Pattern<T> {
+        let fixed = (0..cells.cells.len())
+            .filter(|&i| cells.cells[i].is_some())
+            .collect();
+        Pattern { cells, fixed }
+    }
+
+    /// Parses a pattern like [`Grid::parse`], with `wildcard` standing for
+    /// cells that match anything.
+    pub fn parse(
+        text: &str,
+        wildcard: char,
+        mut cell: impl FnMut(char) -> Option<T>,
+    ) -> anyhow::Result<Pattern<T>> {
+        let cells = Grid::parse(text, |ch| {
+            if ch == wildcard {
+                Some(None)
+            } else {
+                cell(ch).map(Some)
+            }
+        })?;
+        Ok(Pattern::new(cells))
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.height()
+    }
+
+    /// Returns true if the pattern matches with its top-left cell at
+    /// `origin`, looking up the cells it covers with `lookup`.  A cell for
+    /// which `lookup` returns `None`, such as one off the edge of a grid,
+    /// matches only a wildcard.  Only non-wildcard cells are visited, so
+    /// `lookup` can be expensive and a large pattern that is mostly
+    /// wildcards is cheap to match.
+    pub fn matches_at<C, B>(
+        &self,
+        origin: Point2D<C>,
+        mut lookup: impl FnMut(Point2D<C>) -> Option<B>,
+    ) -> bool
+    where
+        T: PartialEq,
+        C: Coordinate + Add<Output = C>,
+        B: Borrow<T>,
+    {
+        self.fixed.iter().all(|&i| {
+            let expected = self.cells.cells[i].as_ref().unwrap();
+            let offset = self.cells.point_at::<C>(i);
+            lookup(origin + offset).is_some_and(|actual| actual.borrow() == expected)
+        })
+    }
+
+    pub fn rotate(&self, turn: Turn) -> Pattern<T>
+    where
+        T: Clone,
+    {
+        Pattern::new(self.cells.rotate(turn))
+    }
+}
+
+impl<T, C: Coordinate> Index<Point2D<C>> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, p: Point2D<C>) -> &T {
+        self.get(p).expect("point out of bounds")
+    }
+}
+
+impl<T, C: Coordinate> IndexMut<Point2D<C>> for Grid<T> {
+    fn index_mut(&mut self, p: Point2D<C>) -> &mut T {
+        self.get_mut(p).expect("point out of bounds")
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.rows() {
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.rows()).finish()
+    }
+}
+
+/// An unbounded grid of cells keyed by `Point2D<i32>`.  Cells that were never
+/// set read as the default value.  The grid tracks the bounding box of the
+/// cells that were set, which is the region it iterates over and renders.
+#[derive(Clone, Debug)]
+pub struct SparseGrid<T> {
+    cells: HashMap<Point2D<i32>, T>,
+    default: T,
+    bounds: Option<Rect<i32>>,
+}
+
+impl<T> SparseGrid<T> {
+    pub fn new(default: T) -> SparseGrid<T> {
+        SparseGrid {
+            cells: HashMap::new(),
+            default,
+            bounds: None,
+        }
+    }
+
+    /// Returns the cell at `p`, or the default value if it was never set.
+    pub fn get(&self, p: Point2D<i32>) -> &T {
+        self.cells.get(&p).unwrap_or(&self.default)
+    }
+
+    /// Returns true if the cell at `p` has been set.
+    pub fn is_set(&self, p: Point2D<i32>) -> bool {
+        self.cells.contains_key(&p)
+    }
+
+    /// Sets the cell at `p`, growing the bounding box to include it.
+    pub fn set(&mut self, p: Point2D<i32>, value: T) {
+        self.bounds = Some(match self.bounds {
+            None => Rect::new(p, p),
+            Some(bounds) => bounds.include(p),
+        });
+        self.cells.insert(p, value);
+    }
+
+    /// The number of cells that have been set.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn default_value(&self) -> &T {
+        &self.default
+    }
+
+    /// The bounding box of the cells that have been set, or `None` if no
+    /// cell has been set.
+    pub fn bounds(&self) -> Option<Rect<i32>> {
+        self.bounds
+    }
+
+    /// Iterates over every point in the bounding box, in row-major order,
+    /// with the default value standing in for cells that were never set.
+    pub fn iter(&self) -> impl Iterator<Item = (Point2D<i32>, &T)> + '_ {
+        self.bounds
+            .into_iter()
+            .flat_map(|bounds| bounds.points())
+            .map(|p| (p, self.get(p)))
+    }
+
+    /// Renders the bounding box one row per line, converting each cell with
+    /// `glyph`.  Renders an empty string if no cell has been set.
+    pub fn render(&self, glyph: impl FnMut(&T) -> char) -> String {
+        match self.bounds {
+            Some(bounds) => self.render_region(bounds, glyph),
+            None => String::new(),
+        }
+    }
+
+    /// Renders `region`, whether or not it lies within the bounding box.
+    pub fn render_region(&self, region: Rect<i32>, mut glyph: impl FnMut(&T) -> char) -> String {
+        let mut out = String::new();
+        for y in region.min.y..=region.max.y {
+            out.extend((region.min.x..=region.max.x).map(|x| glyph(self.get(Point2D { x, y }))));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn size(&self) -> (usize, usize) {
+        match self.bounds {
+            Some(bounds) => (bounds.width() as usize, bounds.height() as usize),
+            None => (0, 0),
+        }
+    }
+
+    /// Writes the bounding box as a plain (text) PBM bitmap, in which cells
+    /// for which `ink` returns true are black.
+    pub fn write_pbm<W: io::Write>(
+        &self,
+        w: &mut W,
+        mut ink: impl FnMut(&T) -> bool,
+    ) -> io::Result<()> {
+        let (width, height) = self.size();
+        writeln!(w, "P1\n{} {}", width, height)?;
+        self.write_rows(w, |cell| u16::from(ink(cell)))
+    }
+
+    /// Writes the bounding box as a plain (text) PGM graymap, converting each
+    /// cell to a gray level from 0 (black) to `max_level` (white) with `level`.
+    pub fn write_pgm<W: io::Write>(
+        &self,
+        w: &mut W,
+        max_level: u16,
+        mut level: impl FnMut(&T) -> u16,
+    ) -> io::Result<()> {
+        assert!(max_level > 0, "max_level must be positive");
+        let (width, height) = self.size();
+        writeln!(w, "P2\n{} {}\n{}", width, height, max_level)?;
+        self.write_rows(w, |cell| level(cell).min(max_level))
+    }
+
+    fn write_rows<W: io::Write>(
+        &self,
+        w: &mut W,
+        mut value: impl FnMut(&T) -> u16,
+    ) -> io::Result<()> {
+        let Some(Rect { min, max }) = self.bounds else {
+            return Ok(());
+        };
+        for y in min.y..=max.y {
+            let row: Vec<String> = (min.x..=max.x)
+                .map(|x| value(self.get(Point2D { x, y })).to_string())
+                .collect();
+            writeln!(w, "{}", row.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    type Point = Point2D<i32>;
+
+    fn parse_bool_grid(text: &str) -> Grid<bool> {
+        Grid::parse(text, |ch| match ch {
+            '#' => Some(true),
+            '.' => Some(false),
+            _ => None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_grid_parse() {
+        let grid = parse_bool_grid("\n#..\n.#.\n\n");
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert!(grid[Point::new(0, 0)]);
+        assert!(!grid[Point::new(1, 0)]);
+        assert!(grid[Point::new(1, 1)]);
+        assert_eq!(grid.get(Point::new(3, 0)), None);
+        assert_eq!(grid.get(Point::new(-1, 0)), None);
+        assert_eq!(grid.render(|&b| if b { '#' } else { '.' }), "#..\n.#.\n");
+
+        let error = Grid::parse("#.\n.x", |ch| (ch != 'x').then_some(ch)).unwrap_err();
+        assert_eq!(error.to_string(), "invalid character 'x' at (1, 1)");
+        assert!(Grid::parse("#.\n.", Some).is_err());
+        assert_eq!(Grid::parse("\n", Some).unwrap().height(), 0);
+        // Lines of spaces are cells like any other.
+        let spaces = Grid::parse("\n  \n .\n  \n\n", Some).unwrap();
+        assert_eq!((spaces.width(), spaces.height()), (2, 3));
+        assert_eq!(spaces[Point::new(1, 1)], '.');
+    }
+
+    #[test]
+    fn test_grid_cells_and_find() {
+        let mut grid = Grid::parse("ab\ncd", Some).unwrap();
+        grid[Point::new(1, 1)] = 'z';
+        assert_eq!(grid.to_string(), "ab\ncz\n");
+        let cells: Vec<(Point, char)> = grid.cells().map(|(p, &ch)| (p, ch)).collect();
+        assert_eq!(
+            cells,
+            vec![
+                (Point::new(0, 0), 'a'),
+                (Point::new(1, 0), 'b'),
+                (Point::new(0, 1), 'c'),
+                (Point::new(1, 1), 'z'),
+            ]
+        );
+        assert_eq!(grid.find(|&ch| ch == 'c'), Some(Point::new(0, 1)));
+        assert_eq!(grid.find::<i32>(|&ch| ch == 'q'), None);
+        assert_eq!(
+            grid.find_all(|&ch| ch > 'a'),
+            vec![Point::new(1, 0), Point::new(0, 1), Point::new(1, 1)]
+        );
+        let rows: Vec<&[char]> = grid.rows().collect();
+        assert_eq!(rows, vec![&['a', 'b'][..], &['c', 'z'][..]]);
+    }
+
+    #[test]
+    fn test_grid_neighbors() {
+        let grid = Grid::new(3, 2, 0u8);
+        let mut corner: Vec<Point> = grid.neighbors(Point::new(0, 0)).collect();
+        corner.sort();
+        assert_eq!(
+            corner,
+            vec![Point::new(0, 1), Point::new(1, 0), Point::new(1, 1)]
+        );
+        assert_eq!(grid.cardinal_neighbors(Point::new(1, 1)).count(), 3);
+        assert_eq!(grid.neighbors(Point::new(1, 0)).count(), 5);
+        assert_eq!(grid.neighbors(Point::new(5, 5)).count(), 0);
+    }
+
+    #[test]
+    fn test_sparse_grid() {
+        let mut grid = SparseGrid::new('.');
+        assert_eq!(grid.bounds(), None);
+        assert_eq!(grid.render(|&ch| ch), "");
+        assert_eq!(grid.iter().count(), 0);
+
+        grid.set(Point::new(2, -1), 'a');
+        grid.set(Point::new(0, 1), 'b');
+        grid.set(Point::new(0, 1), 'c');
+        assert_eq!(grid.len(), 2);
+        assert_eq!(
+            grid.bounds(),
+            Some(Rect::new(Point::new(0, -1), Point::new(2, 1)))
+        );
+        assert_eq!(*grid.get(Point::new(0, 1)), 'c');
+        assert_eq!(*grid.get(Point::new(9, 9)), '.');
+        assert!(!grid.is_set(Point::new(1, 0)));
+        assert_eq!(grid.render(|&ch| ch), "..a\n...\nc..\n");
+        assert_eq!(
+            grid.render_region(Rect::new(Point::new(-1, 1), Point::new(0, 2)), |&ch| ch),
+            ".c\n..\n"
+        );
+        let cells: Vec<(Point, char)> = grid.iter().map(|(p, &ch)| (p, ch)).collect();
+        assert_eq!(cells.len(), 9);
+        assert_eq!(cells[0], (Point::new(0, -1), '.'));
+        assert_eq!(cells[2], (Point::new(2, -1), 'a'));
+        assert_eq!(cells[6], (Point::new(0, 1), 'c'));
+    }
+
+    #[test]
+    fn test_sparse_grid_images() {
+        let mut grid = SparseGrid::new(0u16);
+        grid.set(Point::new(-1, 0), 3);
+        grid.set(Point::new(1, 1), 9);
+        let mut pbm = Vec::new();
+        grid.write_pbm(&mut pbm, |&level| level > 0).unwrap();
+        assert_eq!(String::from_utf8(pbm).unwrap(), "P1\n3 2\n1 0 0\n0 0 1\n");
+        let mut pgm = Vec::new();
+        grid.write_pgm(&mut pgm, 5, |&level| level).unwrap();
+        assert_eq!(
+            String::from_utf8(pgm).unwrap(),
+            "P2\n3 2\n5\n3 0 0\n0 0 5\n"
+        );
+    }
+
+    #[test]
+    fn test_cardinal_direction_turns() {
+        use CardinalDirection::*;
+        for dir in CARDINAL_DIRECTIONS {
+            assert_eq!(dir.turn_left().turn_right(), dir);
+            assert_eq!(dir.rotate(Turn::Right).rotate(Turn::Right), dir.negate());
+            assert_eq!(dir.rotate(Turn::Around), dir.negate());
+        }
+        assert_eq!(North.turn_left(), West);
+        assert_eq!(North.turn_right(), East);
+        assert_eq!(North.delta::<i32>(YAxis::Down), Point::new(0, -1));
+        assert_eq!(North.delta::<i32>(YAxis::Up), Point::new(0, 1));
+        assert_eq!(West.delta::<i64>(YAxis::Up), Point2D::new(-1, 0));
+        assert_eq!(
+            Point::new(3, 3) + South.delta(YAxis::Down),
+            Point::new(3, 3).cardinal_neighbor(South)
+        );
+    }
+
+    #[test]
+    fn test_cardinal_direction_parse() {
+        use CardinalDirection::*;
+        let parsed: Vec<CardinalDirection> = "NSEWudlr^v<>"
+            .chars()
+            .map(|ch| CardinalDirection::try_from(ch).unwrap())
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                North, South, East, West, North, South, West, East, North, South, West, East
+            ]
+        );
+        assert_eq!("north".parse::<CardinalDirection>().unwrap(), North);
+        assert_eq!("West".parse::<CardinalDirection>().unwrap(), West);
+        assert_eq!(">".parse::<CardinalDirection>().unwrap(), East);
+        assert!("x".parse::<CardinalDirection>().is_err());
+        assert!("up".parse::<CardinalDirection>().is_err());
+        assert_eq!(South.arrow(), 'v');
+    }
+
+    #[test]
+    fn test_turtle() {
+        let mut turtle = Turtle::new(Point::new(0, 0), CardinalDirection::North);
+        turtle.forward(2);
+        turtle.execute(TurtleCommand::Turn(Turn::Right));
+        turtle.execute(TurtleCommand::Forward(1));
+        assert_eq!(turtle.position(), Point::new(1, -2));
+        assert_eq!(turtle.heading(), CardinalDirection::East);
+        assert_eq!(turtle.ahead(), Point::new(2, -2));
+        assert_eq!(
+            turtle.path(),
+            &[
+                Point::new(0, 0),
+                Point::new(0, -1),
+                Point::new(0, -2),
+                Point::new(1, -2)
+            ]
+        );
+
+        let mut turtle = Turtle::with_y_axis(Point::new(0, 0), CardinalDirection::North, YAxis::Up);
+        turtle.forward(1);
+        assert_eq!(turtle.position(), Point::new(0, 1));
+    }
+
+    #[test]
+    fn test_point3d() {
+        let a = Point3D::new(1, -2, 3);
+        let b = Point3D::new(-4, 0, 3);
+        assert_eq!(a + b, Point3D::new(-3, -2, 6));
+        assert_eq!(a - b, Point3D::new(5, -2, 0));
+        assert_eq!(-a, Point3D::new(-1, 2, -3));
+        assert_eq!(a.manhattan_distance(b), 7);
+        assert_eq!((b - a).signum(), Point3D::new(-1, 1, 0));
+        assert_eq!(a.axis(Axis::Y), -2);
+        assert_eq!(AXES.map(|axis| b.axis(axis)), [-4, 0, 3]);
+        assert_eq!(a.xy(), Point::new(1, -2));
+    }
+
+    #[test]
+    fn test_point3d_neighbors() {
+        let center = Point3D::new(0i64, 0, 0);
+        let mut faces: Vec<Point3D<i64>> = center.cardinal_neighbors().collect();
+        faces.sort();
+        assert_eq!(faces.len(), 6);
+        assert!(faces.iter().all(|p| p.manhattan_distance(center) == 1));
+        assert_eq!(faces[0], Point3D::new(-1, 0, 0));
+        let all: HashSet<Point3D<i64>> = center.neighbors().collect();
+        assert_eq!(all.len(), 26);
+        assert!(!all.contains(&center));
+        assert!(all.contains(&Point3D::new(-1, 1, -1)));
+    }
+
+    #[test]
+    fn test_point3d_parse() {
+        let p: Point3D<i32> = "<x=-1, y=0, z=2>".parse().unwrap();
+        assert_eq!(p, Point3D::new(-1, 0, 2));
+        assert_eq!(p.to_string(), "<x=-1, y=0, z=2>");
+        assert_eq!(
+            " <x=3,y=-10 , z= 7> ".parse::<Point3D<i64>>().unwrap(),
+            Point3D::new(3, -10, 7)
+        );
+        assert!("<x=1, y=2>".parse::<Point3D<i32>>().is_err());
+        assert!("<x=1, y=2, z=3, w=4>".parse::<Point3D<i32>>().is_err());
+        assert!("<x=1, z=2, y=3>".parse::<Point3D<i32>>().is_err());
+        assert!("x=1, y=2, z=3".parse::<Point3D<i32>>().is_err());
+        assert!("<x=a, y=2, z=3>".parse::<Point3D<i32>>().is_err());
+    }
+
+    #[test]
+    fn test_primitive_direction_and_lattice_points() {
+        assert_eq!(Point::new(6, -4).primitive_direction(), Point::new(3, -2));
+        assert_eq!(Point::new(0, -7).primitive_direction(), Point::new(0, -1));
+        assert_eq!(Point::new(0, 0).primitive_direction(), Point::new(0, 0));
+        let points: Vec<Point> = Point::new(1, 1).lattice_points(Point::new(7, -3)).collect();
+        assert_eq!(
+            points,
+            vec![Point::new(1, 1), Point::new(4, -1), Point::new(7, -3)]
+        );
+        assert_eq!(Point::new(2, 5).lattice_points(Point::new(2, 1)).count(), 5);
+        assert_eq!(
+            Point::new(2, 5)
+                .lattice_points(Point::new(2, 5))
+                .collect::<Vec<_>>(),
+            vec![Point::new(2, 5)]
+        );
+        let ray: Vec<Point> = Point::new(0, 0).ray(Point::new(1, 2)).take(2).collect();
+        assert_eq!(ray, vec![Point::new(1, 2), Point::new(2, 4)]);
+    }
+
+    #[test]
+    fn test_clockwise_cmp() {
+        let up = Point::new(0, -1);
+        let mut vectors = vec![
+            Point::new(-1, -1),
+            Point::new(-3, 0),
+            Point::new(0, 0),
+            Point::new(-2, 1),
+            Point::new(0, 5),
+            Point::new(2, 1),
+            Point::new(1, 0),
+            Point::new(2, -2),
+            Point::new(0, -4),
+            Point::new(1, -3),
+        ];
+        vectors.sort_by(|a, b| a.clockwise_cmp(b, up, YAxis::Down));
+        assert_eq!(
+            vectors,
+            vec![
+                Point::new(0, 0),
+                Point::new(0, -4),
+                Point::new(1, -3),
+                Point::new(2, -2),
+                Point::new(1, 0),
+                Point::new(2, 1),
+                Point::new(0, 5),
+                Point::new(-2, 1),
+                Point::new(-3, 0),
+                Point::new(-1, -1),
+            ]
+        );
+        assert_eq!(
+            Point::new(1, 1).clockwise_cmp(&Point::new(3, 3), up, YAxis::Down),
+            Ordering::Equal
+        );
+        // With y growing upward, (0, 1) is up and clockwise leads to (1, 0).
+        assert_eq!(
+            Point::new(1, 0).clockwise_cmp(&Point::new(-1, 0), Point::new(0, 1), YAxis::Up),
+            Ordering::Less
+        );
+        // Starting from a different heading.
+        assert_eq!(
+            Point::new(0, -1).clockwise_cmp(&Point::new(-1, 0), Point::new(1, 0), YAxis::Down),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "nonzero heading")]
+    fn test_clockwise_cmp_zero_heading() {
+        Point::new(1, 0).clockwise_cmp(&Point::new(0, 1), Point::new(0, 0), YAxis::Down);
+    }
+
+    #[test]
+    fn test_grid_transforms() {
+        let grid = Grid::parse("abc\ndef", Some).unwrap();
+        assert_eq!(grid.rotate(Turn::Right).to_string(), "da\neb\nfc\n");
+        assert_eq!(grid.rotate(Turn::Left).to_string(), "cf\nbe\nad\n");
+        assert_eq!(grid.rotate(Turn::Around).to_string(), "fed\ncba\n");
+        assert_eq!(grid.rotate(Turn::Right).rotate(Turn::Left), grid);
+        assert_eq!(grid.flip_horizontal().to_string(), "cba\nfed\n");
+        assert_eq!(grid.flip_vertical().to_string(), "def\nabc\n");
+        assert_eq!(grid.transpose().to_string(), "ad\nbe\ncf\n");
+        assert_eq!(
+            grid.crop(Rect::new(Point::new(1, 0), Point::new(2, 1)))
+                .to_string(),
+            "bc\nef\n"
+        );
+        assert_eq!(
+            grid.bounding_box(|&ch| ch == 'b' || ch == 'd'),
+            Some(Rect::new(Point::new(0, 0), Point::new(1, 1)))
+        );
+        assert_eq!(grid.bounding_box::<i32>(|&ch| ch == 'z'), None);
+    }
+
+    #[test]
+    fn test_find_pattern() {
+        let grid = parse_bool_grid("..#..\n.###.\n..#..\n#....\n##...");
+        let plus = Pattern::parse(".#.\n###\n.#.", '.', |ch| (ch == '#').then_some(true)).unwrap();
+        let found: Vec<Placement<i32>> = grid.find_pattern(&plus, true);
+        assert_eq!(
+            found,
+            vec![Placement {
+                position: Point::new(1, 0),
+                turns: 0
+            }]
+        );
+
+        let corner = Pattern::parse("#.\n##", '.', |ch| (ch == '#').then_some(true)).unwrap();
+        let positions = |placements: Vec<Placement<i32>>| -> Vec<(Point, u8)> {
+            placements.iter().map(|p| (p.position, p.turns)).collect()
+        };
+        assert_eq!(
+            positions(grid.find_pattern(&corner, false)),
+            vec![(Point::new(2, 0), 0), (Point::new(0, 3), 0)]
+        );
+        assert_eq!(
+            positions(grid.find_pattern(&corner, true)),
+            vec![
+                (Point::new(1, 0), 3),
+                (Point::new(2, 0), 0),
+                (Point::new(1, 1), 2),
+                (Point::new(2, 1), 1),
+                (Point::new(0, 3), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pattern_matches_at() {
+        let corners =
+            Pattern::new(Grid::from_rows(vec![vec![Some(1), None], vec![None, Some(4)]]).unwrap());
+        assert!(corners.matches_at(Point::new(3, 3), |p| Some(p.x - 2 + (p.y - 3) * 2)));
+        assert!(!corners.matches_at(Point::new(0, 0), |p| Some(p.x + p.y)));
+        assert!(!corners.matches_at(Point::new(0, 0), |_| None::<i32>));
+
+        let mut lookups = Vec::new();
+        corners.matches_at(Point::new(0, 0), |p: Point| {
+            lookups.push(p);
+            Some(1)
+        });
+        assert_eq!(lookups, vec![Point::new(0, 0), Point::new(1, 1)]);
+    }
+
+    #[test]
+    fn test_rect() {
+        let rect = Rect::new(Point::new(3, -1), Point::new(0, 1));
+        assert_eq!(rect.min, Point::new(0, -1));
+        assert_eq!((rect.width(), rect.height(), rect.area()), (4, 3, 12));
+        assert!(rect.contains(&Point::new(3, 0)));
+        assert!(!rect.contains(&Point::new(4, 0)));
+
+        let other = Rect::new(Point::new(2, 1), Point::new(5, 4));
+        assert_eq!(
+            rect.intersect(&other),
+            Some(Rect::new(Point::new(2, 1), Point::new(3, 1)))
+        );
+        assert_eq!(rect.intersect(&other.expand(0, -1).unwrap()), None);
+        assert_eq!(
+            rect.union(&other),
+            Rect::new(Point::new(0, -1), Point::new(5, 4))
+        );
+        assert_eq!(
+            rect.expand(1, 2),
+            Some(Rect::new(Point::new(-1, -3), Point::new(4, 3)))
+        );
+        assert_eq!(
+            rect.expand(-1, -1),
+            Some(Rect::new(Point::new(1, 0), Point::new(2, 0)))
+        );
+        assert_eq!(rect.expand(0, -2), None);
+
+        let small = Rect::new(Point::new(0, 0), Point::new(1, 2));
+        let rows: Vec<Point> = small.points().collect();
+        assert_eq!(rows.len(), 6);
+        assert_eq!(
+            &rows[..3],
+            &[Point::new(0, 0), Point::new(1, 0), Point::new(0, 1)]
+        );
+        let columns: Vec<Point> = small.points_column_major().collect();
+        assert_eq!(
+            &columns[..4],
+            &[
+                Point::new(0, 0),
+                Point::new(0, 1),
+                Point::new(0, 2),
+                Point::new(1, 0)
+            ]
+        );
+        assert_eq!(columns.last(), Some(&Point::new(1, 2)));
+    }
+
+    #[test]
+    fn test_inverted_rect() {
+        let inverted = Rect {
+            min: Point::new(2, 0),
+            max: Point::new(1, 3),
+        };
+        assert!(inverted.is_empty());
+        assert_eq!(
+            (inverted.width(), inverted.height(), inverted.area()),
+            (0, 0, 0)
+        );
+        assert_eq!(inverted.points().count(), 0);
+        assert_eq!(inverted.points_column_major().count(), 0);
+        assert!(!inverted.contains(&Point::new(2, 1)));
+
+        let unsigned: Rect<usize> = Rect {
+            min: Point2D::new(0, 5),
+            max: Point2D::new(3, 4),
+        };
+        assert_eq!(unsigned.height(), 0);
+        assert_eq!(unsigned.points().next(), None);
+        assert!(!Rect::new(Point::new(0, 0), Point::new(0, 0)).is_empty());
+
+        let rect = Rect::new(Point::new(5, 5), Point::new(6, 7));
+        assert_eq!(inverted.union(&rect), rect);
+        assert_eq!(rect.union(&inverted), rect);
+        assert!(inverted.union(&inverted).is_empty());
+        assert_eq!(
+            inverted.include(Point::new(9, 9)),
+            Rect::new(Point::new(9, 9), Point::new(9, 9))
+        );
+    }
+
+    #[test]
+    fn test_bounding_rect() {
+        let points = vec![Point::new(2, 5), Point::new(-1, 3), Point::new(0, 7)];
+        assert_eq!(
+            points.iter().bounding_rect(),
+            Some(Rect::new(Point::new(-1, 3), Point::new(2, 7)))
+        );
+        assert_eq!(Rect::from_points(&points), points.iter().bounding_rect());
+        let set: HashSet<Point> = points.into_iter().collect();
+        assert_eq!(set.bounding_rect().unwrap().area(), 20);
+        assert_eq!(Vec::<Point>::new().bounding_rect(), None);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let a = Point::new(1, -2);
+        let b = Point::new(-3, 1);
+        assert_eq!(a.distance::<Manhattan>(b), 7);
+        assert_eq!(a.distance::<Manhattan>(b), a.manhattan_distance(b));
+        assert_eq!(a.distance::<Chebyshev>(b), 4);
+        assert_eq!(a.distance::<SquaredEuclidean>(b), 25);
+        assert_eq!(a.distance::<Euclidean>(b), 5.0);
+    }
+
+    #[test]
+    fn test_checked_and_scalar_arithmetic() {
+        let p = Point::new(3, -4);
+        assert_eq!(p.checked_add(&Point::new(1, 1)), Some(Point::new(4, -3)));
+        assert_eq!(p.checked_add(&Point::new(i32::MAX, 0)), None);
+        assert_eq!(p.checked_sub(&Point::new(0, i32::MAX)), None);
+        assert_eq!(p.checked_sub(&p), Some(Point::new(0, 0)));
+        assert_eq!(Point2D::new(0u8, 1).checked_sub(&Point2D::new(1, 0)), None);
+        assert_eq!(p * 3, Point::new(9, -12));
+        assert_eq!(p / 2, Point::new(1, -2));
+    }
+}
@@ -0,0 +1,363 @@
+// Rooted trees built from parent-child pairs, such as day 6's orbit map.
+//
+// Every query a puzzle is likely to ask is answered from tables built once
+// up front: depths, subtree sizes and, for lowest common ancestors, each
+// node's ancestors at every power-of-two distance, so that many queries on
+// a large tree each take logarithmic time.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::hash::Hash;
+use std::io;
+
+use anyhow::{Result, bail};
+
+use crate::graph::format::dot_string;
+
+/// A rooted tree whose nodes are identified by keys of type `K`.
+#[derive(Debug, Clone)]
+pub struct Tree<K> {
+    keys: Vec<K>,
+    indices: HashMap<K, usize>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    depths: Vec<usize>,
+    subtree_sizes: Vec<usize>,
+    // ancestors[k][i] is the ancestor 2^k levels above node i, or the root
+    // if that is higher than the root.
+    ancestors: Vec<Vec<usize>>,
+    root: usize,
+}
+
+impl<K: Clone + Eq + Hash + fmt::Debug> Tree<K> {
+    /// Builds a tree from `(parent, child)` pairs.  Fails if a node has two
+    /// parents, if there is no single root, or if some nodes form a cycle.
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (K, K)>) -> Result<Tree<K>> {
+        let mut keys = Vec::new();
+        let mut indices = HashMap::new();
+        let mut parents = Vec::new();
+        let mut children = Vec::new();
+        let mut index_of =
+            |key: K, parents: &mut Vec<Option<usize>>, children: &mut Vec<Vec<usize>>| {
+                *indices.entry(key.clone()).or_insert_with(|| {
+                    keys.push(key);
+                    parents.push(None);
+                    children.push(Vec::new());
+                    parents.len() - 1
+                })
+            };
+        for (parent, child) in pairs {
+            let parent = index_of(parent, &mut parents, &mut children);
+            let child = index_of(child, &mut parents, &mut children);
+            if let Some(previous) = parents[child] {
+                bail!(
+                    "{:?} has two parents: {:?} and {:?}",
+                    keys[child],
+                    keys[previous],
+                    keys[parent]
+                );
+            }
+            parents[child] = Some(parent);
+            children[parent].push(child);
+        }
+
+        let roots: Vec<usize> = (0..keys.len()).filter(|&i| parents[i].is_none()).collect();
+        let root = match roots[..] {
+            [root] => root,
+            [] if keys.is_empty() => bail!("empty tree"),
+            [] => bail!("no root: every node has a parent, so there is a cycle"),
+            _ => bail!(
+                "several roots: {:?}",
+                roots.iter().map(|&i| &keys[i]).collect::<Vec<_>>()
+            ),
+        };
+
+        // Visit the nodes top down; any left unvisited hang off a cycle.
+        let mut order = vec![root];
+        let mut visited = vec![false; keys.len()];
+        visited[root] = true;
+        let mut depths = vec![0; keys.len()];
+        let mut next = 0;
+        while let Some(&node) = order.get(next) {
+            next += 1;
+            for &child in &children[node] {
+                depths[child] = depths[node] + 1;
+                visited[child] = true;
+                order.push(child);
+            }
+        }
+        if order.len() != keys.len() {
+            let cyclic = (0..keys.len()).find(|&i| !visited[i]).unwrap();
+            bail!("{:?} is part of a cycle", keys[cyclic]);
+        }
+
+        let mut subtree_sizes = vec![1; keys.len()];
+        for &node in order.iter().rev() {
+            if let Some(parent) = parents[node] {
+                subtree_sizes[parent] += subtree_sizes[node];
+            }
+        }
+
+        let mut ancestors = vec![
+            (0..keys.len())
+                .map(|i| parents[i].unwrap_or(root))
+                .collect::<Vec<_>>(),
+        ];
+        let max_depth = depths.iter().copied().max().unwrap_or(0);
+        while 1 << ancestors.len() <= max_depth {
+            let last = ancestors.last().unwrap();
+            let next = last.iter().map(|&i| last[i]).collect();
+            ancestors.push(next);
+        }
+
+        Ok(Tree {
+            keys,
+            indices,
+            parents,
+            children,
+            depths,
+            subtree_sizes,
+            ancestors,
+            root,
+        })
+    }
+}
+
+impl<K: Eq + Hash> Tree<K> {
+    pub fn root(&self) -> &K {
+        &self.keys[self.root]
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    /// Every node, parents before their children.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.preorder().map(|(i, _)| &self.keys[i])
+    }
+
+    pub fn parent(&self, key: &K) -> Option<&K> {
+        let parent = self.parents[*self.indices.get(key)?]?;
+        Some(&self.keys[parent])
+    }
+
+    /// The children of `key` in the order they were given; empty if `key`
+    /// is not in the tree.
+    pub fn children(&self, key: &K) -> impl Iterator<Item = &K> {
+        let children = self
+            .indices
+            .get(key)
+            .map_or(&[][..], |&i| &self.children[i]);
+        children.iter().map(|&child| &self.keys[child])
+    }
+
+    /// The number of edges between `key` and the root.
+    pub fn depth(&self, key: &K) -> Option<usize> {
+        Some(self.depths[*self.indices.get(key)?])
+    }
+
+    /// The number of nodes in the subtree rooted at `key`, including `key`.
+    pub fn subtree_size(&self, key: &K) -> Option<usize> {
+        Some(self.subtree_sizes[*self.indices.get(key)?])
+    }
+
+    /// The sum of the depths of all nodes: in an orbit map, the number of
+    /// direct and indirect orbits.
+    pub fn sum_of_depths(&self) -> usize {
+        self.depths.iter().sum()
+    }
+
+    /// The deepest node that is an ancestor of both `a` and `b`, where every
+    /// node counts as its own ancestor.
+    pub fn lowest_common_ancestor(&self, a: &K, b: &K) -> Option<&K> {
+        let lca = self.lca_index(*self.indices.get(a)?, *self.indices.get(b)?);
+        Some(&self.keys[lca])
+    }
+
+    /// The number of edges on the path between `a` and `b`.
+    pub fn distance(&self, a: &K, b: &K) -> Option<usize> {
+        let (a, b) = (*self.indices.get(a)?, *self.indices.get(b)?);
+        let lca = self.lca_index(a, b);
+        Some(self.depths[a] + self.depths[b] - 2 * self.depths[lca])
+    }
+
+    /// The nodes on the path from `a` up to their lowest common ancestor and
+    /// down to `b`, including both ends.
+    pub fn path(&self, a: &K, b: &K) -> Option<Vec<&K>> {
+        let (a, b) = (*self.indices.get(a)?, *self.indices.get(b)?);
+        let lca = self.lca_index(a, b);
+        let up_to = |mut node: usize| {
+            let mut nodes = vec![node];
+            while node != lca {
+                node = self.parents[node].unwrap();
+                nodes.push(node);
+            }
+            nodes
+        };
+        let mut path = up_to(a);
+        let mut down = up_to(b);
+        down.pop();
+        path.extend(down.into_iter().rev());
+        Some(path.into_iter().map(|i| &self.keys[i]).collect())
+    }
+
+    fn ancestor(&self, mut node: usize, levels: usize) -> usize {
+        for (k, table) in self.ancestors.iter().enumerate() {
+            if levels & (1 << k) != 0 {
+                node = table[node];
+            }
+        }
+        node
+    }
+
+    fn lca_index(&self, a: usize, b: usize) -> usize {
+        let (mut a, mut b) = if self.depths[a] >= self.depths[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        a = self.ancestor(a, self.depths[a] - self.depths[b]);
+        if a == b {
+            return a;
+        }
+        for table in self.ancestors.iter().rev() {
+            if table[a] != table[b] {
+                a = table[a];
+                b = table[b];
+            }
+        }
+        self.parents[a].unwrap()
+    }
+
+    // Every node with its depth, depth first with parents before children.
+    fn preorder(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut stack = vec![self.root];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(self.children[node].iter().rev());
+            Some((node, self.depths[node]))
+        })
+    }
+}
+
+impl<K: Eq + Hash + fmt::Display> Tree<K> {
+    /// Renders the tree as text, one node per line, indented by depth.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for (node, depth) in self.preorder() {
+            writeln!(
+                text,
+                "{:indent$}{}",
+                "",
+                self.keys[node],
+                indent = 2 * depth
+            )
+            .unwrap();
+        }
+        text
+    }
+
+    /// Writes the tree as a Graphviz `digraph` with edges from parents to
+    /// children.
+    pub fn write_dot(&self, w: &mut impl io::Write) -> io::Result<()> {
+        writeln!(w, "digraph {{")?;
+        for (node, _) in self.preorder() {
+            for &child in &self.children[node] {
+                writeln!(
+                    w,
+                    "    {} -> {};",
+                    dot_string(&self.keys[node].to_string()),
+                    dot_string(&self.keys[child].to_string())
+                )?;
+            }
+        }
+        writeln!(w, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //         G - H       J - K - L
+    //        /           /
+    // COM - B - C - D - E - F
+    //                \
+    //                 I
+    const ORBITS: &str = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L";
+
+    fn orbits(text: &str) -> Result<Tree<&str>> {
+        Tree::from_pairs(text.lines().map(|line| line.split_once(')').unwrap()))
+    }
+
+    #[test]
+    fn test_structure() {
+        let tree = orbits(ORBITS).unwrap();
+        assert_eq!(*tree.root(), "COM");
+        assert_eq!(tree.len(), 12);
+        assert!(tree.contains(&"L") && !tree.contains(&"X"));
+        assert_eq!(tree.parent(&"D"), Some(&"C"));
+        assert_eq!(tree.parent(&"COM"), None);
+        assert_eq!(tree.children(&"D").collect::<Vec<_>>(), vec![&"E", &"I"]);
+        assert_eq!(tree.children(&"X").count(), 0);
+        assert_eq!(tree.depth(&"L"), Some(7));
+        assert_eq!(tree.depth(&"X"), None);
+        assert_eq!(tree.subtree_size(&"COM"), Some(12));
+        assert_eq!(tree.subtree_size(&"E"), Some(5));
+        assert_eq!(tree.sum_of_depths(), 42);
+        assert_eq!(tree.keys().count(), 12);
+    }
+
+    #[test]
+    fn test_ancestors_and_paths() {
+        let tree = orbits(ORBITS).unwrap();
+        let lca = |a, b| tree.lowest_common_ancestor(&a, &b).copied();
+        assert_eq!(lca("L", "I"), Some("D"));
+        assert_eq!(lca("H", "F"), Some("B"));
+        assert_eq!(lca("E", "K"), Some("E"));
+        assert_eq!(lca("COM", "COM"), Some("COM"));
+        assert_eq!(lca("L", "X"), None);
+        assert_eq!(tree.distance(&"L", &"I"), Some(5));
+        assert_eq!(tree.distance(&"H", &"H"), Some(0));
+        assert_eq!(
+            tree.path(&"K", &"I").unwrap(),
+            vec![&"K", &"J", &"E", &"D", &"I"]
+        );
+        assert_eq!(tree.path(&"C", &"F").unwrap(), vec![&"C", &"D", &"E", &"F"]);
+        assert_eq!(tree.path(&"G", &"G").unwrap(), vec![&"G"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text| orbits(text).unwrap_err().to_string();
+        assert_eq!(error("A)B\nC)B"), "\"B\" has two parents: \"A\" and \"C\"");
+        assert_eq!(error("A)B\nC)D"), "several roots: [\"A\", \"C\"]");
+        assert_eq!(
+            error("A)B\nB)A"),
+            "no root: every node has a parent, so there is a cycle"
+        );
+        assert_eq!(error("A)B\nC)D\nD)C"), "\"C\" is part of a cycle");
+        assert!(Tree::<&str>::from_pairs([]).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let tree = orbits("COM)B\nB)C\nCOM)D").unwrap();
+        assert_eq!(tree.render(), "COM\n  B\n    C\n  D\n");
+        let mut dot = Vec::new();
+        tree.write_dot(&mut dot).unwrap();
+        assert_eq!(
+            String::from_utf8(dot).unwrap(),
+            "digraph {\n    \"COM\" -> \"B\";\n    \"COM\" -> \"D\";\n    \"B\" -> \"C\";\n}\n"
+        );
+    }
+}